@@ -0,0 +1,17 @@
+use {
+    crate::{function::NativeFunction, value::Value},
+    std::time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Native functions defined in the global scope of every interpreter.
+pub(crate) fn natives<'a>() -> Vec<NativeFunction<'a>> {
+    vec![NativeFunction::new("clock", 0, |_| clock())]
+}
+
+/// Number of seconds since the Unix epoch, with sub-second precision.
+fn clock<'a>() -> Value<'a> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is after the Unix epoch");
+    Value::Number(elapsed.as_secs_f64())
+}
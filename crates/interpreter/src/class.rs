@@ -0,0 +1,127 @@
+use {
+    crate::{
+        function::{Callable, LoxFunction},
+        interpreter::Interpreter,
+        value::Value,
+        Result,
+    },
+    core::fmt,
+    std::{cell::RefCell, collections::HashMap, rc::Rc},
+};
+
+/// Class declared in a Lox program.
+#[derive(Debug)]
+pub struct LoxClass<'a> {
+    name: &'a str,
+    superclass: Option<Rc<LoxClass<'a>>>,
+    methods: HashMap<&'a str, Rc<LoxFunction<'a>>>,
+}
+
+impl<'a> LoxClass<'a> {
+    pub fn new(
+        name: &'a str,
+        superclass: Option<Rc<LoxClass<'a>>>,
+        methods: HashMap<&'a str, Rc<LoxFunction<'a>>>,
+    ) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    /// Returns the method with the given name, unbound, looking it up in the
+    /// superclasses if the class doesn't define it.
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction<'a>>> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+
+    /// Name of the class, as declared.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+}
+
+/// Calling a class constructs a new instance of it, initialized by the `init`
+/// method (if any) with the arguments of the call.
+impl<'a> Callable<'a> for Rc<LoxClass<'a>> {
+    fn arity(&self) -> usize {
+        self.find_method("init")
+            .map_or(0, |initializer| initializer.arity())
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: Vec<Value<'a>>,
+    ) -> Result<Value<'a>> {
+        let instance = Rc::new(LoxInstance::new(self.clone()));
+        if let Some(initializer) = self.find_method("init") {
+            initializer
+                .bind(instance.clone(), interpreter)
+                .call(interpreter, arguments)?;
+        }
+        Ok(Value::Instance(instance))
+    }
+}
+
+/// Instance of a class, with its own set of fields.
+pub struct LoxInstance<'a> {
+    class: Rc<LoxClass<'a>>,
+
+    /// Fields are created on first assignment, and can be mutated through any
+    /// reference to the instance.
+    fields: RefCell<HashMap<&'a str, Value<'a>>>,
+}
+
+impl<'a> LoxInstance<'a> {
+    pub fn new(class: Rc<LoxClass<'a>>) -> Self {
+        Self {
+            class,
+            fields: RefCell::default(),
+        }
+    }
+
+    /// Class the instance was constructed from.
+    pub fn class(&self) -> &Rc<LoxClass<'a>> {
+        &self.class
+    }
+
+    /// Returns the value of a property, if defined.
+    ///
+    /// Fields shadow methods. Methods are bound to the instance, so they can be
+    /// called later on, e.g. after being stored in a variable.
+    pub fn get(
+        self: &Rc<Self>,
+        name: &str,
+        interpreter: &mut Interpreter<'a>,
+    ) -> Option<Value<'a>> {
+        if let Some(value) = self.fields.borrow().get(name) {
+            return Some(value.clone());
+        }
+        let method = self.class.find_method(name)?;
+        Some(Value::Function(Rc::new(
+            method.bind(self.clone(), interpreter),
+        )))
+    }
+
+    /// Sets the value of a field, defining it if needed.
+    ///
+    /// Returns the previous value of the field, if it was already defined.
+    pub fn set(&self, name: &'a str, value: Value<'a>) -> Option<Value<'a>> {
+        self.fields.borrow_mut().insert(name, value)
+    }
+}
+
+// Fields may (indirectly) refer to the instance itself, so only the class is
+// printed.
+impl fmt::Debug for LoxInstance<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoxInstance")
+            .field("class", &self.class.name())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,93 @@
+use {
+    crate::value::Value,
+    std::{cell::RefCell, collections::HashMap, rc::Rc},
+};
+
+/// Storage of variables and their values, for a single scope.
+///
+/// Scopes are shared, as functions keep the scope they are declared in alive
+/// for as long as they are.
+#[derive(Debug, Default)]
+pub struct Environment<'a> {
+    values: HashMap<&'a str, Value<'a>>,
+
+    /// Environment of the enclosing scope, `None` for the global scope.
+    enclosing: Option<Rc<RefCell<Environment<'a>>>>,
+}
+
+impl<'a> Environment<'a> {
+    /// Creates an empty global environment.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty environment nested in the given one.
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment<'a>>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    /// Defines a variable in this scope, replacing any previous definition with
+    /// that name.
+    pub fn define(&mut self, name: &'a str, value: Value<'a>) {
+        self.values.insert(name, value);
+    }
+
+    /// Returns the value of a variable, looking it up from the innermost scope
+    /// outwards.
+    pub fn get(&self, name: &str) -> Option<Value<'a>> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.enclosing.as_ref()?.borrow().get(name),
+        }
+    }
+
+    /// Returns the variables defined in this scope, in no particular order.
+    pub fn bindings(&self) -> impl Iterator<Item = (&'a str, &Value<'a>)> + '_ {
+        self.values.iter().map(|(name, value)| (*name, value))
+    }
+
+    /// Assigns a new value to an existing variable, in the innermost scope that
+    /// defines it.
+    ///
+    /// Returns `false` if the variable is not defined.
+    pub fn assign(&mut self, name: &str, value: Value<'a>) -> bool {
+        match self.values.get_mut(name) {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => match &self.enclosing {
+                Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+                None => false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn scopes() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define("a", Value::Number(1.0));
+        globals.borrow_mut().define("b", Value::Number(2.0));
+
+        let mut local = Environment::with_enclosing(globals.clone());
+        local.define("a", Value::Boolean(true));
+        assert_eq!(local.get("a"), Some(Value::Boolean(true)));
+        assert_eq!(local.get("b"), Some(Value::Number(2.0)));
+        assert_eq!(local.get("c"), None);
+
+        assert!(local.assign("b", Value::Nil));
+        assert!(!local.assign("c", Value::Nil));
+
+        let globals = globals.borrow();
+        assert_eq!(globals.get("a"), Some(Value::Number(1.0)));
+        assert_eq!(globals.get("b"), Some(Value::Nil));
+    }
+}
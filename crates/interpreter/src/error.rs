@@ -0,0 +1,184 @@
+use {
+    miette::{Diagnostic, SourceSpan},
+    thiserror::Error,
+};
+
+/// Runtime error of the interpreter.
+#[derive(Error, Diagnostic, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Operand must be a number")]
+    #[diagnostic(code(interpreter::operand_must_be_number))]
+    OperandMustBeNumber {
+        #[source_code]
+        src: String,
+        #[label("this is {found}")]
+        at: SourceSpan,
+        found: &'static str,
+    },
+
+    #[error("Operands must be numbers")]
+    #[diagnostic(code(interpreter::operands_must_be_numbers))]
+    OperandsMustBeNumbers {
+        #[source_code]
+        src: String,
+        #[label("this is {left_type}")]
+        left: SourceSpan,
+        left_type: &'static str,
+        #[label("this is {right_type}")]
+        right: SourceSpan,
+        right_type: &'static str,
+    },
+
+    #[error("Operands must be two numbers or two strings")]
+    #[diagnostic(code(interpreter::operands_must_be_numbers_or_strings))]
+    OperandsMustBeNumbersOrStrings {
+        #[source_code]
+        src: String,
+        #[label("this is {left_type}")]
+        left: SourceSpan,
+        left_type: &'static str,
+        #[label("this is {right_type}")]
+        right: SourceSpan,
+        right_type: &'static str,
+    },
+
+    #[error("Division by zero")]
+    #[diagnostic(code(interpreter::division_by_zero))]
+    DivisionByZero {
+        #[source_code]
+        src: String,
+        #[label("this is zero")]
+        at: SourceSpan,
+    },
+
+    #[error("Undefined variable '{name}'")]
+    #[diagnostic(code(interpreter::undefined_variable))]
+    UndefinedVariable {
+        #[source_code]
+        src: String,
+        #[label("not defined")]
+        at: SourceSpan,
+        name: String,
+    },
+
+    #[error("Can only call functions and classes")]
+    #[diagnostic(code(interpreter::not_callable))]
+    NotCallable {
+        #[source_code]
+        src: String,
+        #[label("this is {found}")]
+        at: SourceSpan,
+        found: &'static str,
+    },
+
+    #[error("Expected {expected} arguments but got {found}")]
+    #[diagnostic(code(interpreter::arity_mismatch))]
+    ArityMismatch {
+        #[source_code]
+        src: String,
+        #[label("called with {found} arguments")]
+        at: SourceSpan,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("Stack overflow: maximum call depth {max_depth} exceeded")]
+    #[diagnostic(code(interpreter::stack_overflow))]
+    StackOverflow {
+        #[source_code]
+        src: String,
+        #[label("call exceeding the limit")]
+        at: SourceSpan,
+        max_depth: usize,
+    },
+
+    #[error("Stack overflow: more than {limit} bytes of native stack in use")]
+    #[diagnostic(code(interpreter::stack_exhausted))]
+    StackExhausted {
+        #[source_code]
+        src: String,
+        #[label("out of stack here")]
+        at: SourceSpan,
+        limit: usize,
+    },
+
+    #[error("Interrupted")]
+    #[diagnostic(code(interpreter::interrupted))]
+    Interrupted {
+        #[source_code]
+        src: String,
+        #[label("interrupted here")]
+        at: SourceSpan,
+    },
+
+    #[error("Failed to write output: {reason}")]
+    #[diagnostic(code(interpreter::output))]
+    Output {
+        #[source_code]
+        src: String,
+        #[label("while printing this")]
+        at: SourceSpan,
+        reason: String,
+    },
+
+    #[error("Execution budget of {limit} steps exceeded")]
+    #[diagnostic(code(interpreter::budget_exceeded))]
+    BudgetExceeded {
+        #[source_code]
+        src: String,
+        #[label("out of steps here")]
+        at: SourceSpan,
+        limit: u64,
+    },
+
+    #[error("Out of memory: more than {limit} bytes in use")]
+    #[diagnostic(code(interpreter::out_of_memory))]
+    OutOfMemory {
+        #[source_code]
+        src: String,
+        #[label("allocated here")]
+        at: SourceSpan,
+        limit: usize,
+    },
+
+    #[error("Only instances have properties")]
+    #[diagnostic(code(interpreter::not_an_instance))]
+    NotAnInstance {
+        #[source_code]
+        src: String,
+        #[label("this is {found}")]
+        at: SourceSpan,
+        found: &'static str,
+    },
+
+    #[error("Undefined property '{name}'")]
+    #[diagnostic(code(interpreter::undefined_property))]
+    UndefinedProperty {
+        #[source_code]
+        src: String,
+        #[label("not defined on this instance")]
+        at: SourceSpan,
+        name: String,
+    },
+
+    #[error("Invalid assignment target")]
+    #[diagnostic(code(interpreter::invalid_assignment_target))]
+    InvalidAssignmentTarget {
+        #[source_code]
+        src: String,
+        #[label("can't assign to this")]
+        at: SourceSpan,
+    },
+
+    #[error("Superclass must be a class")]
+    #[diagnostic(code(interpreter::superclass_must_be_class))]
+    SuperclassMustBeClass {
+        #[source_code]
+        src: String,
+        #[label("this is {found}")]
+        at: SourceSpan,
+        found: &'static str,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
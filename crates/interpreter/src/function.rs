@@ -0,0 +1,178 @@
+use {
+    crate::{
+        class::LoxInstance,
+        environment::Environment,
+        interpreter::{Flow, Interpreter},
+        value::Value,
+        Result,
+    },
+    core::fmt,
+    parser::grammar::Function,
+    std::{cell::RefCell, rc::Rc},
+};
+
+/// Value that can be called with a list of arguments.
+pub trait Callable<'a> {
+    /// Number of arguments the callable expects.
+    fn arity(&self) -> usize;
+
+    /// Calls the callable, the number of `arguments` being already checked
+    /// against `arity()`.
+    fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: Vec<Value<'a>>,
+    ) -> Result<Value<'a>>;
+}
+
+/// Function declared in a Lox program.
+pub struct LoxFunction<'a> {
+    declaration: Function<'a>,
+
+    /// Environment of the scope the function is declared in, enclosing the
+    /// scope of every call.
+    closure: Rc<RefCell<Environment<'a>>>,
+
+    /// Source code of the declaration, for diagnostics raised in the body.
+    src: &'a str,
+
+    /// Whether the function is the `init` method of a class, which always
+    /// returns `this`.
+    is_initializer: bool,
+}
+
+impl<'a> LoxFunction<'a> {
+    pub fn new(
+        declaration: Function<'a>,
+        closure: Rc<RefCell<Environment<'a>>>,
+        src: &'a str,
+        is_initializer: bool,
+    ) -> Self {
+        Self {
+            declaration,
+            closure,
+            src,
+            is_initializer,
+        }
+    }
+
+    /// Returns a copy of the method with `this` bound to the given instance.
+    pub fn bind(&self, instance: Rc<LoxInstance<'a>>, interpreter: &mut Interpreter<'a>) -> Self {
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+        environment.define("this", Value::Instance(instance));
+        Self::new(
+            self.declaration,
+            interpreter.scope(environment),
+            self.src,
+            self.is_initializer,
+        )
+    }
+
+    /// Name of the function, as declared.
+    pub fn name(&self) -> &'a str {
+        self.declaration.name.lexeme
+    }
+
+    /// Executes the body of the function, up to its return or tail call.
+    fn execute(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: Vec<Value<'a>>,
+    ) -> Result<Flow<'a>> {
+        // Every call gets its own scope, so recursive calls don't share locals.
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+        for (param, argument) in self.declaration.params.iter().zip(arguments) {
+            environment.define(param.lexeme, argument);
+        }
+        let flow = interpreter.execute_body(self.src, self.declaration.body, environment)?;
+        if !self.is_initializer {
+            return Ok(flow);
+        }
+        if let Flow::TailCall(function, arguments) = flow {
+            function.call(interpreter, arguments)?;
+        }
+        // Bound initializers find `this` in their closure, whatever they return.
+        let this = self.closure.borrow().get("this").unwrap_or(Value::Nil);
+        Ok(Flow::Return(this))
+    }
+}
+
+impl<'a> Callable<'a> for LoxFunction<'a> {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: Vec<Value<'a>>,
+    ) -> Result<Value<'a>> {
+        // Tail calls of the body are made here, in a loop, so that they don't
+        // grow the stack.
+        let mut flow = self.execute(interpreter, arguments)?;
+        loop {
+            match flow {
+                Flow::Normal => return Ok(Value::Nil),
+                Flow::Return(value) => return Ok(value),
+                Flow::TailCall(function, arguments) => {
+                    flow = function.execute(interpreter, arguments)?;
+                }
+            }
+        }
+    }
+}
+
+// The closure may (indirectly) contain the function itself, so only the name
+// is printed.
+impl fmt::Debug for LoxFunction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoxFunction")
+            .field("name", &self.name())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Function implemented in Rust, callable from Lox programs.
+pub struct NativeFunction<'a> {
+    name: &'static str,
+    arity: usize,
+    function: Box<dyn Fn(Vec<Value<'a>>) -> Value<'a> + 'a>,
+}
+
+impl<'a> NativeFunction<'a> {
+    pub fn new(
+        name: &'static str,
+        arity: usize,
+        function: impl Fn(Vec<Value<'a>>) -> Value<'a> + 'a,
+    ) -> Self {
+        Self {
+            name,
+            arity,
+            function: Box::new(function),
+        }
+    }
+
+    /// Name the function is defined with, in the global scope.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<'a> Callable<'a> for NativeFunction<'a> {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(&self, _: &mut Interpreter<'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>> {
+        Ok((self.function)(arguments))
+    }
+}
+
+impl fmt::Debug for NativeFunction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
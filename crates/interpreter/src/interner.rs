@@ -0,0 +1,72 @@
+use std::{collections::HashSet, rc::Rc};
+
+/// Minimum number of strings before the interner is purged.
+const MIN_PURGE_LEN: usize = 64;
+
+/// Set of the strings of a program, so that equal strings share a single
+/// allocation, and are compared by pointer.
+#[derive(Debug, Default)]
+pub(crate) struct Interner {
+    strings: HashSet<Rc<str>>,
+
+    /// Number of strings left by the last purge. Purging again only once that
+    /// has doubled keeps interning amortized constant time.
+    purged_len: usize,
+}
+
+impl Interner {
+    /// Returns the shared copy of the string, and whether it was just added.
+    pub fn intern(&mut self, string: &str) -> (Rc<str>, bool) {
+        if let Some(interned) = self.strings.get(string) {
+            return (interned.clone(), false);
+        }
+        if self.strings.len() >= 2 * self.purged_len.max(MIN_PURGE_LEN) {
+            self.purge();
+        }
+        let interned: Rc<str> = string.into();
+        self.strings.insert(interned.clone());
+        (interned, true)
+    }
+
+    /// Returns the number of strings the program still refers to.
+    pub fn live(&mut self) -> usize {
+        self.purge();
+        self.strings.len()
+    }
+
+    /// Whether the string is only referenced by the interner.
+    pub fn is_unused(string: &Rc<str>) -> bool {
+        Rc::strong_count(string) <= 1
+    }
+
+    /// Drops the strings the program no longer refers to.
+    fn purge(&mut self) {
+        self.strings.retain(|string| !Self::is_unused(string));
+        self.purged_len = self.strings.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn interning() {
+        let mut interner = Interner::default();
+        let (a, added) = interner.intern("a");
+        assert!(added);
+        let (again, added) = interner.intern("a");
+        assert!(!added);
+        assert!(Rc::ptr_eq(&a, &again));
+
+        // Unused strings are dropped once the interner has grown enough.
+        for i in 1..2 * MIN_PURGE_LEN {
+            interner.intern(&i.to_string());
+        }
+        assert_eq!(interner.strings.len(), 2 * MIN_PURGE_LEN);
+        interner.intern("b");
+        assert_eq!(interner.strings.len(), 2);
+        assert!(interner.strings.contains("a"));
+        assert!(interner.strings.contains("b"));
+    }
+}
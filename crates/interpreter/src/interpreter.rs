@@ -0,0 +1,2078 @@
+use {
+    crate::{
+        builtins,
+        class::{LoxClass, LoxInstance},
+        environment::Environment,
+        function::{Callable, LoxFunction, NativeFunction},
+        interner::Interner,
+        memory::{self, Heap, MemoryStats, Object},
+        value::Value,
+        Error,
+        Result,
+    },
+    core::fmt,
+    lexer::token::TokenSpan,
+    parser::grammar::{
+        self,
+        Assign,
+        Binary,
+        BinaryOperator,
+        Block,
+        Call,
+        Class,
+        Expr,
+        ExprVisitor,
+        Expression,
+        Function,
+        Get,
+        Grouping,
+        If,
+        Literal,
+        LiteralValue,
+        Logical,
+        LogicalOperator,
+        Print,
+        Return,
+        Sequence,
+        Set,
+        Stmt,
+        StmtVisitor,
+        Super,
+        This,
+        Unary,
+        UnaryOperator,
+        Update,
+        UpdateOperator,
+        Var,
+        Variable,
+        Visitable,
+        Visitor,
+        While,
+    },
+    std::{
+        cell::RefCell,
+        io::{self, Write},
+        mem,
+        rc::Rc,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    },
+};
+
+/// Tree-walking interpreter, evaluating the AST produced by the parser.
+pub struct Interpreter<'a> {
+    /// Source code of the code being run, attached to diagnostics.
+    src: &'a str,
+
+    /// Environment of the global scope.
+    globals: Rc<RefCell<Environment<'a>>>,
+
+    /// Environment of the innermost scope being executed.
+    environment: Rc<RefCell<Environment<'a>>>,
+
+    division_by_zero: DivisionByZero,
+
+    /// Number of calls currently being executed.
+    call_depth: usize,
+
+    /// Maximum number of nested calls.
+    max_call_depth: usize,
+
+    /// Maximum number of bytes of native stack a run uses, if limited.
+    stack_limit: Option<usize>,
+
+    /// Address of the native stack at the start of the current run, from which
+    /// the stack in use is measured.
+    stack_base: usize,
+
+    /// Flag raised from outside (e.g. by a signal handler) to abort execution.
+    interrupt: Option<Arc<AtomicBool>>,
+
+    /// Stream `print` statements write to.
+    output: Box<dyn Write + 'a>,
+
+    /// Maximum number of steps, if limited.
+    step_limit: Option<u64>,
+
+    /// Number of steps executed so far.
+    steps: u64,
+
+    /// Memory used by the objects of the program.
+    heap: Heap<'a>,
+
+    /// Strings of the program.
+    strings: Interner,
+}
+
+/// Default maximum number of nested calls.
+///
+/// The limit is best-effort: the native stack a call takes grows with how
+/// deeply the body of the function is nested, so no call count fits a given
+/// stack. Hosts which must not overflow their stack also set
+/// `with_stack_limit()`. Calls in tail position don't count.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 128;
+
+/// What dividing a number by zero results in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DivisionByZero {
+    /// Runtime error, pointing at the divisor.
+    #[default]
+    Error,
+
+    /// Infinity (or NaN, for `0 / 0`), as in IEEE 754 arithmetic and the
+    /// reference implementation.
+    Infinity,
+}
+
+/// How execution continues after a statement.
+#[derive(Debug)]
+pub enum Flow<'a> {
+    /// On to the next statement.
+    Normal,
+
+    /// Out of the enclosing function, with the returned value.
+    Return(Value<'a>),
+
+    /// Out of the enclosing function, returning the result of calling another
+    /// function with the given arguments.
+    ///
+    /// The call is made by the caller of the enclosing function, so that calls
+    /// in tail position don't grow the stack.
+    TailCall(Rc<LoxFunction<'a>>, Vec<Value<'a>>),
+}
+
+impl fmt::Debug for Interpreter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interpreter")
+            .field("environment", &self.environment)
+            .field("division_by_zero", &self.division_by_zero)
+            .field("call_depth", &self.call_depth)
+            .field("max_call_depth", &self.max_call_depth)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Interpreter<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Interpreter<'a> {
+    /// Creates a new interpreter, with only the built-in native functions
+    /// defined.
+    pub fn new() -> Self {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        let mut interpreter = Self {
+            src: "",
+            environment: globals.clone(),
+            globals,
+            division_by_zero: DivisionByZero::default(),
+            call_depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            stack_limit: None,
+            stack_base: 0,
+            interrupt: None,
+            output: Box::new(io::stdout()),
+            step_limit: None,
+            steps: 0,
+            heap: Heap::default(),
+            strings: Interner::default(),
+        };
+        for native in builtins::natives() {
+            interpreter.define_native(native);
+        }
+        interpreter
+    }
+
+    /// Sets what dividing a number by zero results in.
+    pub fn with_division_by_zero(mut self, division_by_zero: DivisionByZero) -> Self {
+        self.division_by_zero = division_by_zero;
+        self
+    }
+
+    /// Sets the maximum number of nested calls, past which a program fails with
+    /// a stack overflow error.
+    pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+
+    /// Limits the number of bytes of native stack a run uses, measured from
+    /// the call to `interpret()` or `evaluate()`, so that deeply nested or
+    /// recursive programs fail with a stack overflow error instead of crashing
+    /// the host.
+    ///
+    /// The limit must leave some room in the stack of the thread: the stack in
+    /// use is checked before every evaluated expression, not at every native
+    /// frame.
+    pub fn with_stack_limit(mut self, limit: usize) -> Self {
+        self.stack_limit = Some(limit);
+        self
+    }
+
+    /// Sets a flag which, once raised, aborts the program being run with an
+    /// interrupted error.
+    ///
+    /// The flag is checked before every call and loop iteration, and lowered
+    /// when it aborts a program.
+    pub fn with_interrupt(mut self, interrupt: Arc<AtomicBool>) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    /// Sets the stream `print` statements write to, instead of the standard
+    /// output.
+    pub fn with_output(mut self, output: impl Write + 'a) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Limits the number of steps the interpreter executes, every evaluated
+    /// expression being a step, so that untrusted programs can't run forever.
+    ///
+    /// The budget is shared by all the programs run by this interpreter; once
+    /// exhausted, they fail with a budget exceeded error.
+    pub fn with_step_limit(mut self, limit: u64) -> Self {
+        self.step_limit = Some(limit);
+        self
+    }
+
+    /// Limits the (approximate) number of bytes used by strings, instances and
+    /// closures, so that untrusted programs can't exhaust the memory of the
+    /// host.
+    ///
+    /// Allocating past the limit fails with an out of memory error.
+    pub fn with_memory_limit(mut self, limit: usize) -> Self {
+        self.heap.set_limit(limit);
+        self.heap.track_environment(&self.globals);
+        self
+    }
+
+    /// Tracks the memory used by the objects of the programs, for
+    /// `memory_stats()`, even if it isn't limited.
+    pub fn with_memory_stats(mut self) -> Self {
+        self.heap.track();
+        self.heap.track_environment(&self.globals);
+        self
+    }
+
+    /// Returns the memory usage of the programs run so far, when tracked by
+    /// `with_memory_stats()` or `with_memory_limit()`, or else only the
+    /// number of interned strings.
+    pub fn memory_stats(&mut self) -> MemoryStats {
+        MemoryStats {
+            interned_strings: self.strings.live(),
+            ..self.heap.stats()
+        }
+    }
+
+    /// Defines a native function in the global scope, under its name.
+    pub fn define_native(&mut self, native: NativeFunction<'a>) {
+        self.globals
+            .borrow_mut()
+            .define(native.name(), Value::Native(Rc::new(native)));
+    }
+
+    /// Returns the global variables, including the native functions, sorted by
+    /// name.
+    pub fn globals(&self) -> Vec<(&'a str, Value<'a>)> {
+        let mut globals: Vec<_> = self
+            .globals
+            .borrow()
+            .bindings()
+            .map(|(name, value)| (name, value.clone()))
+            .collect();
+        globals.sort_by_key(|(name, _)| *name);
+        globals
+    }
+
+    /// Executes a program parsed from the given source code.
+    ///
+    /// Execution stops at the first runtime error, or at a top-level `return`.
+    /// Variables defined before that point remain defined, for subsequent runs.
+    pub fn interpret(&mut self, src: &'a str, statements: &[Stmt<'a>]) -> Result<()> {
+        self.src = src;
+        self.stack_base = stack_address();
+        self.execute_statements(statements)?;
+        Ok(())
+    }
+
+    /// Evaluates an expression parsed from the given source code.
+    pub fn evaluate(&mut self, src: &'a str, expr: &Expr<'a>) -> Result<Value<'a>> {
+        self.src = src;
+        self.stack_base = stack_address();
+        expr.accept(self)
+    }
+
+    /// Executes the body of a function in the given environment.
+    ///
+    /// Diagnostics raised in the body are attached to `src`, the source code of
+    /// the function declaration.
+    pub(crate) fn execute_body(
+        &mut self,
+        src: &'a str,
+        body: &[Stmt<'a>],
+        environment: Environment<'a>,
+    ) -> Result<Flow<'a>> {
+        let caller_src = mem::replace(&mut self.src, src);
+        let result = self.execute_block(body, environment);
+        self.src = caller_src;
+        result
+    }
+
+    /// Executes the statements in the given environment.
+    fn execute_block(
+        &mut self,
+        statements: &[Stmt<'a>],
+        environment: Environment<'a>,
+    ) -> Result<Flow<'a>> {
+        let environment = self.scope(environment);
+        let enclosing = mem::replace(&mut self.environment, environment);
+        let result = self.execute_statements(statements);
+        // Restore the enclosing scope even if execution failed.
+        self.environment = enclosing;
+        result
+    }
+
+    /// Executes the statements in order, until one of them returns.
+    fn execute_statements(&mut self, statements: &[Stmt<'a>]) -> Result<Flow<'a>> {
+        for stmt in statements {
+            match stmt.accept(self)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    /// Makes the scope of a new declaration the innermost one, returning it.
+    ///
+    /// Globals are all declared in the global scope, as they may be used before
+    /// their declaration, e.g. in functions. Every local declaration gets a
+    /// scope of its own instead, nested in the current one, so that closures
+    /// only see the locals declared before them, as resolved statically.
+    fn declaration_scope(&mut self) -> Rc<RefCell<Environment<'a>>> {
+        if !Rc::ptr_eq(&self.environment, &self.globals) {
+            let environment = Environment::with_enclosing(self.environment.clone());
+            self.environment = self.scope(environment);
+        }
+        self.environment.clone()
+    }
+
+    /// Shares a new environment, tracking it for the memory statistics.
+    pub(crate) fn scope(&mut self, environment: Environment<'a>) -> Rc<RefCell<Environment<'a>>> {
+        let environment = Rc::new(RefCell::new(environment));
+        self.heap.track_environment(&environment);
+        environment
+    }
+
+    /// Evaluates the arguments of the call, from left to right.
+    fn arguments(&mut self, call: &Call<'a>) -> Result<Vec<Value<'a>>> {
+        call.arguments
+            .iter()
+            .map(|argument| argument.accept(self))
+            .collect()
+    }
+
+    /// Calls the callee, if it is callable at all.
+    fn call_value(
+        &mut self,
+        call: &Call<'a>,
+        callee: Value<'a>,
+        arguments: Vec<Value<'a>>,
+    ) -> Result<Value<'a>> {
+        match callee {
+            Value::Function(function) => self.call(call, function.as_ref(), arguments),
+            Value::Native(native) => self.call(call, native.as_ref(), arguments),
+            Value::Class(class) => {
+                let instance = self.call(call, &class, arguments)?;
+                if let Value::Instance(instance) = &instance {
+                    let object = Object::Instance(Rc::downgrade(instance));
+                    let size = memory::rc_size(&**instance);
+                    self.allocate(object, size, call.callee.span().merge(call.span))?;
+                }
+                Ok(instance)
+            }
+            _ => Err(Error::NotCallable {
+                src: self.src(),
+                at: call.callee.span().into(),
+                found: callee.type_name(),
+            }),
+        }
+    }
+
+    /// Evaluates a call in tail position, leaving calls to Lox functions to the
+    /// caller of the enclosing function.
+    fn tail_call(&mut self, call: &Call<'a>) -> Result<Flow<'a>> {
+        let Some(callee) = self.callee(call)? else {
+            return Ok(Flow::Return(Value::Nil));
+        };
+        let arguments = self.arguments(call)?;
+        match callee {
+            Value::Function(function) => {
+                self.check_call(call, function.as_ref(), &arguments)?;
+                Ok(Flow::TailCall(function, arguments))
+            }
+            callee => Ok(Flow::Return(self.call_value(call, callee, arguments)?)),
+        }
+    }
+
+    /// Evaluates the callee of a call, or returns `None` if it's part of a
+    /// chain short-circuited by a null-safe access, e.g. `a?.m()` with `a`
+    /// being `nil`, in which case the call is skipped, arguments included.
+    fn callee(&mut self, call: &Call<'a>) -> Result<Option<Value<'a>>> {
+        self.chain(call.callee)
+    }
+
+    /// Evaluates a link of a chain of property accesses and calls, e.g. the
+    /// `a?.b` of `a?.b.c()`, or returns `None` if a null-safe access in the
+    /// chain found `nil`, which skips the rest of the chain, as in JavaScript.
+    ///
+    /// Parentheses end the chain: `(a?.b).c` evaluates `a?.b` to `nil`.
+    fn chain(&mut self, expr: &Expr<'a>) -> Result<Option<Value<'a>>> {
+        match expr {
+            Expr::Get(get) => {
+                self.step(expr)?;
+                self.check_stack(expr)?;
+                self.member(get)
+            }
+            Expr::Call(call) => {
+                self.step(expr)?;
+                self.check_stack(expr)?;
+                let Some(callee) = self.callee(call)? else {
+                    return Ok(None);
+                };
+                let arguments = self.arguments(call)?;
+                self.call_value(call, callee, arguments).map(Some)
+            }
+            expr => expr.accept(self).map(Some),
+        }
+    }
+
+    /// Evaluates a property access, or returns `None` if its chain is
+    /// short-circuited, see [`Self::chain`].
+    fn member(&mut self, get: &Get<'a>) -> Result<Option<Value<'a>>> {
+        match self.chain(get.object)? {
+            None => Ok(None),
+            Some(Value::Nil) if get.optional => Ok(None),
+            Some(object) => self.property(get, object).map(Some),
+        }
+    }
+
+    /// Calls the callee, checking the number of arguments first.
+    fn call(
+        &mut self,
+        call: &Call<'a>,
+        callee: &dyn Callable<'a>,
+        arguments: Vec<Value<'a>>,
+    ) -> Result<Value<'a>> {
+        let at = self.check_call(call, callee, &arguments)?;
+        if self.call_depth == self.max_call_depth {
+            return Err(Error::StackOverflow {
+                src: self.src(),
+                at: at.into(),
+                max_depth: self.max_call_depth,
+            });
+        }
+        self.call_depth += 1;
+        let result = callee.call(self, arguments);
+        self.call_depth -= 1;
+        result
+    }
+
+    /// Checks that the call can be made, returning its span.
+    fn check_call(
+        &self,
+        call: &Call<'a>,
+        callee: &dyn Callable<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<TokenSpan> {
+        let at = call.callee.span().merge(call.span);
+        if arguments.len() != callee.arity() {
+            return Err(Error::ArityMismatch {
+                src: self.src(),
+                at: at.into(),
+                expected: callee.arity(),
+                found: arguments.len(),
+            });
+        }
+        self.check_interrupt(at)?;
+        Ok(at)
+    }
+
+    /// Accounts for a newly allocated object, created at the given span.
+    fn allocate(&mut self, object: Object<'a>, size: usize, at: TokenSpan) -> Result<()> {
+        if self.heap.allocate(object, size) {
+            return Ok(());
+        }
+        Err(Error::OutOfMemory {
+            src: self.src(),
+            at: at.into(),
+            limit: self
+                .heap
+                .limit()
+                .expect("only limited heaps run out of memory"),
+        })
+    }
+
+    /// Counts the evaluation of the expression against the step limit.
+    fn step(&mut self, expr: &Expr<'a>) -> Result<()> {
+        match self.step_limit {
+            Some(limit) if self.steps == limit => Err(Error::BudgetExceeded {
+                src: self.src(),
+                at: expr.span().into(),
+                limit,
+            }),
+            _ => {
+                self.steps += 1;
+                Ok(())
+            }
+        }
+    }
+
+    /// Fails at the expression if the run uses more native stack than the
+    /// limit.
+    fn check_stack(&self, expr: &Expr<'a>) -> Result<()> {
+        match self.stack_limit {
+            Some(limit) if self.stack_base.abs_diff(stack_address()) > limit => {
+                Err(Error::StackExhausted {
+                    src: self.src(),
+                    at: expr.span().into(),
+                    limit,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Aborts execution at the given span, if the interrupt flag is raised.
+    fn check_interrupt(&self, at: TokenSpan) -> Result<()> {
+        match &self.interrupt {
+            Some(interrupt) if interrupt.swap(false, Ordering::Relaxed) => {
+                Err(Error::Interrupted {
+                    src: self.src(),
+                    at: at.into(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Evaluates the object of a property access, which must be an instance.
+    fn instance(&mut self, object: &Expr<'a>) -> Result<Rc<LoxInstance<'a>>> {
+        match object.accept(self)? {
+            Value::Instance(instance) => Ok(instance),
+            value => Err(Error::NotAnInstance {
+                src: self.src(),
+                at: object.span().into(),
+                found: value.type_name(),
+            }),
+        }
+    }
+
+    /// Returns the property accessed by `get` on its (evaluated) object.
+    fn property(&mut self, get: &Get<'a>, object: Value<'a>) -> Result<Value<'a>> {
+        let instance = match object {
+            Value::Instance(instance) => instance,
+            value => {
+                return Err(Error::NotAnInstance {
+                    src: self.src(),
+                    at: get.object.span().into(),
+                    found: value.type_name(),
+                })
+            }
+        };
+        instance
+            .get(get.name.lexeme, self)
+            .ok_or_else(|| Error::UndefinedProperty {
+                src: self.src(),
+                at: get.name.span.into(),
+                name: get.name.lexeme.to_string(),
+            })
+    }
+
+    /// Returns a copy of the source code, to be attached to diagnostics.
+    fn src(&self) -> String {
+        self.src.to_string()
+    }
+
+    /// Returns the operands as numbers, if both of them are numbers.
+    fn numbers(&self, binary: &Binary<'a>, left: &Value, right: &Value) -> Result<(f64, f64)> {
+        match (left, right) {
+            (Value::Number(a), Value::Number(b)) => Ok((*a, *b)),
+            _ => Err(Error::OperandsMustBeNumbers {
+                src: self.src(),
+                left: binary.left.span().into(),
+                left_type: left.type_name(),
+                right: binary.right.span().into(),
+                right_type: right.type_name(),
+            }),
+        }
+    }
+}
+
+/// Returns the address of a local variable, approximating the top of the native
+/// stack.
+fn stack_address() -> usize {
+    let marker = 0_u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+impl<'a> ExprVisitor<'a, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit_expr(&mut self, data: &Expr<'a>) -> Result<Value<'a>> {
+        self.step(data)?;
+        self.check_stack(data)?;
+        grammar::visit_expr(self, data)
+    }
+}
+
+impl<'a> Visitor<Assign<'a>, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Assign<'a>) -> Result<Value<'a>> {
+        let value = data.value.accept(self)?;
+        if !self
+            .environment
+            .borrow_mut()
+            .assign(data.name.lexeme, value.clone())
+        {
+            return Err(Error::UndefinedVariable {
+                src: self.src(),
+                at: data.name.span.into(),
+                name: data.name.lexeme.to_string(),
+            });
+        }
+        Ok(value)
+    }
+}
+
+impl<'a> Visitor<Binary<'a>, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Binary<'a>) -> Result<Value<'a>> {
+        let left = data.left.accept(self)?;
+        let right = data.right.accept(self)?;
+        let value = match data.operator {
+            BinaryOperator::EqualEqual => Value::Boolean(left == right),
+            BinaryOperator::BangEqual => Value::Boolean(left != right),
+            BinaryOperator::Plus => match (left, right) {
+                (Value::Number(a), Value::Number(b)) => Value::Number(a + b),
+                (Value::String(a), Value::String(b)) => {
+                    let (string, added) = self.strings.intern(&format!("{a}{b}"));
+                    if added {
+                        let size = memory::rc_size(&*string);
+                        self.allocate(Object::String(Rc::downgrade(&string)), size, data.span)?;
+                    }
+                    Value::String(string)
+                }
+                (left, right) => {
+                    return Err(Error::OperandsMustBeNumbersOrStrings {
+                        src: self.src(),
+                        left: data.left.span().into(),
+                        left_type: left.type_name(),
+                        right: data.right.span().into(),
+                        right_type: right.type_name(),
+                    })
+                }
+            },
+            operator => {
+                let (a, b) = self.numbers(data, &left, &right)?;
+                match operator {
+                    BinaryOperator::Minus => Value::Number(a - b),
+                    BinaryOperator::Star => Value::Number(a * b),
+                    BinaryOperator::Slash => {
+                        if b == 0.0 && self.division_by_zero == DivisionByZero::Error {
+                            return Err(Error::DivisionByZero {
+                                src: self.src(),
+                                at: data.right.span().into(),
+                            });
+                        }
+                        Value::Number(a / b)
+                    }
+                    BinaryOperator::Greater => Value::Boolean(a > b),
+                    BinaryOperator::GreaterEqual => Value::Boolean(a >= b),
+                    BinaryOperator::Less => Value::Boolean(a < b),
+                    BinaryOperator::LessEqual => Value::Boolean(a <= b),
+                    _ => unreachable!("handled above"),
+                }
+            }
+        };
+        Ok(value)
+    }
+}
+
+impl<'a> Visitor<Call<'a>, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Call<'a>) -> Result<Value<'a>> {
+        let Some(callee) = self.callee(data)? else {
+            return Ok(Value::Nil);
+        };
+        let arguments = self.arguments(data)?;
+        self.call_value(data, callee, arguments)
+    }
+}
+
+impl<'a> Visitor<Get<'a>, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Get<'a>) -> Result<Value<'a>> {
+        Ok(self.member(data)?.unwrap_or(Value::Nil))
+    }
+}
+
+impl<'a> Visitor<Grouping<'a>, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Grouping<'a>) -> Result<Value<'a>> {
+        data.expression.accept(self)
+    }
+}
+
+impl<'a> Visitor<Literal<'a>, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Literal<'a>) -> Result<Value<'a>> {
+        match data.value {
+            LiteralValue::String(string) => {
+                let (string, added) = self.strings.intern(string);
+                if added {
+                    let size = memory::rc_size(&*string);
+                    self.allocate(Object::String(Rc::downgrade(&string)), size, data.span)?;
+                }
+                Ok(Value::String(string))
+            }
+            value => Ok(value.into()),
+        }
+    }
+}
+
+impl<'a> Visitor<Logical<'a>, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Logical<'a>) -> Result<Value<'a>> {
+        let left = data.left.accept(self)?;
+        // Short-circuit, yielding the operand itself rather than a boolean.
+        let short_circuit = match data.operator {
+            LogicalOperator::Or => left.is_truthy(),
+            LogicalOperator::And => !left.is_truthy(),
+        };
+        if short_circuit {
+            return Ok(left);
+        }
+        data.right.accept(self)
+    }
+}
+
+impl<'a> Visitor<Sequence<'a>, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Sequence<'a>) -> Result<Value<'a>> {
+        data.left.accept(self)?;
+        data.right.accept(self)
+    }
+}
+
+impl<'a> Visitor<Set<'a>, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Set<'a>) -> Result<Value<'a>> {
+        let instance = self.instance(data.object)?;
+        let value = data.value.accept(self)?;
+        if instance.set(data.name.lexeme, value.clone()).is_none() {
+            // New fields grow the instance.
+            let size = mem::size_of::<(&str, Value)>();
+            self.allocate(
+                Object::Instance(Rc::downgrade(&instance)),
+                size,
+                data.name.span,
+            )?;
+        }
+        Ok(value)
+    }
+}
+
+impl<'a> Visitor<Super<'a>, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Super<'a>) -> Result<Value<'a>> {
+        // `super` is defined in the scope enclosing the one defining `this`.
+        let found = {
+            let environment = self.environment.borrow();
+            (environment.get("super"), environment.get("this"))
+        };
+        let (Some(Value::Class(superclass)), Some(Value::Instance(instance))) = found else {
+            return Err(Error::UndefinedVariable {
+                src: self.src(),
+                at: data.span.into(),
+                name: "super".to_string(),
+            });
+        };
+        let method =
+            superclass
+                .find_method(data.method.lexeme)
+                .ok_or_else(|| Error::UndefinedProperty {
+                    src: self.src(),
+                    at: data.method.span.into(),
+                    name: data.method.lexeme.to_string(),
+                })?;
+        Ok(Value::Function(Rc::new(method.bind(instance, self))))
+    }
+}
+
+impl<'a> Visitor<This, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &This) -> Result<Value<'a>> {
+        // Defined in the scope enclosing the body of bound methods.
+        self.environment
+            .borrow()
+            .get("this")
+            .ok_or_else(|| Error::UndefinedVariable {
+                src: self.src(),
+                at: data.span.into(),
+                name: "this".to_string(),
+            })
+    }
+}
+
+impl<'a> Visitor<Unary<'a>, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Unary<'a>) -> Result<Value<'a>> {
+        let right = data.right.accept(self)?;
+        match data.operator {
+            UnaryOperator::Bang => Ok(Value::Boolean(!right.is_truthy())),
+            UnaryOperator::Minus => match right {
+                Value::Number(n) => Ok(Value::Number(-n)),
+                _ => Err(Error::OperandMustBeNumber {
+                    src: self.src(),
+                    at: data.right.span().into(),
+                    found: right.type_name(),
+                }),
+            },
+        }
+    }
+}
+
+impl<'a> Visitor<Update<'a>, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Update<'a>) -> Result<Value<'a>> {
+        let updated = |interpreter: &Self, value: &Value<'a>| match value {
+            Value::Number(n) => Ok(Value::Number(match data.operator {
+                UpdateOperator::Increment => n + 1.0,
+                UpdateOperator::Decrement => n - 1.0,
+            })),
+            _ => Err(Error::OperandMustBeNumber {
+                src: interpreter.src(),
+                at: data.target.span().into(),
+                found: value.type_name(),
+            }),
+        };
+        let (previous, value) = match data.target {
+            Expr::Variable(variable) => {
+                let previous = self.visit(variable)?;
+                let value = updated(self, &previous)?;
+                self.environment
+                    .borrow_mut()
+                    .assign(variable.name.lexeme, value.clone());
+                (previous, value)
+            }
+            Expr::Get(get) => {
+                let instance = self.instance(get.object)?;
+                let previous = self.property(get, Value::Instance(instance.clone()))?;
+                let value = updated(self, &previous)?;
+                // Numbers are fields, not methods, so the field already exists.
+                instance.set(get.name.lexeme, value.clone());
+                (previous, value)
+            }
+            // The parser only builds updates of variables and fields, but
+            // transforms may build others.
+            target => {
+                return Err(Error::InvalidAssignmentTarget {
+                    src: self.src(),
+                    at: target.span().into(),
+                })
+            }
+        };
+        Ok(if data.prefix { value } else { previous })
+    }
+}
+
+impl<'a> Visitor<Variable<'a>, Result<Value<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Variable<'a>) -> Result<Value<'a>> {
+        self.environment
+            .borrow()
+            .get(data.name.lexeme)
+            .ok_or_else(|| Error::UndefinedVariable {
+                src: self.src(),
+                at: data.name.span.into(),
+                name: data.name.lexeme.to_string(),
+            })
+    }
+}
+
+impl<'a> StmtVisitor<'a, Result<Flow<'a>>> for Interpreter<'a> {}
+
+impl<'a> Visitor<Block<'a>, Result<Flow<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Block<'a>) -> Result<Flow<'a>> {
+        let environment = Environment::with_enclosing(self.environment.clone());
+        self.execute_block(data.statements, environment)
+    }
+}
+
+impl<'a> Visitor<Class<'a>, Result<Flow<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Class<'a>) -> Result<Flow<'a>> {
+        let superclass = match &data.superclass {
+            Some(superclass) => match superclass.accept(self)? {
+                Value::Class(class) => Some(class),
+                value => {
+                    return Err(Error::SuperclassMustBeClass {
+                        src: self.src(),
+                        at: superclass.name.span.into(),
+                        found: value.type_name(),
+                    })
+                }
+            },
+            None => None,
+        };
+
+        // Methods of a subclass close over a scope defining `super`.
+        let scope = self.declaration_scope();
+        let closure = match &superclass {
+            Some(superclass) => {
+                let mut environment = Environment::with_enclosing(scope.clone());
+                environment.define("super", Value::Class(superclass.clone()));
+                self.scope(environment)
+            }
+            None => scope.clone(),
+        };
+        let methods = data
+            .methods
+            .iter()
+            .map(|method| {
+                let is_initializer = method.name.lexeme == "init";
+                let function = LoxFunction::new(*method, closure.clone(), self.src, is_initializer);
+                (method.name.lexeme, Rc::new(function))
+            })
+            .collect();
+        let class = LoxClass::new(data.name.lexeme, superclass, methods);
+        scope
+            .borrow_mut()
+            .define(data.name.lexeme, Value::Class(Rc::new(class)));
+        Ok(Flow::Normal)
+    }
+}
+
+impl<'a> Visitor<Expression<'a>, Result<Flow<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Expression<'a>) -> Result<Flow<'a>> {
+        data.expression.accept(self)?;
+        Ok(Flow::Normal)
+    }
+}
+
+impl<'a> Visitor<Function<'a>, Result<Flow<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Function<'a>) -> Result<Flow<'a>> {
+        // The function closes over the scope it is declared in, which defines
+        // the function itself, for recursion.
+        let scope = self.declaration_scope();
+        let function = Rc::new(LoxFunction::new(*data, scope.clone(), self.src, false));
+        let size = memory::rc_size(&*function);
+        self.allocate(
+            Object::Function(Rc::downgrade(&function)),
+            size,
+            data.name.span,
+        )?;
+        scope
+            .borrow_mut()
+            .define(data.name.lexeme, Value::Function(function));
+        Ok(Flow::Normal)
+    }
+}
+
+impl<'a> Visitor<If<'a>, Result<Flow<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &If<'a>) -> Result<Flow<'a>> {
+        if data.condition.accept(self)?.is_truthy() {
+            data.then_branch.accept(self)
+        } else if let Some(else_branch) = data.else_branch {
+            else_branch.accept(self)
+        } else {
+            Ok(Flow::Normal)
+        }
+    }
+}
+
+impl<'a> Visitor<Print<'a>, Result<Flow<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Print<'a>) -> Result<Flow<'a>> {
+        let value = data.expression.accept(self)?;
+        writeln!(self.output, "{value}").map_err(|e| Error::Output {
+            src: self.src(),
+            at: data.span.into(),
+            reason: e.to_string(),
+        })?;
+        Ok(Flow::Normal)
+    }
+}
+
+impl<'a> Visitor<Return<'a>, Result<Flow<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Return<'a>) -> Result<Flow<'a>> {
+        let value = match &data.value {
+            // Only within functions, as top-level code has no caller to make
+            // the call.
+            Some(expr @ Expr::Call(call)) if self.call_depth > 0 => {
+                self.step(expr)?;
+                return self.tail_call(call);
+            }
+            Some(value) => value.accept(self)?,
+            None => Value::Nil,
+        };
+        Ok(Flow::Return(value))
+    }
+}
+
+impl<'a> Visitor<Var<'a>, Result<Flow<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &Var<'a>) -> Result<Flow<'a>> {
+        let value = match &data.initializer {
+            Some(initializer) => initializer.accept(self)?,
+            None => Value::Nil,
+        };
+        self.declaration_scope()
+            .borrow_mut()
+            .define(data.name.lexeme, value);
+        Ok(Flow::Normal)
+    }
+}
+
+impl<'a> Visitor<While<'a>, Result<Flow<'a>>> for Interpreter<'a> {
+    fn visit(&mut self, data: &While<'a>) -> Result<Flow<'a>> {
+        // The condition is re-evaluated before every iteration.
+        while data.condition.accept(self)?.is_truthy() {
+            match data.body.accept(self)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+            self.check_interrupt(data.span)?;
+        }
+        Ok(Flow::Normal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        lexer::{LanguageLevel, Lexer, LexerConfig},
+        parser::Parser,
+        pretty_assertions::assert_eq,
+    };
+
+    /// Creates a parser over the input, at the extended language level.
+    fn extended(input: &str) -> Parser<'_> {
+        let lexer = Lexer::new(input).with_config(LexerConfig {
+            level: LanguageLevel::Extended,
+            ..LexerConfig::default()
+        });
+        Parser::new(lexer, Box::leak(Box::default()))
+    }
+
+    fn eval_with<'a>(interpreter: &mut Interpreter<'a>, input: &'a str) -> Result<Value<'a>> {
+        let arena = Box::leak(Box::default());
+        let expr = Parser::new(Lexer::new(input), arena)
+            .parse_expression()
+            .unwrap();
+        interpreter.evaluate(input, &expr)
+    }
+
+    fn eval(input: &str) -> Result<Value<'_>> {
+        eval_with(&mut Interpreter::new(), input)
+    }
+
+    fn run_with<'a>(interpreter: &mut Interpreter<'a>, input: &'a str) -> Result<()> {
+        let arena = Box::leak(Box::default());
+        let statements = Parser::new(Lexer::new(input), arena).parse().unwrap();
+        interpreter.interpret(input, &statements)
+    }
+
+    /// Runs the program and returns the value of the given global variable.
+    fn run_and_get<'a>(input: &'a str, name: &str) -> Value<'a> {
+        let mut interpreter = Interpreter::new();
+        run_with(&mut interpreter, input).unwrap();
+        let value = interpreter.environment.borrow().get(name);
+        value.unwrap()
+    }
+
+    fn assert_eval(input: &str, expected: &str) {
+        assert_eq!(eval(input).unwrap().to_string(), expected);
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eval("1 + 2 * 3", "7");
+        assert_eval("(1 + 2) * 3", "9");
+        assert_eval("10 - 4 - 3", "3");
+        assert_eval("7 / 2", "3.5");
+        assert_eval("-(2 + 3)", "-5");
+        assert_eval("--1", "1");
+        assert_eval("\"foo\" + \"bar\"", "foobar");
+    }
+
+    #[test]
+    fn comparison_and_equality() {
+        assert_eval("1 < 2", "true");
+        assert_eval("2 <= 2", "true");
+        assert_eval("1 > 2", "false");
+        assert_eval("1 >= 2", "false");
+        assert_eval("1 == 1", "true");
+        assert_eval("\"a\" == \"a\"", "true");
+        assert_eval("nil == nil", "true");
+        assert_eval("nil == false", "false");
+        assert_eval("1 == \"1\"", "false");
+        assert_eval("1 != 2", "true");
+    }
+
+    #[test]
+    fn truthiness() {
+        assert_eval("!nil", "true");
+        assert_eval("!false", "true");
+        assert_eval("!0", "false");
+        assert_eval("!\"\"", "false");
+        assert_eval("nil or \"default\"", "default");
+        assert_eval("1 and 2", "2");
+        assert_eval("false and undefined", "false");
+        assert_eval("1, 2, 3", "3");
+    }
+
+    #[test]
+    fn variables() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .environment
+            .borrow_mut()
+            .define("a", Value::Number(1.0));
+        assert_eq!(
+            eval_with(&mut interpreter, "a = a + 1").unwrap(),
+            Value::Number(2.0)
+        );
+        assert_eq!(
+            eval_with(&mut interpreter, "a").unwrap(),
+            Value::Number(2.0)
+        );
+
+        let input = "b = 1";
+        assert_eq!(eval(input).unwrap_err(), Error::UndefinedVariable {
+            src: input.to_string(),
+            at: (0, 1).into(),
+            name: "b".to_string()
+        });
+        let input = "1 + b";
+        assert_eq!(eval(input).unwrap_err(), Error::UndefinedVariable {
+            src: input.to_string(),
+            at: (4, 1).into(),
+            name: "b".to_string()
+        });
+    }
+
+    #[test]
+    fn type_errors() {
+        let input = "-\"a\"";
+        assert_eq!(eval(input).unwrap_err(), Error::OperandMustBeNumber {
+            src: input.to_string(),
+            at: (1, 3).into(),
+            found: "a string"
+        });
+        let input = "1 < (nil)";
+        assert_eq!(eval(input).unwrap_err(), Error::OperandsMustBeNumbers {
+            src: input.to_string(),
+            left: (0, 1).into(),
+            left_type: "a number",
+            right: (4, 5).into(),
+            right_type: "nil",
+        });
+        let input = "\"a\" + 1";
+        assert_eq!(
+            eval(input).unwrap_err(),
+            Error::OperandsMustBeNumbersOrStrings {
+                src: input.to_string(),
+                left: (0, 3).into(),
+                left_type: "a string",
+                right: (6, 1).into(),
+                right_type: "a number",
+            }
+        );
+        let input = "true(1)";
+        assert_eq!(eval(input).unwrap_err(), Error::NotCallable {
+            src: input.to_string(),
+            at: (0, 4).into(),
+            found: "a boolean"
+        });
+        let input = "1.5.field";
+        assert_eq!(eval(input).unwrap_err(), Error::NotAnInstance {
+            src: input.to_string(),
+            at: (0, 3).into(),
+            found: "a number"
+        });
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(run_and_get("var a;", "a"), Value::Nil);
+        assert_eq!(
+            run_and_get("var a = 1 + 2; a = a * 2;", "a"),
+            Value::Number(6.0)
+        );
+        assert_eq!(
+            run_and_get("var a = 1; var a = \"x\";", "a"),
+            Value::String("x".into())
+        );
+        assert_eq!(run_and_get("var a; print a = 1;", "a"), Value::Number(1.0));
+    }
+
+    #[test]
+    fn blocks() {
+        let input = "var a = 1; var b = 2; { var a = 10; b = a; { a = a + 1; } }";
+        assert_eq!(run_and_get(input, "a"), Value::Number(1.0));
+        assert_eq!(run_and_get(input, "b"), Value::Number(10.0));
+
+        // Locals don't leak out of the block, even when it fails.
+        let mut interpreter = Interpreter::new();
+        let input = "{ var a = 1; -nil; }";
+        assert!(matches!(
+            run_with(&mut interpreter, input),
+            Err(Error::OperandMustBeNumber { .. })
+        ));
+        assert_eq!(interpreter.environment.borrow().get("a"), None);
+
+        let input = "{ var a = 1; } print a;";
+        assert_eq!(
+            run_with(&mut Interpreter::new(), input).unwrap_err(),
+            Error::UndefinedVariable {
+                src: input.to_string(),
+                at: (21, 1).into(),
+                name: "a".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn if_statements() {
+        let input = "var a; if (1 < 2) a = \"then\"; else a = \"else\";";
+        assert_eq!(run_and_get(input, "a"), Value::String("then".into()));
+        let input = "var a; if (nil) a = \"then\"; else a = \"else\";";
+        assert_eq!(run_and_get(input, "a"), Value::String("else".into()));
+        let input = "var a = 1; if (false) a = 2;";
+        assert_eq!(run_and_get(input, "a"), Value::Number(1.0));
+        // Dangling else binds to the nearest if.
+        let input = "var a = 1; if (true) if (false) a = 2; else a = 3;";
+        assert_eq!(run_and_get(input, "a"), Value::Number(3.0));
+    }
+
+    #[test]
+    fn short_circuit() {
+        let input = "var a = 0; var b = false and (a = 1);";
+        assert_eq!(run_and_get(input, "a"), Value::Number(0.0));
+        assert_eq!(run_and_get(input, "b"), Value::Boolean(false));
+        let input = "var a = 0; var b = \"yes\" or (a = 1);";
+        assert_eq!(run_and_get(input, "a"), Value::Number(0.0));
+        assert_eq!(run_and_get(input, "b"), Value::String("yes".into()));
+        let input = "var a = 0; var b = nil or (a = 1);";
+        assert_eq!(run_and_get(input, "a"), Value::Number(1.0));
+        assert_eq!(run_and_get(input, "b"), Value::Number(1.0));
+    }
+
+    #[test]
+    fn loops() {
+        let input = "var i = 0; var sum = 0; while (i < 5) { i = i + 1; sum = sum + i; }";
+        assert_eq!(run_and_get(input, "sum"), Value::Number(15.0));
+        let input = "var n = 0; while (false) n = 1;";
+        assert_eq!(run_and_get(input, "n"), Value::Number(0.0));
+
+        // Fibonacci numbers, as in the book.
+        let input = "
+            var a = 0;
+            var fib;
+            for (var b = 1; a < 10000; b = fib + b) {
+                fib = a;
+                a = b;
+            }
+        ";
+        assert_eq!(run_and_get(input, "fib"), Value::Number(6765.0));
+
+        // The initializer of a for loop is scoped to the loop.
+        let mut interpreter = Interpreter::new();
+        let input = "var i = \"outer\"; for (var i = 0; i < 3; i = i + 1) {}";
+        run_with(&mut interpreter, input).unwrap();
+        assert_eq!(
+            interpreter.environment.borrow().get("i"),
+            Some(Value::String("outer".into()))
+        );
+        let input = "for (var j = 0; j < 3; j = j + 1) {} j;";
+        assert!(matches!(
+            run_with(&mut interpreter, input),
+            Err(Error::UndefinedVariable { .. })
+        ));
+    }
+
+    #[test]
+    fn functions() {
+        let input = "
+            fun fib(n) {
+                if (n < 2) return n;
+                return fib(n - 2) + fib(n - 1);
+            }
+            var a = fib(20);
+        ";
+        assert_eq!(run_and_get(input, "a"), Value::Number(6765.0));
+
+        // Return unwinds loops and blocks; no return yields nil.
+        let input = "
+            fun find(limit) { var i = 0; while (true) { if (i >= limit) return i; i = i + 1; } }
+            fun nothing() { 1; }
+            var a = find(3);
+            var b = nothing();
+        ";
+        assert_eq!(run_and_get(input, "a"), Value::Number(3.0));
+        assert_eq!(run_and_get(input, "b"), Value::Nil);
+
+        // Parameters are local to the call.
+        let input = "var n = 1; fun f(n) { n = n + 1; return n; } var a = f(10);";
+        assert_eq!(run_and_get(input, "a"), Value::Number(11.0));
+        assert_eq!(run_and_get(input, "n"), Value::Number(1.0));
+
+        let input = "fun add(a, b) { return a + b; } var a = add; var b = add(1, 2) + 3;";
+        assert_eq!(run_and_get(input, "a").to_string(), "<fn add>");
+        assert_eq!(run_and_get(input, "b"), Value::Number(6.0));
+    }
+
+    #[test]
+    fn function_errors() {
+        let input = "fun f(a, b) {} f(1);";
+        assert_eq!(
+            run_with(&mut Interpreter::new(), input).unwrap_err(),
+            Error::ArityMismatch {
+                src: input.to_string(),
+                at: (15, 4).into(),
+                expected: 2,
+                found: 1,
+            }
+        );
+
+        // Errors in the body point into the source of the declaration.
+        let mut interpreter = Interpreter::new();
+        let declaration = "fun f(a) { return -a; }";
+        run_with(&mut interpreter, declaration).unwrap();
+        let input = "f(nil);";
+        assert_eq!(
+            run_with(&mut interpreter, input).unwrap_err(),
+            Error::OperandMustBeNumber {
+                src: declaration.to_string(),
+                at: (19, 1).into(),
+                found: "nil",
+            }
+        );
+
+        // The caller's source is restored after the call.
+        let input = "f(1) + nil;";
+        assert_eq!(
+            run_with(&mut interpreter, input).unwrap_err(),
+            Error::OperandsMustBeNumbersOrStrings {
+                src: input.to_string(),
+                left: (0, 4).into(),
+                left_type: "a number",
+                right: (7, 3).into(),
+                right_type: "nil",
+            }
+        );
+    }
+
+    #[test]
+    fn closures() {
+        let input = "
+            fun makeCounter() {
+                var i = 0;
+                fun count() { i = i + 1; return i; }
+                return count;
+            }
+            var counter = makeCounter();
+            var other = makeCounter();
+            counter();
+            var a = counter();
+            var b = other();
+        ";
+        assert_eq!(run_and_get(input, "a"), Value::Number(2.0));
+        assert_eq!(run_and_get(input, "b"), Value::Number(1.0));
+
+        // The captured scope outlives the block it was declared in.
+        let input = "
+            var f;
+            { var x = \"block\"; fun g() { return x; } f = g; }
+            var a = f();
+        ";
+        assert_eq!(run_and_get(input, "a"), Value::String("block".into()));
+
+        // Closures don't see the locals declared after them, as in
+        // `closure/assign_to_shadowed_later.lox` and `variable/early_bound.lox`
+        // of the reference suite.
+        let input = "
+            var a = \"global\";
+            var b;
+            {
+                fun assign() { a = \"assigned\"; }
+                var a = \"inner\";
+                assign();
+                b = a;
+            }
+        ";
+        assert_eq!(run_and_get(input, "a"), Value::String("assigned".into()));
+        assert_eq!(run_and_get(input, "b"), Value::String("inner".into()));
+        let input = "
+            var a = \"outer\";
+            var b;
+            var c;
+            {
+                fun get() { return a; }
+                b = get();
+                var a = \"inner\";
+                c = get();
+            }
+        ";
+        assert_eq!(run_and_get(input, "b"), Value::String("outer".into()));
+        assert_eq!(run_and_get(input, "c"), Value::String("outer".into()));
+    }
+
+    #[test]
+    fn natives() {
+        let mut interpreter = Interpreter::new();
+        interpreter.define_native(NativeFunction::new("max", 2, |arguments| {
+            match (&arguments[0], &arguments[1]) {
+                (Value::Number(a), Value::Number(b)) => Value::Number(a.max(*b)),
+                _ => Value::Nil,
+            }
+        }));
+        let input = "var a = max(1, 2); var b = max; { fun max() {} } var c = max(nil, 1);";
+        run_with(&mut interpreter, input).unwrap();
+        let globals = interpreter.globals.borrow();
+        assert_eq!(globals.get("a"), Some(Value::Number(2.0)));
+        assert_eq!(globals.get("b").unwrap().to_string(), "<native fn>");
+        assert_eq!(globals.get("c"), Some(Value::Nil));
+        drop(globals);
+
+        let input = "max(1);";
+        assert_eq!(
+            run_with(&mut interpreter, input).unwrap_err(),
+            Error::ArityMismatch {
+                src: input.to_string(),
+                at: (0, 6).into(),
+                expected: 2,
+                found: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn clock() {
+        let input = "var start = clock(); var elapsed = clock() - start;";
+        let Value::Number(start) = run_and_get(input, "start") else {
+            panic!("expected a number");
+        };
+        assert!(start > 1e9);
+        let Value::Number(elapsed) = run_and_get(input, "elapsed") else {
+            panic!("expected a number");
+        };
+        assert!(elapsed >= 0.0);
+        assert_eq!(
+            run_and_get("var c = clock;", "c").to_string(),
+            "<native fn>"
+        );
+    }
+
+    #[test]
+    fn classes() {
+        let input = "
+            class Point {}
+            var p = Point();
+            p.x = 1;
+            p.y = p.x + 1;
+            var q = p;
+            q.x = 10;
+            var a = p.x + p.y;
+            var b = p;
+            var c = Point;
+            var d = Point() == Point();
+        ";
+        assert_eq!(run_and_get(input, "a"), Value::Number(12.0));
+        assert_eq!(run_and_get(input, "b").to_string(), "Point instance");
+        assert_eq!(run_and_get(input, "c").to_string(), "Point");
+        assert_eq!(run_and_get(input, "d"), Value::Boolean(false));
+
+        let input = "class A {} A().field;";
+        assert_eq!(
+            run_with(&mut Interpreter::new(), input).unwrap_err(),
+            Error::UndefinedProperty {
+                src: input.to_string(),
+                at: (15, 5).into(),
+                name: "field".to_string(),
+            }
+        );
+        let input = "class A {} A.field = 1;";
+        assert_eq!(
+            run_with(&mut Interpreter::new(), input).unwrap_err(),
+            Error::NotAnInstance {
+                src: input.to_string(),
+                at: (11, 1).into(),
+                found: "a class",
+            }
+        );
+        let input = "class A {} A(1);";
+        assert!(matches!(
+            run_with(&mut Interpreter::new(), input),
+            Err(Error::ArityMismatch {
+                expected: 0,
+                found: 1,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn methods() {
+        let input = "
+            class Counter {
+                increment() { this.count = this.count + 1; return this; }
+                get() { return this.count; }
+            }
+            var counter = Counter();
+            counter.count = 0;
+            counter.increment().increment();
+            var increment = counter.increment;
+            increment();
+            var a = counter.get();
+            var b = increment;
+        ";
+        assert_eq!(run_and_get(input, "a"), Value::Number(3.0));
+        assert_eq!(run_and_get(input, "b").to_string(), "<fn increment>");
+
+        // Fields shadow methods; `this` in closures refers to the receiver.
+        let input = "
+            class A {
+                name() { return \"method\"; }
+                getter() { fun f() { return this.label; } return f; }
+            }
+            var obj = A();
+            obj.label = \"obj\";
+            var getter = obj.getter();
+            var a = getter();
+            obj.name = \"field\";
+            var b = obj.name;
+        ";
+        assert_eq!(run_and_get(input, "a"), Value::String("obj".into()));
+        assert_eq!(run_and_get(input, "b"), Value::String("field".into()));
+
+        let input = "print this;";
+        assert_eq!(
+            run_with(&mut Interpreter::new(), input).unwrap_err(),
+            Error::UndefinedVariable {
+                src: input.to_string(),
+                at: (6, 4).into(),
+                name: "this".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn null_safe_access() {
+        let input = "
+            class A { init() { this.x = 1; } m(n) { return this.x + n; } }
+            fun call(object) { return object?.m(2); }
+            var a = A();
+            var none = nil;
+            var x = a?.x;
+            var y = a?.m(1);
+            var z = none?.x;
+            var w = none?.m(undefined);
+            var v = call(nil);
+            var u = none?.b.c;
+            var t = none?.m().c(undefined).d;
+        ";
+        let statements = extended(input).parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret(input, &statements).unwrap();
+        let get = |name| interpreter.environment.borrow().get(name).unwrap();
+        assert_eq!(get("x"), Value::Number(1.0));
+        assert_eq!(get("y"), Value::Number(2.0));
+        assert_eq!(get("z"), Value::Nil);
+        // The call is skipped, arguments included.
+        assert_eq!(get("w"), Value::Nil);
+        assert_eq!(get("v"), Value::Nil);
+        // As is the rest of the chain.
+        assert_eq!(get("u"), Value::Nil);
+        assert_eq!(get("t"), Value::Nil);
+
+        // Parentheses end the chain.
+        let input = "(nil?.b).c";
+        let expr = extended(input).parse_expression().unwrap();
+        assert_eq!(
+            Interpreter::new().evaluate(input, &expr).unwrap_err(),
+            Error::NotAnInstance {
+                src: input.to_string(),
+                at: (0, 8).into(),
+                found: "nil",
+            }
+        );
+
+        // Only `nil` short-circuits.
+        let input = "1?.x";
+        let expr = extended(input).parse_expression().unwrap();
+        assert_eq!(
+            Interpreter::new().evaluate(input, &expr).unwrap_err(),
+            Error::NotAnInstance {
+                src: input.to_string(),
+                at: (0, 1).into(),
+                found: "a number",
+            }
+        );
+    }
+
+    #[test]
+    fn updates() {
+        let input = "
+            class A {}
+            var object = A();
+            object.n = 1;
+            var a = 1;
+            var b = a++;
+            var c = ++a;
+            var d = object.n--;
+            var e = --object.n;
+            var n = object.n;
+            fun counter() { var i = 0; fun next() { return i++; } return next; }
+            var next = counter();
+            next();
+            var f = next();
+        ";
+        let statements = extended(input).parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret(input, &statements).unwrap();
+        let get = |name| interpreter.environment.borrow().get(name).unwrap();
+        // Postfix operators yield the previous value, prefix ones the new one.
+        assert_eq!(get("a"), Value::Number(3.0));
+        assert_eq!(get("b"), Value::Number(1.0));
+        assert_eq!(get("c"), Value::Number(3.0));
+        assert_eq!(get("d"), Value::Number(1.0));
+        assert_eq!(get("e"), Value::Number(-1.0));
+        assert_eq!(get("n"), Value::Number(-1.0));
+        assert_eq!(get("f"), Value::Number(1.0));
+
+        let input = "var s = \"a\"; s++;";
+        let statements = extended(input).parse().unwrap();
+        assert_eq!(
+            Interpreter::new()
+                .interpret(input, &statements)
+                .unwrap_err(),
+            Error::OperandMustBeNumber {
+                src: input.to_string(),
+                at: (13, 1).into(),
+                found: "a string",
+            }
+        );
+
+        // Updates of other targets, e.g. built by a transform, fail at runtime.
+        let input = "1";
+        let target = extended(input).parse_expression().unwrap();
+        let update = Expr::Update(Update {
+            operator: UpdateOperator::Increment,
+            target: &target,
+            prefix: true,
+            span: TokenSpan::new(0, 0),
+        });
+        assert_eq!(
+            Interpreter::new().evaluate(input, &update).unwrap_err(),
+            Error::InvalidAssignmentTarget {
+                src: input.to_string(),
+                at: (0, 1).into(),
+            }
+        );
+    }
+
+    #[test]
+    fn inheritance() {
+        let input = "
+            class A {
+                name() { return \"A\"; }
+                describe() { return \"I am \" + this.name(); }
+            }
+            class B < A {
+                name() { return \"B\"; }
+                describe() { return super.describe() + \" (via \" + super.name() + \")\"; }
+            }
+            class C < B {}
+            var a = C().describe();
+            var b = A().describe();
+        ";
+        assert_eq!(
+            run_and_get(input, "a"),
+            Value::String("I am B (via A)".into())
+        );
+        assert_eq!(run_and_get(input, "b"), Value::String("I am A".into()));
+
+        let input = "var A = \"not a class\"; class B < A {}";
+        assert_eq!(
+            run_with(&mut Interpreter::new(), input).unwrap_err(),
+            Error::SuperclassMustBeClass {
+                src: input.to_string(),
+                at: (33, 1).into(),
+                found: "a string",
+            }
+        );
+        let input = "class A {} class B < A { m() { return super.missing; } } B().m();";
+        assert_eq!(
+            run_with(&mut Interpreter::new(), input).unwrap_err(),
+            Error::UndefinedProperty {
+                src: input.to_string(),
+                at: (44, 7).into(),
+                name: "missing".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn initializers() {
+        let input = "
+            class Point {
+                init(x, y) { this.x = x; this.y = y; }
+                sum() { return this.x + this.y; }
+            }
+            class Origin < Point {
+                init() { super.init(0, 0); return; }
+            }
+            var p = Point(1, 2);
+            var a = p.sum();
+            var b = p.init(3, 4) == p;
+            var c = p.sum();
+            var d = Origin().sum();
+        ";
+        assert_eq!(run_and_get(input, "a"), Value::Number(3.0));
+        assert_eq!(run_and_get(input, "b"), Value::Boolean(true));
+        assert_eq!(run_and_get(input, "c"), Value::Number(7.0));
+        assert_eq!(run_and_get(input, "d"), Value::Number(0.0));
+
+        // A free function called `init` is not an initializer.
+        let input = "fun init() { return 1; } var a = init();";
+        assert_eq!(run_and_get(input, "a"), Value::Number(1.0));
+
+        let input = "class A { init(a) {} } A();";
+        assert_eq!(
+            run_with(&mut Interpreter::new(), input).unwrap_err(),
+            Error::ArityMismatch {
+                src: input.to_string(),
+                at: (23, 3).into(),
+                expected: 1,
+                found: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let input = "1 / (2 - 2)";
+        assert_eq!(eval(input).unwrap_err(), Error::DivisionByZero {
+            src: input.to_string(),
+            at: (4, 7).into(),
+        });
+        assert_eval("0 / 2", "0");
+
+        let mut interpreter = Interpreter::new().with_division_by_zero(DivisionByZero::Infinity);
+        assert_eq!(
+            eval_with(&mut interpreter, "-1 / 0").unwrap(),
+            Value::Number(f64::NEG_INFINITY)
+        );
+        assert!(matches!(
+            eval_with(&mut interpreter, "0 / 0").unwrap(),
+            Value::Number(n) if n.is_nan()
+        ));
+    }
+
+    #[test]
+    fn call_depth() {
+        let input = "fun f(n) { if (n > 0) f(n - 1); return \"done\"; }";
+        let mut interpreter = Interpreter::new().with_max_call_depth(10);
+        run_with(&mut interpreter, input).unwrap();
+        assert_eq!(
+            eval_with(&mut interpreter, "f(9)").unwrap(),
+            Value::String("done".into())
+        );
+        assert_eq!(
+            eval_with(&mut interpreter, "f(10)").unwrap_err(),
+            Error::StackOverflow {
+                src: input.to_string(),
+                at: (22, 8).into(),
+                max_depth: 10,
+            }
+        );
+        // The depth is back to zero after the failure.
+        assert!(eval_with(&mut interpreter, "f(9)").is_ok());
+
+        // Unbounded recursion fails without overflowing the native stack.
+        let input = "fun f() { f(); } f();";
+        assert!(matches!(
+            run_with(&mut Interpreter::new(), input),
+            Err(Error::StackOverflow {
+                max_depth: DEFAULT_MAX_CALL_DEPTH,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn stack_limit() {
+        // Nesting makes every call take far more native stack than a bare
+        // recursive call, so the call depth limit alone doesn't hold.
+        let input = "
+            class A {
+                go(n) {
+                    if (n > 0) {
+                        while (true) {
+                            { { var x = ((1 + 2) * (3 - 4)) + ((n * 2) - (n / 2)); } }
+                            A().go(n - 1);
+                            return nil;
+                        }
+                    }
+                }
+            }
+            A().go(100000);";
+        let result = std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(|| {
+                let mut interpreter = Interpreter::new()
+                    .with_max_call_depth(1_000_000)
+                    .with_stack_limit(4 << 20);
+                run_with(&mut interpreter, input)
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(matches!(
+            result,
+            Err(Error::StackExhausted { limit, .. }) if limit == 4 << 20
+        ));
+    }
+
+    #[test]
+    fn interrupt() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let mut interpreter = Interpreter::new().with_interrupt(flag.clone());
+        let input = "var i = 0; while (true) { i = i + 1; }";
+
+        // Raised before the run, so the first iteration is the last one.
+        flag.store(true, Ordering::Relaxed);
+        assert_eq!(
+            run_with(&mut interpreter, input).unwrap_err(),
+            Error::Interrupted {
+                src: input.to_string(),
+                at: (11, 5).into(),
+            }
+        );
+        assert!(!flag.load(Ordering::Relaxed));
+        assert_eq!(
+            interpreter.environment.borrow().get("i"),
+            Some(Value::Number(1.0))
+        );
+
+        let input = "fun f() {} f();";
+        flag.store(true, Ordering::Relaxed);
+        assert!(matches!(
+            run_with(&mut interpreter, input),
+            Err(Error::Interrupted { .. })
+        ));
+        assert!(run_with(&mut interpreter, input).is_ok());
+    }
+
+    #[test]
+    fn output() {
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new().with_output(&mut output);
+        let input = "print 1 + 2; print \"a\"; print nil; print clock; print 1 < 2;";
+        run_with(&mut interpreter, input).unwrap();
+        drop(interpreter);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "3\na\nnil\n<native fn>\ntrue\n"
+        );
+
+        struct Closed;
+        impl Write for Closed {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::from(io::ErrorKind::BrokenPipe))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let input = "print 1;";
+        assert_eq!(
+            run_with(&mut Interpreter::new().with_output(Closed), input).unwrap_err(),
+            Error::Output {
+                src: input.to_string(),
+                at: (0, 5).into(),
+                reason: "broken pipe".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn plain_strings() {
+        // Interpolation is an extension of the language, so `${` is plain text
+        // in classic Lox.
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new().with_output(&mut output);
+        let input = "var x = 1; print \"cost: ${x}\"; print \"${\";";
+        run_with(&mut interpreter, input).unwrap();
+        drop(interpreter);
+        assert_eq!(String::from_utf8(output).unwrap(), "cost: ${x}\n${\n");
+    }
+
+    #[test]
+    fn step_limit() {
+        // `1 + 2` is three steps: the binary expression and its operands.
+        let mut interpreter = Interpreter::new().with_step_limit(4);
+        assert_eq!(
+            eval_with(&mut interpreter, "1 + 2").unwrap(),
+            Value::Number(3.0)
+        );
+        let input = "-1";
+        assert_eq!(
+            eval_with(&mut interpreter, input).unwrap_err(),
+            Error::BudgetExceeded {
+                src: input.to_string(),
+                at: (1, 1).into(),
+                limit: 4,
+            }
+        );
+
+        let input = "while (true) {}";
+        assert!(matches!(
+            run_with(&mut Interpreter::new().with_step_limit(1000), input),
+            Err(Error::BudgetExceeded { limit: 1000, .. })
+        ));
+    }
+
+    #[test]
+    fn memory_limit() {
+        // Strings that are no longer referenced don't count.
+        let input = "
+            var i = 0;
+            while (i < 1000) { var s = \"abc\" + \"def\"; i = i + 1; }
+            class A {}
+            var a = A();
+            a.x = 1;
+            fun f() {}
+        ";
+        run_with(&mut Interpreter::new().with_memory_limit(1024), input).unwrap();
+
+        let input = "var s = \"a\"; while (true) s = s + s;";
+        assert_eq!(
+            run_with(&mut Interpreter::new().with_memory_limit(1024), input).unwrap_err(),
+            Error::OutOfMemory {
+                src: input.to_string(),
+                at: (32, 1).into(),
+                limit: 1024,
+            }
+        );
+
+        let input = "class A {} var list; while (true) { var a = A(); a.next = list; list = a; }";
+        assert!(matches!(
+            run_with(&mut Interpreter::new().with_memory_limit(1024), input),
+            Err(Error::OutOfMemory { limit: 1024, .. })
+        ));
+    }
+
+    #[test]
+    fn memory_stats() {
+        let input = "
+            var i = 0;
+            while (i < 1000) { var s = \"abc\" + \"def\"; i = i + 1; }
+            class A {}
+            var a = A();
+            a.x = 1;
+            a.y = 2;
+            fun f() {}
+            var s = \"abc\" + \"def\";
+            var t = \"literal\";
+        ";
+        let mut interpreter = Interpreter::new().with_memory_stats();
+        run_with(&mut interpreter, input).unwrap();
+        let stats = interpreter.memory_stats();
+        // `a`, `f`, `s` and `t`, the instance counting once despite its new
+        // fields.
+        assert_eq!(stats.live_objects, 4);
+        assert_eq!(stats.interned_strings, 2);
+        // Only the global scope is left once the blocks are done.
+        assert_eq!(stats.live_environments, 1);
+        assert!(stats.peak_bytes > 0 && stats.peak_bytes < 1024);
+
+        // Closures keep the scopes they are declared in alive: the scope of
+        // the call, then one per local declaration.
+        let input = "
+            fun counter() { var n = 0; fun count() { n = n + 1; } return count; }
+            var c = counter();
+        ";
+        let mut interpreter = Interpreter::new().with_memory_stats();
+        run_with(&mut interpreter, input).unwrap();
+        assert_eq!(interpreter.memory_stats().live_environments, 4);
+
+        // Untracked.
+        let mut interpreter = Interpreter::new();
+        run_with(&mut interpreter, input).unwrap();
+        assert_eq!(interpreter.memory_stats(), MemoryStats::default());
+    }
+
+    #[test]
+    fn tail_calls() {
+        // Far deeper than the call depth limit.
+        let input = "
+            fun count(n, acc) { if (n == 0) return acc; return count(n - 1, acc + 1); }
+            fun isEven(n) { if (n == 0) return true; return isOdd(n - 1); }
+            fun isOdd(n) { if (n == 0) return false; return isEven(n - 1); }
+            var a = count(10000, 0);
+            var b = isEven(10001);
+        ";
+        assert_eq!(run_and_get(input, "a"), Value::Number(10000.0));
+        assert_eq!(run_and_get(input, "b"), Value::Boolean(false));
+
+        // Tail calls to natives and classes, and errors reported at the call.
+        let input = "
+            class A { init(x) { this.x = x; } }
+            fun make(x) { return A(x); }
+            fun now() { return clock(); }
+            var a = make(1).x;
+            var b = now() > 0;
+        ";
+        assert_eq!(run_and_get(input, "a"), Value::Number(1.0));
+        assert_eq!(run_and_get(input, "b"), Value::Boolean(true));
+        let input = "fun f(a) { return f(); } f(1);";
+        assert_eq!(
+            run_with(&mut Interpreter::new(), input).unwrap_err(),
+            Error::ArityMismatch {
+                src: input.to_string(),
+                at: (18, 3).into(),
+                expected: 1,
+                found: 0,
+            }
+        );
+
+        // Initializers still return `this`, after making the tail call.
+        let input = "
+            var calls = 0;
+            fun touch() { calls = calls + 1; return 42; }
+            class A { init() { return touch(); } }
+            var a = A();
+            a.init();
+        ";
+        assert_eq!(run_and_get(input, "a").to_string(), "A instance");
+        assert_eq!(run_and_get(input, "calls"), Value::Number(2.0));
+    }
+
+    #[test]
+    fn globals() {
+        let mut interpreter = Interpreter::new();
+        run_with(&mut interpreter, "var b = 1; { var c = 2; } fun a() {}").unwrap();
+        let globals: Vec<_> = interpreter
+            .globals()
+            .into_iter()
+            .map(|(name, value)| format!("{name} = {value}"))
+            .collect();
+        assert_eq!(globals, &["a = <fn a>", "b = 1", "clock = <native fn>"]);
+    }
+
+    #[test]
+    fn interned_strings() {
+        let mut interpreter = Interpreter::new();
+        let input = "var a = \"ab\"; var b = \"a\" + \"b\"; var c = \"ab\";";
+        run_with(&mut interpreter, input).unwrap();
+        let globals = interpreter.globals.borrow();
+        let strings = ["a", "b", "c"].map(|name| match globals.get(name) {
+            Some(Value::String(string)) => string,
+            value => panic!("expected a string, found {value:?}"),
+        });
+        assert!(Rc::ptr_eq(&strings[0], &strings[1]));
+        assert!(Rc::ptr_eq(&strings[0], &strings[2]));
+    }
+}
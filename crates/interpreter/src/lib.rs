@@ -0,0 +1,18 @@
+mod builtins;
+pub mod class;
+pub mod environment;
+pub mod error;
+pub mod function;
+mod interner;
+pub mod interpreter;
+mod memory;
+pub mod value;
+
+pub use {
+    class::{LoxClass, LoxInstance},
+    error::{Error, Result},
+    function::{Callable, LoxFunction, NativeFunction},
+    interpreter::{DivisionByZero, Interpreter},
+    memory::MemoryStats,
+    value::Value,
+};
@@ -0,0 +1,191 @@
+use {
+    crate::{class::LoxInstance, environment::Environment, function::LoxFunction},
+    std::{
+        cell::RefCell,
+        collections::HashSet,
+        mem,
+        rc::{Rc, Weak},
+    },
+};
+
+/// Approximate size of a reference counted value: the value itself, and the
+/// reference counts.
+pub(crate) fn rc_size<T: ?Sized>(value: &T) -> usize {
+    mem::size_of_val(value) + 2 * mem::size_of::<usize>()
+}
+
+/// Value allocated on the heap by a running program.
+#[derive(Debug)]
+pub(crate) enum Object<'a> {
+    String(Weak<str>),
+    Instance(Weak<LoxInstance<'a>>),
+    Function(Weak<LoxFunction<'a>>),
+}
+
+impl Object<'_> {
+    /// Whether the object is still referenced by the program.
+    fn is_alive(&self) -> bool {
+        match self {
+            // Strings are interned, so the interner holds a reference too.
+            Self::String(string) => string.strong_count() > 1,
+            Self::Instance(instance) => instance.strong_count() > 0,
+            Self::Function(function) => function.strong_count() > 0,
+        }
+    }
+
+    /// Address of the object, which identifies it while it's alive.
+    fn address(&self) -> *const () {
+        match self {
+            Self::String(string) => string.as_ptr().cast(),
+            Self::Instance(instance) => instance.as_ptr().cast(),
+            Self::Function(function) => function.as_ptr().cast(),
+        }
+    }
+}
+
+/// Minimum number of bytes used before an unlimited heap is swept.
+const MIN_SWEEP_BYTES: usize = 64 << 10;
+
+/// Minimum number of tracked environments before they are swept.
+const MIN_SWEEP_ENVIRONMENTS: usize = 1024;
+
+/// Memory usage of a program, see [`Interpreter::memory_stats`].
+///
+/// [`Interpreter::memory_stats`]: crate::Interpreter::memory_stats
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Maximum number of bytes used by strings, instances and closures at
+    /// once. Dropped objects count until they are swept, so this is an upper
+    /// bound.
+    pub peak_bytes: usize,
+
+    /// Number of strings, instances and closures still referenced.
+    pub live_objects: usize,
+
+    /// Number of environments (the scopes of blocks, calls and declarations)
+    /// still referenced, by running code or by closures.
+    pub live_environments: usize,
+
+    /// Number of distinct strings still referenced.
+    pub interned_strings: usize,
+}
+
+/// Approximate accounting of the memory used by the objects of a program,
+/// against an optional limit.
+///
+/// Objects, and environments, are only tracked when there is a limit, or when
+/// the statistics are asked for. Dropped objects are not noticed right away:
+/// the memory they used is reclaimed by a sweep, when the limit would otherwise
+/// be exceeded, or once the memory used has doubled since the last sweep, if
+/// unlimited.
+#[derive(Debug, Default)]
+pub(crate) struct Heap<'a> {
+    limit: Option<usize>,
+
+    /// Whether objects are tracked, even though there is no limit.
+    tracked: bool,
+
+    /// Number of bytes used by the tracked objects, including dropped ones
+    /// since the last sweep.
+    used: usize,
+
+    /// Highest number of bytes used so far.
+    peak: usize,
+
+    /// Number of bytes used past which an unlimited heap is swept.
+    next_sweep: usize,
+
+    /// Tracked objects, with their size in bytes.
+    objects: Vec<(Object<'a>, usize)>,
+
+    /// Tracked environments. They don't count towards the limit, which is
+    /// about the memory the program itself allocates.
+    environments: Vec<Weak<RefCell<Environment<'a>>>>,
+
+    /// Number of tracked environments past which they are swept.
+    next_environment_sweep: usize,
+}
+
+impl<'a> Heap<'a> {
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = Some(limit);
+    }
+
+    /// Tracks objects even if there is no limit, for [`Self::stats`].
+    pub fn track(&mut self) {
+        self.tracked = true;
+    }
+
+    /// Tracks a newly allocated object of the given size.
+    ///
+    /// Returns `false` if that takes the memory used by live objects over the
+    /// limit.
+    pub fn allocate(&mut self, object: Object<'a>, size: usize) -> bool {
+        if self.limit.is_none() && !self.tracked {
+            return true;
+        }
+        self.used += size;
+        self.objects.push((object, size));
+        let sweep_at = self
+            .limit
+            .unwrap_or_else(|| self.next_sweep.max(MIN_SWEEP_BYTES));
+        if self.used > sweep_at {
+            self.sweep();
+        }
+        self.peak = self.peak.max(self.used);
+        self.limit.is_none_or(|limit| self.used <= limit)
+    }
+
+    /// Tracks a newly created environment, if objects are tracked.
+    pub fn track_environment(&mut self, environment: &Rc<RefCell<Environment<'a>>>) {
+        if self.limit.is_none() && !self.tracked {
+            return;
+        }
+        self.environments.push(Rc::downgrade(environment));
+        if self.environments.len() > self.next_environment_sweep.max(MIN_SWEEP_ENVIRONMENTS) {
+            self.sweep_environments();
+        }
+    }
+
+    /// Returns the statistics of the tracked objects and environments, but for
+    /// the interned strings, which the heap doesn't know about.
+    pub fn stats(&mut self) -> MemoryStats {
+        self.sweep();
+        self.sweep_environments();
+        // Instances are tracked again as they grow new fields.
+        let live: HashSet<_> = self
+            .objects
+            .iter()
+            .map(|(object, _)| object.address())
+            .collect();
+        MemoryStats {
+            peak_bytes: self.peak,
+            live_objects: live.len(),
+            live_environments: self
+                .environments
+                .iter()
+                .map(Weak::as_ptr)
+                .collect::<HashSet<_>>()
+                .len(),
+            interned_strings: 0,
+        }
+    }
+
+    /// Stops tracking dropped objects.
+    fn sweep(&mut self) {
+        self.objects.retain(|(object, _)| object.is_alive());
+        self.used = self.objects.iter().map(|(_, size)| size).sum();
+        self.next_sweep = 2 * self.used;
+    }
+
+    /// Stops tracking dropped environments.
+    fn sweep_environments(&mut self) {
+        self.environments
+            .retain(|environment| environment.strong_count() > 0);
+        self.next_environment_sweep = 2 * self.environments.len();
+    }
+}
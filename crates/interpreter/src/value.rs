@@ -0,0 +1,146 @@
+use {
+    crate::{
+        class::{LoxClass, LoxInstance},
+        function::{LoxFunction, NativeFunction},
+    },
+    core::fmt,
+    parser::grammar::LiteralValue,
+    std::rc::Rc,
+};
+
+/// Runtime value of a Lox expression.
+#[derive(Debug, Clone)]
+pub enum Value<'a> {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(Rc<str>),
+    Function(Rc<LoxFunction<'a>>),
+    Native(Rc<NativeFunction<'a>>),
+    Class(Rc<LoxClass<'a>>),
+    Instance(Rc<LoxInstance<'a>>),
+}
+
+impl Value<'_> {
+    /// Lox truthiness: `nil` and `false` are falsey, everything else is truthy.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Self::Nil | Self::Boolean(false))
+    }
+
+    /// Name of the value's type, as used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Nil => "nil",
+            Self::Boolean(_) => "a boolean",
+            Self::Number(_) => "a number",
+            Self::String(_) => "a string",
+            Self::Function(_) | Self::Native(_) => "a function",
+            Self::Class(_) => "a class",
+            Self::Instance(_) => "an instance",
+        }
+    }
+}
+
+/// Primitive values are equal by value, functions, classes and instances by
+/// identity.
+impl PartialEq for Value<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Nil, Self::Nil) => true,
+            (Self::Boolean(a), Self::Boolean(b)) => a == b,
+            (Self::Number(a), Self::Number(b)) => a == b,
+            // Strings of a program are interned, so equal strings are the
+            // same allocation, except for the ones made outside of it.
+            (Self::String(a), Self::String(b)) => Rc::ptr_eq(a, b) || a == b,
+            (Self::Function(a), Self::Function(b)) => Rc::ptr_eq(a, b),
+            (Self::Native(a), Self::Native(b)) => Rc::ptr_eq(a, b),
+            (Self::Class(a), Self::Class(b)) => Rc::ptr_eq(a, b),
+            (Self::Instance(a), Self::Instance(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl From<LiteralValue<'_>> for Value<'_> {
+    fn from(literal: LiteralValue<'_>) -> Self {
+        match literal {
+            LiteralValue::Nil => Self::Nil,
+            LiteralValue::Boolean(b) => Self::Boolean(b),
+            LiteralValue::Number(n) => Self::Number(n),
+            LiteralValue::String(s) => Self::String(s.into()),
+        }
+    }
+}
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nil => write!(f, "nil"),
+            Self::Boolean(b) => write!(f, "{b}"),
+            Self::Number(n) => fmt_number(f, *n),
+            Self::String(s) => write!(f, "{s}"),
+            Self::Function(function) => write!(f, "<fn {}>", function.name()),
+            Self::Native(_) => write!(f, "<native fn>"),
+            Self::Class(class) => write!(f, "{}", class.name()),
+            Self::Instance(instance) => write!(f, "{} instance", instance.class().name()),
+        }
+    }
+}
+
+/// Formats a number as jlox does, i.e. as Java's `Double.toString()` without
+/// the trailing `.0` of integers.
+fn fmt_number(f: &mut fmt::Formatter<'_>, n: f64) -> fmt::Result {
+    if n.is_nan() {
+        return write!(f, "NaN");
+    }
+    if n.is_infinite() {
+        let sign = if n < 0.0 { "-" } else { "" };
+        return write!(f, "{sign}Infinity");
+    }
+    if n == 0.0 || (1e-3..1e7).contains(&n.abs()) {
+        // Shortest representation that round-trips, with no `.0` for integers.
+        return write!(f, "{n}");
+    }
+    // Java switches to scientific notation, with at least one fractional digit.
+    let scientific = format!("{n:e}");
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("scientific notation has an exponent");
+    if mantissa.contains('.') {
+        write!(f, "{mantissa}E{exponent}")
+    } else {
+        write!(f, "{mantissa}.0E{exponent}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn number(n: f64) -> String {
+        Value::Number(n).to_string()
+    }
+
+    #[test]
+    fn number_formatting() {
+        assert_eq!(number(2.0), "2");
+        assert_eq!(number(2.5), "2.5");
+        assert_eq!(number(-3.0), "-3");
+        assert_eq!(number(0.0), "0");
+        assert_eq!(number(-0.0), "-0");
+        assert_eq!(number(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(number(1234567.0), "1234567");
+        assert_eq!(number(0.001), "0.001");
+
+        // Out of [1e-3, 1e7), in scientific notation.
+        assert_eq!(number(1e7), "1.0E7");
+        assert_eq!(number(12345678.0), "1.2345678E7");
+        assert_eq!(number(-1e21), "-1.0E21");
+        assert_eq!(number(0.0001), "1.0E-4");
+        assert_eq!(number(1.5e-7), "1.5E-7");
+
+        assert_eq!(number(f64::NAN), "NaN");
+        assert_eq!(number(f64::INFINITY), "Infinity");
+        assert_eq!(number(f64::NEG_INFINITY), "-Infinity");
+    }
+}
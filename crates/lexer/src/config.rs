@@ -0,0 +1,42 @@
+use crate::token::TokenKind;
+
+/// Version of the language the lexer accepts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LanguageLevel {
+    /// Lox as defined by Crafting Interpreters.
+    #[default]
+    Classic,
+
+    /// Lox with extension operators: `%`, `?`, `:`, `+=`, `**`, `?.`, `++` and
+    /// `--`, and with string interpolation, e.g. `"a ${b}"`.
+    Extended,
+}
+
+impl LanguageLevel {
+    /// Whether tokens of the given kind are part of the language at this level.
+    pub fn allows(self, kind: TokenKind) -> bool {
+        match kind {
+            TokenKind::Percent
+            | TokenKind::Question
+            | TokenKind::Colon
+            | TokenKind::PlusEqual
+            | TokenKind::StarStar
+            | TokenKind::QuestionDot
+            | TokenKind::PlusPlus
+            | TokenKind::MinusMinus
+            | TokenKind::InterpolationStart
+            | TokenKind::InterpolationMiddle
+            | TokenKind::InterpolationEnd => self == Self::Extended,
+            _ => true,
+        }
+    }
+}
+
+/// Options of a [`Lexer`](crate::Lexer).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LexerConfig {
+    pub level: LanguageLevel,
+
+    /// Whether comments are produced as tokens, rather than skipped.
+    pub comments: bool,
+}
@@ -0,0 +1,77 @@
+use std::str::Chars;
+
+/// Persistent cursor over the characters of the source code.
+///
+/// The cursor keeps a single `Chars` iterator alive for the whole scan, so
+/// characters are decoded once, and the byte position is derived from the
+/// length of the remaining input.
+#[derive(Clone)]
+pub(crate) struct Cursor<'a> {
+    /// Reference to input source code.
+    src: &'a str,
+
+    /// Characters that are yet to be consumed.
+    chars: Chars<'a>,
+}
+
+impl<'a> Cursor<'a> {
+    /// Creates a new cursor pointing at the beginning of the input.
+    pub fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            chars: src.chars(),
+        }
+    }
+
+    /// Creates a new cursor pointing at the given offset of the input.
+    pub fn at(src: &'a str, offset: usize) -> Self {
+        Self {
+            src,
+            chars: src[offset..].chars(),
+        }
+    }
+
+    /// Absolute byte position starting from the beginning of input code.
+    pub fn pos(&self) -> usize {
+        self.src.len() - self.chars.as_str().len()
+    }
+
+    /// Returns the next character without consuming it.
+    pub fn peek(&self) -> Option<char> {
+        // Fast path: most of the source is ASCII, which needs no decoding.
+        match self.chars.as_str().as_bytes().first() {
+            Some(&b) if b.is_ascii() => Some(b as char),
+            Some(_) => self.chars.clone().next(),
+            None => None,
+        }
+    }
+
+    /// Returns the character after the next one without consuming anything.
+    pub fn peek_second(&self) -> Option<char> {
+        let mut chars = self.chars.clone();
+        chars.next();
+        chars.next()
+    }
+
+    /// Consumes the next character.
+    pub fn bump(&mut self) -> Option<char> {
+        self.chars.next()
+    }
+
+    /// Consumes the next character if it is equal to `c`.
+    pub fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.chars.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes characters while the predicate holds.
+    pub fn eat_while(&mut self, mut predicate: impl FnMut(char) -> bool) {
+        let rest = self.chars.as_str();
+        let len = rest.find(|c| !predicate(c)).unwrap_or(rest.len());
+        self.chars = rest[len..].chars();
+    }
+}
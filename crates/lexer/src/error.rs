@@ -34,6 +34,42 @@ pub enum Error {
         at: SourceSpan,
     },
 
+    #[error("Unterminated string interpolation")]
+    #[diagnostic(
+        code(lexer::unterminated_interpolation),
+        help("close the interpolated expression with `}}`")
+    )]
+    UnterminatedInterpolation {
+        #[source_code]
+        src: String,
+        #[label("interpolation starts here")]
+        at: SourceSpan,
+    },
+
+    #[error("Number literal ends with a separator")]
+    #[diagnostic(
+        code(lexer::trailing_separator),
+        help("`_` is only allowed between digits")
+    )]
+    TrailingSeparator {
+        #[source_code]
+        src: String,
+        #[label("here")]
+        at: SourceSpan,
+    },
+
+    #[error("Consecutive separators in number literal")]
+    #[diagnostic(
+        code(lexer::consecutive_separators),
+        help("use a single `_` between digits")
+    )]
+    ConsecutiveSeparators {
+        #[source_code]
+        src: String,
+        #[label("here")]
+        at: SourceSpan,
+    },
+
     #[error("Unterminated block comment")]
     #[diagnostic(code(lexer::unterminated_block_comment))]
     UnterminatedBlockComment {
@@ -44,4 +80,17 @@ pub enum Error {
     },
 }
 
+impl Error {
+    /// Whether the error is about a string, comment or interpolation that the
+    /// source ends in, and that more input could terminate.
+    pub fn is_unterminated(&self) -> bool {
+        matches!(
+            self,
+            Self::UnterminatedString { .. }
+                | Self::UnterminatedInterpolation { .. }
+                | Self::UnterminatedBlockComment { .. }
+        )
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
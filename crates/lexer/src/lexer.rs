@@ -1,10 +1,14 @@
 use {
     crate::{
-        token::{Token, TokenKind, TokenSpan},
+        config::LexerConfig,
+        cursor::Cursor,
+        line_index::LineCol,
+        peekable::PeekableLexer,
+        token::{Literal, Token, TokenKind, TokenSpan, TriviaToken},
         Error,
         Result,
     },
-    std::ops::ControlFlow,
+    std::ops::{ControlFlow, Range},
 };
 
 /// Streaming lexer that produces tokens from the input source.
@@ -12,110 +16,402 @@ pub struct Lexer<'a> {
     /// Reference to input source code.
     src: &'a str,
 
-    /// Absolute position starting from the beginning of input code.
-    pos: usize,
+    /// Cursor over the characters that are yet to be consumed.
+    cursor: Cursor<'a>,
+
+    /// Start of the last token produced, with its line and column, from which
+    /// the position of the next one is computed.
+    last: (usize, LineCol),
+
+    /// Interpolated expressions of strings being lexed, innermost last: the
+    /// number of braces opened in the expression and not yet closed, and the
+    /// offset of its `${`.
+    interpolations: Vec<(usize, usize)>,
+
+    config: LexerConfig,
+
+    /// Whether a shebang line is allowed at the start of the source, which
+    /// isn't the case if the source is a chunk of a larger one.
+    shebang: bool,
 }
 
 impl<'a> Lexer<'a> {
     /// Creates a new lexer instance from the input source code.
     pub fn new(input: &'a str) -> Self {
-        Self { src: input, pos: 0 }
+        Self {
+            src: input,
+            cursor: Cursor::new(input),
+            last: (0, LineCol::START),
+            interpolations: Vec::new(),
+            config: LexerConfig::default(),
+            shebang: true,
+        }
+    }
+
+    /// Lexes a chunk of a larger source, that can't start with a shebang line.
+    pub(crate) fn chunk(input: &'a str) -> Self {
+        Self {
+            shebang: false,
+            ..Self::new(input)
+        }
+    }
+
+    /// Produces comments as `LineComment` and `BlockComment` tokens, e.g. for
+    /// a formatter to keep them. The parser skips them.
+    pub fn with_comments(mut self, comments: bool) -> Self {
+        self.config.comments = comments;
+        self
+    }
+
+    pub fn with_config(mut self, config: LexerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Returns the source code being tokenized.
+    pub fn source(&self) -> &'a str {
+        self.src
+    }
+
+    /// Returns an iterator over the tokens in the source code, ending with an
+    /// `Eof` token.
+    pub fn tokens(mut self) -> impl Iterator<Item = Result<Token<'a>>> {
+        let mut done = false;
+        std::iter::from_fn(move || match self.next() {
+            None if done => None,
+            None => {
+                done = true;
+                let offset = self.src.len();
+                Some(Ok(Token::eof(offset, self.line_col(offset))))
+            }
+            token => token,
+        })
+    }
+
+    /// Scans the whole source code, returning the tokens, ending with an `Eof`
+    /// token, and all the errors met along the way.
+    pub fn scan_all(self) -> (Vec<Token<'a>>, Vec<Error>) {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        for token in self.tokens() {
+            match token {
+                Ok(token) => tokens.push(token),
+                Err(e) => errors.push(e),
+            }
+        }
+        (tokens, errors)
     }
 
-    /// Returns an iterator over the tokens in the source code.
-    pub fn tokens(self) -> impl Iterator<Item = Result<Token<'a>>> {
-        let offset = self.src.len();
-        self.chain(std::iter::once(Ok(Token::eof(offset))))
+    /// Re-lexes the source after an edit, reusing the tokens of the previous
+    /// version of the source around the edited region, e.g. in an editor.
+    ///
+    /// The lexer is over the edited source, in which `new_text` replaced the
+    /// `range` of the previous source. Lexing restarts at the last previous
+    /// token which the edit can't extend, and stops once it lines up again with
+    /// a previous token after the edit; the tokens after that one are reused,
+    /// shifted.
+    ///
+    /// Returns all the tokens of the edited source, without `Eof`, like
+    /// collecting the lexer would. Errors are only reported for the re-lexed
+    /// part.
+    pub fn relex(
+        mut self,
+        range: Range<usize>,
+        new_text: &str,
+        previous: &[Token<'_>],
+    ) -> Vec<Result<Token<'a>>> {
+        let src = self.src;
+        let previous: Vec<_> = previous
+            .iter()
+            .filter(|t| t.kind != TokenKind::Eof)
+            .collect();
+
+        // Interpolations open before each previous token, and at the end: tokens
+        // can only be reused outside of interpolations, where lexing is
+        // independent of what came before.
+        let mut open = 0_usize;
+        let mut depths: Vec<_> = previous
+            .iter()
+            .map(|token| {
+                let depth = open;
+                match token.kind {
+                    TokenKind::InterpolationStart => open += 1,
+                    TokenKind::InterpolationEnd => open = depth.saturating_sub(1),
+                    _ => {}
+                }
+                depth
+            })
+            .collect();
+        depths.push(open);
+
+        // Keep the tokens ending at least two bytes before the edit: lexing a
+        // token looks up to two characters past its end, e.g. at `.5` after a
+        // number, so the edit could extend a token up to there.
+        let kept = (0..previous.len())
+            .rev()
+            .find(|&i| previous[i].span.end() + 1 < range.start && depths[i + 1] == 0)
+            .map_or(0, |i| i + 1);
+        let mut tokens: Vec<_> = previous[..kept]
+            .iter()
+            .map(|token| Ok(self.rebase(token, token.span, token.line_col)))
+            .collect();
+        if let Some(last) = kept.checked_sub(1).map(|i| previous[i]) {
+            self.cursor = Cursor::at(src, last.span.end());
+            self.last = (last.span.end(), last.line_col.advance(last.lexeme));
+        }
+
+        let edit_end = range.start + new_text.len();
+        // Offsets past the edit, from the current source to the previous one, and
+        // back.
+        let old_offset = |offset: usize| offset - new_text.len() + range.len();
+        let new_offset = |offset: usize| offset - range.len() + new_text.len();
+        loop {
+            let clean = self.interpolations.is_empty();
+            let Some(item) = self.next() else {
+                return tokens;
+            };
+            let synced = match &item {
+                Ok(token) if clean && token.span.start() >= edit_end => {
+                    let start = old_offset(token.span.start());
+                    let i = previous.partition_point(|p| p.span.start() < start);
+                    previous
+                        .get(i)
+                        .filter(|p| p.span.start() == start && p.kind == token.kind)
+                        .filter(|_| depths[i] == 0)
+                        .map(|_| (i, token.line_col))
+                }
+                _ => None,
+            };
+            let Some((i, new)) = synced else {
+                tokens.push(item);
+                continue;
+            };
+
+            // Shift the rest of the previous tokens: lines by the number of lines
+            // added, and columns too on the line the edit ends on.
+            let old = previous[i].line_col;
+            tokens.extend(previous[i..].iter().map(|token| {
+                let span =
+                    TokenSpan::new(new_offset(token.span.start()), new_offset(token.span.end()));
+                let line_col = if token.line_col.line == old.line {
+                    LineCol {
+                        line: new.line,
+                        column: token.line_col.column - old.column + new.column,
+                    }
+                } else {
+                    LineCol {
+                        line: token.line_col.line - old.line + new.line,
+                        ..token.line_col
+                    }
+                };
+                Ok(self.rebase(token, span, line_col))
+            }));
+            return tokens;
+        }
     }
 
-    /// Returns the remaining part of the source code.
-    fn remaining(&self) -> &str {
-        self.src.get(self.pos..).unwrap_or("")
+    /// Re-creates a token of the previous source at the given place of the
+    /// current one.
+    fn rebase(&self, token: &Token<'_>, span: TokenSpan, line_col: LineCol) -> Token<'a> {
+        let lexeme = &self.src[span.range()];
+        Token {
+            kind: token.kind,
+            lexeme,
+            span,
+            line_col,
+            literal: Literal::parse(token.kind, lexeme),
+        }
+    }
+
+    /// Wraps the lexer to allow looking at the next two tokens without
+    /// consuming them.
+    pub fn peekable(self) -> PeekableLexer<'a> {
+        PeekableLexer::new(self)
+    }
+
+    /// Returns an iterator over the tokens in the source code with their
+    /// trivia, ending with an `Eof` token holding the trivia at the end of
+    /// the source.
+    ///
+    /// The full spans of the tokens cover the source without gaps, so that it
+    /// can be reconstructed byte-for-byte, e.g. by a formatter. Input skipped
+    /// because of an error is part of the leading trivia of the next token.
+    pub fn lossless(self) -> impl Iterator<Item = Result<TriviaToken<'a>>> {
+        let src = self.src;
+        let mut tokens = self.with_comments(true).tokens().peekable();
+        let mut pos = 0;
+        std::iter::from_fn(move || {
+            let token = loop {
+                match tokens.next()? {
+                    // Only comments on their own line are left at this point.
+                    Ok(token) if token.kind.is_comment() => continue,
+                    Ok(token) => break token,
+                    Err(e) => return Some(Err(e)),
+                }
+            };
+            let leading = &src[pos..token.span.start()];
+
+            // Take the whitespace and comments up to the end of the line.
+            let mut end = token.span.end();
+            loop {
+                end = src.len() - src[end..].trim_start_matches([' ', '\t']).len();
+                let comment = tokens.next_if(
+                    |next| matches!(next, Ok(c) if c.kind.is_comment() && c.span.start() == end),
+                );
+                match comment {
+                    Some(Ok(comment)) => end = comment.span.end(),
+                    _ => break,
+                }
+            }
+            pos = end;
+            Some(Ok(TriviaToken {
+                leading,
+                token,
+                trailing: &src[token.span.end()..end],
+            }))
+        })
+    }
+
+    /// Returns the line and column of an offset past the start of the last
+    /// token, advancing from it, so that the whole scan stays linear.
+    fn line_col(&mut self, offset: usize) -> LineCol {
+        let (last, line_col) = self.last;
+        let line_col = line_col.advance(&self.src[last..offset]);
+        self.last = (offset, line_col);
+        line_col
+    }
+
+    /// Absolute position starting from the beginning of input code.
+    fn pos(&self) -> usize {
+        self.cursor.pos()
     }
 
     /// Process the next token from the source code.
     fn next_token(&mut self) -> Option<Result<Token<'a>>> {
         // Read until the full lexeme is consumed, then return it wrapped into token.
         loop {
-            if self.pos >= self.src.len() {
-                return None;
-            }
-
-            let c = self.remaining().chars().next()?;
-            let start = self.pos;
-            self.pos += c.len_utf8();
+            let start = self.pos();
+            let Some(c) = self.cursor.bump() else {
+                // Report interpolations left open, innermost first.
+                let (_, start) = self.interpolations.pop()?;
+                return Some(Err(Error::UnterminatedInterpolation {
+                    src: self.src.to_string(),
+                    at: (start, 2).into(),
+                }));
+            };
 
             break Some(match c {
-                '(' | ')' | '{' | '}' | ',' | '.' | '-' | '+' | ';' | '*' => {
-                    self.wrap(c.into(), (start, self.pos))
+                '{' => {
+                    if let Some((depth, _)) = self.interpolations.last_mut() {
+                        *depth += 1;
+                    }
+                    self.wrap(TokenKind::LeftBrace, (start, self.pos()))
                 }
-                '!' => self.op_with_eq(TokenKind::BangEqual, TokenKind::Bang),
-                '=' => self.op_with_eq(TokenKind::EqualEqual, TokenKind::Equal),
-                '>' => self.op_with_eq(TokenKind::GreaterEqual, TokenKind::Greater),
-                '<' => self.op_with_eq(TokenKind::LessEqual, TokenKind::Less),
-                '/' => match self.slash() {
+                '}' => match self.interpolations.last_mut() {
+                    // Closes the interpolated expression, the string goes on.
+                    Some((0, _)) => {
+                        self.interpolations.pop();
+                        self.string_literal(start, true)
+                    }
+                    Some((depth, _)) => {
+                        *depth -= 1;
+                        self.wrap(TokenKind::RightBrace, (start, self.pos()))
+                    }
+                    None => self.wrap(TokenKind::RightBrace, (start, self.pos())),
+                },
+                '!' => self.op_with_eq(start, TokenKind::BangEqual, TokenKind::Bang),
+                '=' => self.op_with_eq(start, TokenKind::EqualEqual, TokenKind::Equal),
+                '>' => self.op_with_eq(start, TokenKind::GreaterEqual, TokenKind::Greater),
+                '<' => self.op_with_eq(start, TokenKind::LessEqual, TokenKind::Less),
+                '+' if self.config.level.allows(TokenKind::PlusPlus) && self.cursor.eat('+') => {
+                    self.wrap(TokenKind::PlusPlus, (start, self.pos()))
+                }
+                '+' => self.op_extended(start, '=', TokenKind::PlusEqual, TokenKind::Plus),
+                '-' => self.op_extended(start, '-', TokenKind::MinusMinus, TokenKind::Minus),
+                '*' => self.op_extended(start, '*', TokenKind::StarStar, TokenKind::Star),
+                // At the classic level, `?` is an unexpected character, below.
+                '?' if self.config.level.allows(TokenKind::QuestionDot) => {
+                    self.op_extended(start, '.', TokenKind::QuestionDot, TokenKind::Question)
+                }
+                '/' => match self.slash(start) {
                     ControlFlow::Continue(_) => continue,
                     ControlFlow::Break(token) => token,
                 },
-                '"' => self.string_literal(),
-                c if c.is_ascii_digit() => self.number_literal(),
+                // Skip a shebang line, so that scripts can be made executable.
+                '#' if start == 0 && self.shebang && self.cursor.eat('!') => {
+                    self.cursor.eat_while(|c| c != '\n');
+                    continue;
+                }
+                '"' => self.string_literal(start, false),
+                c if c.is_ascii_digit() => self.number_literal(start),
                 c if is_alphanumeric(c) => self.identifier(start),
                 '\n' | '\r' | ' ' | '\t' => continue,
-                c => Err(Error::UnexpectedChar {
-                    c,
-                    src: self.src.to_string(),
-                    at: (self.pos - c.len_utf8(), c.len_utf8()).into(),
-                }),
+                c => match TokenKind::try_from(c) {
+                    Ok(kind) if self.config.level.allows(kind) => {
+                        self.wrap(kind, (start, self.pos()))
+                    }
+                    _ => Err(Error::UnexpectedChar {
+                        c,
+                        src: self.src.to_string(),
+                        at: (start, c.len_utf8()).into(),
+                    }),
+                },
             });
         }
     }
 
     /// Wrap the current lexeme into a token.
-    fn wrap(&self, kind: TokenKind, (start, end): (usize, usize)) -> Result<Token<'a>> {
+    fn wrap(&mut self, kind: TokenKind, (start, end): (usize, usize)) -> Result<Token<'a>> {
         let span = TokenSpan::new(start, end);
+        let lexeme = &self.src[span.range()];
         Ok(Token {
             kind,
-            lexeme: &self.src[span.range()],
+            lexeme,
             span,
+            line_col: self.line_col(start),
+            literal: Literal::parse(kind, lexeme),
         })
     }
 
     /// Process an operator that can be followed by an equal sign.
-    fn op_with_eq(&mut self, op_eq: TokenKind, op: TokenKind) -> Result<Token<'a>> {
-        if self.remaining().starts_with('=') {
-            self.pos += 1;
-            self.wrap(op_eq, (self.pos - 2, self.pos))
+    fn op_with_eq(&mut self, start: usize, op_eq: TokenKind, op: TokenKind) -> Result<Token<'a>> {
+        let kind = if self.cursor.eat('=') { op_eq } else { op };
+        self.wrap(kind, (start, self.pos()))
+    }
+
+    /// Process an operator that is extended into another one by the next
+    /// character, if the language level allows it.
+    fn op_extended(
+        &mut self,
+        start: usize,
+        next: char,
+        extended: TokenKind,
+        op: TokenKind,
+    ) -> Result<Token<'a>> {
+        let kind = if self.config.level.allows(extended) && self.cursor.eat(next) {
+            extended
         } else {
-            self.wrap(op, (self.pos - 1, self.pos))
-        }
+            op
+        };
+        self.wrap(kind, (start, self.pos()))
     }
 
     /// Process a slash character, which can be a line/block comment or a
     /// division operator.
-    fn slash(&mut self) -> ControlFlow<Result<Token<'a>>> {
-        let start = self.pos - 1;
-        if self.remaining().starts_with('/') {
-            // Skip the comment until the end of the line.
-            self.pos = self
-                .remaining()
-                .find('\n')
-                .map(|i| self.pos + i)
-                .unwrap_or_else(|| self.src.len());
-            return ControlFlow::Continue(());
+    fn slash(&mut self, start: usize) -> ControlFlow<Result<Token<'a>>> {
+        if self.cursor.eat('/') {
+            // The comment goes until the end of the line.
+            self.cursor.eat_while(|c| c != '\n');
+            return self.comment(TokenKind::LineComment, start);
         }
 
         // Allow multi-line comments, including nested ones.
-        if self.remaining().starts_with('*') {
+        if self.cursor.eat('*') {
             let mut depth = 1;
-            while let Some(c) = self.remaining().chars().next() {
-                self.pos += c.len_utf8();
+            while let Some(c) = self.cursor.bump() {
                 match c {
-                    '/' if self.remaining().starts_with('*') => {
-                        self.pos += 1;
-                        depth += 1;
-                    }
-                    '*' if self.remaining().starts_with('/') => {
-                        self.pos += 1;
+                    '/' if self.cursor.eat('*') => depth += 1,
+                    '*' if self.cursor.eat('/') => {
                         depth -= 1;
                         if depth == 0 {
                             break;
@@ -127,75 +423,122 @@ impl<'a> Lexer<'a> {
             return if depth != 0 {
                 ControlFlow::Break(Err(Error::UnterminatedBlockComment {
                     src: self.src.to_string(),
-                    at: (start, self.pos - start).into(),
+                    at: (start, self.pos() - start).into(),
                 }))
             } else {
-                // All content of the block comment is skipped.
-                ControlFlow::Continue(())
+                self.comment(TokenKind::BlockComment, start)
             };
         }
 
-        ControlFlow::Break(self.wrap(TokenKind::Slash, (start, self.pos)))
+        ControlFlow::Break(self.wrap(TokenKind::Slash, (start, self.pos())))
+    }
+
+    /// Produce the comment just consumed, or skip it, unless comments are
+    /// requested.
+    fn comment(&mut self, kind: TokenKind, start: usize) -> ControlFlow<Result<Token<'a>>> {
+        if self.config.comments {
+            ControlFlow::Break(self.wrap(kind, (start, self.pos())))
+        } else {
+            ControlFlow::Continue(())
+        }
     }
 
-    /// Process a string literal.
-    fn string_literal(&mut self) -> Result<Token<'a>> {
-        let start = self.pos - 1;
-        while let Some(c) = self.remaining().chars().next() {
-            self.pos += c.len_utf8();
-            if c == '"' {
-                return self.wrap(TokenKind::String, (start, self.pos));
+    /// Process a string literal, up to its end or to the next interpolated
+    /// expression (`${expr}`), if the language level allows interpolation.
+    ///
+    /// If `resumed`, the string goes on after an interpolated expression, and
+    /// `start` is the offset of the `}` closing it.
+    fn string_literal(&mut self, start: usize, resumed: bool) -> Result<Token<'a>> {
+        loop {
+            self.cursor.eat_while(|c| c != '"' && c != '$');
+            match self.cursor.bump() {
+                Some('"') => {
+                    let kind = if resumed {
+                        TokenKind::InterpolationEnd
+                    } else {
+                        TokenKind::String
+                    };
+                    return self.wrap(kind, (start, self.pos()));
+                }
+                Some('$')
+                    if self.config.level.allows(TokenKind::InterpolationStart)
+                        && self.cursor.eat('{') =>
+                {
+                    self.interpolations.push((0, self.pos() - 2));
+                    let kind = if resumed {
+                        TokenKind::InterpolationMiddle
+                    } else {
+                        TokenKind::InterpolationStart
+                    };
+                    return self.wrap(kind, (start, self.pos()));
+                }
+                // Otherwise, a `$` is part of the text.
+                Some(_) => continue,
+                None => break,
             }
         }
         Err(Error::UnterminatedString {
             src: self.src.to_string(),
-            at: (start, (self.pos - start).max(1)).into(),
+            at: (start, (self.pos() - start).max(1)).into(),
         })
     }
 
-    /// Process a number literal.
-    fn number_literal(&mut self) -> Result<Token<'a>> {
-        let start = self.pos - 1;
-        let consume_digits = |lexer: &mut Lexer<'_>| {
-            while let Some(c) = lexer.remaining().chars().next() {
-                if c.is_digit(10) {
-                    lexer.pos += c.len_utf8();
-                } else {
-                    break;
-                }
-            }
-        };
-
+    /// Process a number literal, whose digits can be grouped with `_`
+    /// separators, e.g. `1_000.5`.
+    fn number_literal(&mut self, start: usize) -> Result<Token<'a>> {
         // Consume whole part of the number.
-        consume_digits(self);
+        self.cursor.eat_while(is_digit_or_separator);
 
         // If the next char is a dot and the char after that is digit, consider it as
         // decimal part, and move cursor to consume it as well.
-        if self.remaining().starts_with('.') {
-            let c = self.remaining().chars().nth(1).unwrap_or('\0');
-            if c.is_digit(10) {
-                self.pos += 1;
-                consume_digits(self);
-            }
+        if self.cursor.peek() == Some('.')
+            && self
+                .cursor
+                .peek_second()
+                .is_some_and(|c| c.is_ascii_digit())
+        {
+            self.cursor.bump();
+            self.cursor.eat_while(is_digit_or_separator);
         }
 
-        self.wrap(TokenKind::Number, (start, self.pos))
+        self.check_separators(start)?;
+        self.wrap(TokenKind::Number, (start, self.pos()))
     }
 
-    /// Process an identifier and reserved keywords.
-    fn identifier(&mut self, start: usize) -> Result<Token<'a>> {
-        while let Some(c) = self.remaining().chars().next() {
-            if is_alphanumeric(c) {
-                self.pos += c.len_utf8();
-            } else {
-                break;
+    /// Checks that each separator of the number literal just consumed is
+    /// between two digits. Number literals and their decimal part start with a
+    /// digit, so only the character following a separator needs checking.
+    fn check_separators(&self, start: usize) -> Result<()> {
+        let lexeme = &self.src[start..self.pos()];
+        for (i, _) in lexeme.match_indices('_') {
+            match lexeme.as_bytes().get(i + 1) {
+                Some(b'_') => {
+                    let len = lexeme[i..].bytes().take_while(|&b| b == b'_').count();
+                    return Err(Error::ConsecutiveSeparators {
+                        src: self.src.to_string(),
+                        at: (start + i, len).into(),
+                    });
+                }
+                Some(b) if b.is_ascii_digit() => {}
+                _ => {
+                    return Err(Error::TrailingSeparator {
+                        src: self.src.to_string(),
+                        at: (start + i, 1).into(),
+                    });
+                }
             }
         }
-        if let Some(keyword) = TokenKind::from_keyword(&self.src[start..self.pos]) {
-            return self.wrap(keyword, (start, self.pos));
+        Ok(())
+    }
+
+    /// Process an identifier and reserved keywords.
+    fn identifier(&mut self, start: usize) -> Result<Token<'a>> {
+        self.cursor.eat_while(is_alphanumeric);
+        if let Some(keyword) = TokenKind::from_keyword(&self.src[start..self.pos()]) {
+            return self.wrap(keyword, (start, self.pos()));
         }
 
-        self.wrap(TokenKind::Identifier, (start, self.pos))
+        self.wrap(TokenKind::Identifier, (start, self.pos()))
     }
 }
 
@@ -207,24 +550,62 @@ impl<'a> Iterator for Lexer<'a> {
     }
 }
 
+fn is_digit_or_separator(c: char) -> bool {
+    c.is_ascii_digit() || c == '_'
+}
+
 fn is_alphanumeric(c: char) -> bool {
     c.is_ascii_alphanumeric() || c == '_'
 }
 
 #[cfg(test)]
 mod tests {
-    use {super::*, pretty_assertions::assert_eq};
+    use {
+        super::*,
+        crate::{LanguageLevel, LineIndex},
+        pretty_assertions::assert_eq,
+    };
 
+    /// Checks the tokens of the input. The line and column of the expected
+    /// tokens are filled in from their span, and literal values are left out
+    /// (see the `literals` test).
     fn assert_tokens(input: &str, expected: Vec<Token<'_>>) {
-        let lexer = Lexer::new(input);
-        let tokens: Vec<_> = lexer.tokens().map(Result::unwrap).collect();
+        assert_lexed(Lexer::new(input), expected);
+    }
+
+    /// Checks the tokens produced by the lexer, like `assert_tokens`.
+    fn assert_lexed(lexer: Lexer<'_>, mut expected: Vec<Token<'_>>) {
+        let index = LineIndex::new(lexer.source());
+        for token in &mut expected {
+            token.line_col = index.line_col(token.span.start());
+        }
+        let tokens: Vec<_> = lexer
+            .tokens()
+            .map(|token| Token {
+                literal: None,
+                ..token.unwrap()
+            })
+            .collect();
         assert_eq!(tokens, expected);
     }
 
     fn assert_err(input: &str, expected: Error) {
         let lexer = Lexer::new(input);
         let tokens: Vec<_> = lexer.tokens().collect();
-        assert_eq!(tokens, vec![Err(expected), Ok(Token::eof(input.len()))]);
+        let eof = Token::eof(input.len(), LineIndex::new(input).line_col(input.len()));
+        assert_eq!(tokens, vec![Err(expected), Ok(eof)]);
+    }
+
+    /// Lexer over the input at the extended language level.
+    fn extended(input: &str) -> Lexer<'_> {
+        Lexer::new(input).with_config(LexerConfig {
+            level: LanguageLevel::Extended,
+            ..LexerConfig::default()
+        })
+    }
+
+    fn eof(offset: usize) -> Token<'static> {
+        Token::eof(offset, LineCol::START)
     }
 
     fn wrap(kind: TokenKind, lexeme: &'_ str, (start, end): (usize, usize)) -> Token<'_> {
@@ -232,6 +613,8 @@ mod tests {
             kind,
             lexeme,
             span: TokenSpan::new(start, end),
+            line_col: LineCol::START,
+            literal: None,
         }
     }
 
@@ -243,6 +626,8 @@ mod tests {
             kind: token_type,
             lexeme,
             span: TokenSpan::new(col - 1, col - 1 + lexeme.len()),
+            line_col: LineCol::START,
+            literal: None,
         };
 
         assert_tokens("(){};,+-*!===<=>=!=<>/.", vec![
@@ -264,7 +649,7 @@ mod tests {
             wrap(TokenKind::Greater, ">", 21),
             wrap(TokenKind::Slash, "/", 22),
             wrap(TokenKind::Dot, ".", 23),
-            Token::eof(23),
+            eof(23),
         ]);
     }
 
@@ -277,6 +662,8 @@ mod tests {
                 kind: token_type,
                 lexeme,
                 span,
+                line_col: LineCol::START,
+                literal: None,
             }
         };
         let mut input = r#"
@@ -286,7 +673,7 @@ mod tests {
         assert_tokens(input, vec![
             wrap(TokenKind::String, "\"\"", (1, 3)),
             wrap(TokenKind::String, "\"string\"", (4, 12)),
-            Token::eof(13),
+            eof(13),
         ]);
 
         input = r#""unterminated string"#;
@@ -304,6 +691,59 @@ mod tests {
         });
     }
 
+    #[test]
+    fn interpolations() {
+        let input = r#""a ${x} b ${ f({}) + "${y}" } $c""#;
+        assert_lexed(extended(input), vec![
+            wrap(TokenKind::InterpolationStart, "\"a ${", (0, 5)),
+            wrap(TokenKind::Identifier, "x", (5, 6)),
+            wrap(TokenKind::InterpolationMiddle, "} b ${", (6, 12)),
+            wrap(TokenKind::Identifier, "f", (13, 14)),
+            wrap(TokenKind::LeftParen, "(", (14, 15)),
+            wrap(TokenKind::LeftBrace, "{", (15, 16)),
+            wrap(TokenKind::RightBrace, "}", (16, 17)),
+            wrap(TokenKind::RightParen, ")", (17, 18)),
+            wrap(TokenKind::Plus, "+", (19, 20)),
+            wrap(TokenKind::InterpolationStart, "\"${", (21, 24)),
+            wrap(TokenKind::Identifier, "y", (24, 25)),
+            wrap(TokenKind::InterpolationEnd, "}\"", (25, 27)),
+            wrap(TokenKind::InterpolationEnd, "} $c\"", (28, 33)),
+            eof(33),
+        ]);
+
+        // Braces outside of strings are left alone.
+        assert_lexed(extended("}"), vec![
+            wrap(TokenKind::RightBrace, "}", (0, 1)),
+            eof(1),
+        ]);
+
+        // Classic Lox has no interpolation: `${` is part of the text.
+        let input = r#""a ${x}" "${""#;
+        assert_tokens(input, vec![
+            wrap(TokenKind::String, "\"a ${x}\"", (0, 8)),
+            wrap(TokenKind::String, "\"${\"", (9, 13)),
+            eof(13),
+        ]);
+
+        let error = |input: &str| extended(input).tokens().find_map(Result::err);
+        let input = r#""a ${x"#;
+        assert_eq!(
+            error(input),
+            Some(Error::UnterminatedInterpolation {
+                src: input.to_string(),
+                at: (3, 2).into(),
+            })
+        );
+        let input = r#""a ${x} b"#;
+        assert_eq!(
+            error(input),
+            Some(Error::UnterminatedString {
+                src: input.to_string(),
+                at: (6, 3).into(),
+            })
+        );
+    }
+
     // https://github.com/munificent/craftinginterpreters/blob/master/test/scanning/numbers.lox
     #[test]
     fn numbers() {
@@ -319,10 +759,43 @@ mod tests {
             wrap(TokenKind::Number, "456", (22, 25)),
             wrap(TokenKind::Number, "123", (26, 29)),
             wrap(TokenKind::Dot, ".", (29, 30)),
-            Token::eof(30),
+            eof(30),
         ]);
     }
 
+    #[test]
+    fn separators() {
+        let input = "1_000 0.000_1 1_0._5";
+        assert_tokens(input, vec![
+            wrap(TokenKind::Number, "1_000", (0, 5)),
+            wrap(TokenKind::Number, "0.000_1", (6, 13)),
+            wrap(TokenKind::Number, "1_0", (14, 17)),
+            wrap(TokenKind::Dot, ".", (17, 18)),
+            wrap(TokenKind::Identifier, "_5", (18, 20)),
+            eof(20),
+        ]);
+
+        let input = "1_.5";
+        let tokens: Vec<_> = Lexer::new(input).collect();
+        assert_eq!(
+            tokens[0],
+            Err(Error::TrailingSeparator {
+                src: input.to_string(),
+                at: (1, 1).into(),
+            })
+        );
+        let input = "1.5_";
+        assert_err(input, Error::TrailingSeparator {
+            src: input.to_string(),
+            at: (3, 1).into(),
+        });
+        let input = "1__000";
+        assert_err(input, Error::ConsecutiveSeparators {
+            src: input.to_string(),
+            at: (1, 2).into(),
+        });
+    }
+
     // https://github.com/munificent/craftinginterpreters/blob/master/test/scanning/identifiers.lox
     #[test]
     fn identifiers() {
@@ -341,7 +814,7 @@ abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ1234567890_"#;
                 "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ1234567890_",
                 (35, 98),
             ),
-            Token::eof(98),
+            eof(98),
         ]);
     }
 
@@ -365,7 +838,7 @@ abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ1234567890_"#;
             wrap(TokenKind::True, "true", (57, 61)),
             wrap(TokenKind::Var, "var", (62, 65)),
             wrap(TokenKind::While, "while", (66, 71)),
-            Token::eof(71),
+            eof(71),
         ]);
     }
 
@@ -382,7 +855,198 @@ end"#;
             wrap(TokenKind::Identifier, "tabs", (9, 13)),
             wrap(TokenKind::Identifier, "newlines", (17, 25)),
             wrap(TokenKind::Identifier, "end", (30, 33)),
-            Token::eof(33),
+            eof(33),
+        ]);
+    }
+
+    #[test]
+    fn positions() {
+        let input = "var a = \"ü\";\n/* multi\nline */ print a;";
+        let tokens: Vec<_> = Lexer::new(input).tokens().map(Result::unwrap).collect();
+        let positions: Vec<_> = tokens.iter().map(|t| t.line_col.to_string()).collect();
+        assert_eq!(positions, &[
+            "1:1", "1:5", "1:7", "1:9", "1:12", "3:9", "3:15", "3:16", "3:17"
+        ]);
+        assert_eq!(
+            tokens[5].to_string(),
+            "{type: print, lexeme: print((31:36)), at 3:9}"
+        );
+    }
+
+    #[test]
+    fn literals() {
+        let input = r#"12.5 1_000 "a b" "x${y}z${w}" nil"#;
+        let literals: Vec<_> = extended(input).map(|t| t.unwrap().literal).collect();
+        assert_eq!(literals, &[
+            Some(Literal::Number(12.5)),
+            Some(Literal::Number(1000.0)),
+            Some(Literal::String("a b")),
+            Some(Literal::String("x")),
+            None,
+            Some(Literal::String("z")),
+            None,
+            Some(Literal::String("")),
+            None,
+        ]);
+    }
+
+    #[test]
+    fn scan_all() {
+        let input = "a ? b\n\"c";
+        let (tokens, errors) = Lexer::new(input).scan_all();
+        let lexemes: Vec<_> = tokens.iter().map(|t| t.lexeme).collect();
+        assert_eq!(lexemes, &["a", "b", "<eof>"]);
+        assert_eq!(errors, &[
+            Error::UnexpectedChar {
+                c: '?',
+                src: input.to_string(),
+                at: (2, 1).into(),
+            },
+            Error::UnterminatedString {
+                src: input.to_string(),
+                at: (6, 2).into(),
+            },
+        ]);
+    }
+
+    /// Checks that re-lexing after replacing the range of the input gives the
+    /// same tokens as lexing the edited input, at the extended language level
+    /// to cover interpolations.
+    fn assert_relex(input: &str, range: Range<usize>, new_text: &str) {
+        let previous: Vec<_> = extended(input).tokens().map(Result::unwrap).collect();
+        let mut edited = input.to_string();
+        edited.replace_range(range.clone(), new_text);
+        let expected: Vec<_> = extended(&edited).collect();
+        assert_eq!(
+            extended(&edited).relex(range, new_text, &previous),
+            expected
+        );
+    }
+
+    #[test]
+    fn relex() {
+        let input = "var a = 1;\nprint a + \"${b}\";\n/* c */ var d = a;";
+        // Within a line, across lines, and at both ends.
+        assert_relex(input, 8..9, "\"x\" +\n 2");
+        assert_relex(input, 4..5, "abc");
+        assert_relex(input, 9..17, "");
+        assert_relex(input, 0..0, "// first\n");
+        assert_relex(input, input.len()..input.len(), " e");
+        // Into an interpolation, and opening a string or a comment.
+        assert_relex(input, 22..23, "b + c");
+        assert_relex(input, 10..10, "\"");
+        assert_relex(input, 10..10, "/*");
+        assert_relex(input, 31..32, "");
+        // Completing a number, which looks two characters ahead.
+        assert_relex("1.x", 2..3, "5");
+        assert_relex("a 1.x b", 4..5, "5");
+    }
+
+    #[test]
+    fn lossless() {
+        let input = "// header\nvar a = 1; /* one */ // two\n\n  print a ?;\n";
+        let tokens: Vec<_> = Lexer::new(input).lossless().collect();
+        let trivia: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| t.as_ref().ok())
+            .map(|t| (t.leading, t.token.lexeme, t.trailing))
+            .collect();
+        assert_eq!(trivia, &[
+            ("// header\n", "var", " "),
+            ("", "a", " "),
+            ("", "=", " "),
+            ("", "1", ""),
+            ("", ";", " /* one */ // two"),
+            ("\n\n  ", "print", " "),
+            ("", "a", " "),
+            ("?", ";", ""),
+            ("\n", "<eof>", ""),
+        ]);
+        assert!(matches!(
+            tokens[7],
+            Err(Error::UnexpectedChar { c: '?', .. })
+        ));
+
+        let source: String = tokens
+            .iter()
+            .filter_map(|t| t.as_ref().ok())
+            .map(|t| &input[t.full_span().range()])
+            .collect();
+        assert_eq!(source, input);
+    }
+
+    #[test]
+    fn shebang() {
+        let input = "#!/usr/bin/env lox\nprint";
+        assert_tokens(input, vec![
+            wrap(TokenKind::Print, "print", (19, 24)),
+            eof(24),
+        ]);
+
+        // Only on the first line.
+        let input = "print\n#!lox";
+        let tokens: Vec<_> = Lexer::new(input).collect();
+        assert_eq!(
+            tokens[1],
+            Err(Error::UnexpectedChar {
+                c: '#',
+                src: input.to_string(),
+                at: (6, 1).into(),
+            })
+        );
+    }
+
+    #[test]
+    fn language_levels() {
+        let input = "a += 2 ** 3 % 4 ? b : c?.d++ - --e";
+        let kinds: Vec<_> = extended(input).map(|t| t.unwrap().kind).collect();
+        assert_eq!(kinds, &[
+            TokenKind::Identifier,
+            TokenKind::PlusEqual,
+            TokenKind::Number,
+            TokenKind::StarStar,
+            TokenKind::Number,
+            TokenKind::Percent,
+            TokenKind::Number,
+            TokenKind::Question,
+            TokenKind::Identifier,
+            TokenKind::Colon,
+            TokenKind::Identifier,
+            TokenKind::QuestionDot,
+            TokenKind::Identifier,
+            TokenKind::PlusPlus,
+            TokenKind::Minus,
+            TokenKind::MinusMinus,
+            TokenKind::Identifier,
+        ]);
+
+        // Classic Lox has none of the extension operators.
+        let kinds: Vec<_> = Lexer::new(input)
+            .map(|t| t.map(|t| t.kind).map_err(|_| ()))
+            .collect();
+        assert_eq!(kinds, &[
+            Ok(TokenKind::Identifier),
+            Ok(TokenKind::Plus),
+            Ok(TokenKind::Equal),
+            Ok(TokenKind::Number),
+            Ok(TokenKind::Star),
+            Ok(TokenKind::Star),
+            Ok(TokenKind::Number),
+            Err(()),
+            Ok(TokenKind::Number),
+            Err(()),
+            Ok(TokenKind::Identifier),
+            Err(()),
+            Ok(TokenKind::Identifier),
+            Err(()),
+            Ok(TokenKind::Dot),
+            Ok(TokenKind::Identifier),
+            Ok(TokenKind::Plus),
+            Ok(TokenKind::Plus),
+            Ok(TokenKind::Minus),
+            Ok(TokenKind::Minus),
+            Ok(TokenKind::Minus),
+            Ok(TokenKind::Identifier),
         ]);
     }
 
@@ -392,7 +1056,24 @@ end"#;
         // single line comment
         /* block comment */
         /* nested /* block1 */ /* block 2 /* block 2.1*/ */ comment*/ "#;
-        assert_tokens(input, vec![Token::eof(130)]);
+        assert_tokens(input, vec![eof(130)]);
+
+        let input = "a // line\n/* block /* nested */ */ b";
+        let tokens: Vec<_> = Lexer::new(input)
+            .with_comments(true)
+            .tokens()
+            .map(Result::unwrap)
+            .collect();
+        let kinds: Vec<_> = tokens.iter().map(|t| (t.kind, t.lexeme)).collect();
+        assert_eq!(kinds, &[
+            (TokenKind::Identifier, "a"),
+            (TokenKind::LineComment, "// line"),
+            (TokenKind::BlockComment, "/* block /* nested */ */"),
+            (TokenKind::Identifier, "b"),
+            (TokenKind::Eof, "<eof>"),
+        ]);
+        assert_eq!(tokens[2].span, TokenSpan::new(10, 34));
+        assert_eq!(tokens[2].line_col.to_string(), "2:1");
 
         let input = r#"
         /* unterminated block comment"#;
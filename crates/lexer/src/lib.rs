@@ -1,8 +1,17 @@
+pub mod config;
+mod cursor;
 pub mod error;
 pub mod lexer;
+pub mod line_index;
+pub mod peekable;
+pub mod reader;
 pub mod token;
 
 pub use {
+    config::{LanguageLevel, LexerConfig},
     error::{Error, Result},
     lexer::Lexer,
+    line_index::{LineCol, LineIndex},
+    peekable::PeekableLexer,
+    reader::{OwnedToken, ReaderLexer},
 };
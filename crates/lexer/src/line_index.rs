@@ -0,0 +1,156 @@
+use {crate::token::TokenSpan, core::fmt};
+
+/// 1-based line and column of a position in the source code.
+///
+/// Columns are counted in characters, not bytes, so that they match what
+/// editors display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl LineCol {
+    /// Position of the first character of the source code.
+    pub const START: Self = Self { line: 1, column: 1 };
+
+    /// Returns the position after the given text, which starts at `self`.
+    pub fn advance(self, text: &str) -> Self {
+        match text.rfind('\n') {
+            Some(last) => Self {
+                line: self.line + text.matches('\n').count(),
+                column: text[last + 1..].chars().count() + 1,
+            },
+            None => Self {
+                line: self.line,
+                column: self.column + text.chars().count(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for LineCol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Index of line starts, built once per source.
+///
+/// Converting a byte offset to a line is a binary search over the line starts,
+/// so diagnostics don't need to rescan the source for newlines.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    /// Reference to input source code.
+    src: &'a str,
+
+    /// Byte offsets at which each line starts (the first one is always 0).
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    /// Builds the line index for the given source code.
+    pub fn new(src: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { src, line_starts }
+    }
+
+    /// Returns the number of lines in the source code.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Returns the 1-based line number containing the given byte offset.
+    ///
+    /// Offsets past the end of input are attributed to the last line.
+    pub fn line(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset)
+    }
+
+    /// Converts a byte offset into a 1-based line and column.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let offset = offset.min(self.src.len());
+        let line = self.line(offset);
+        let start = self.line_starts[line - 1];
+        let column = self
+            .src
+            .get(start..offset)
+            .map_or(offset - start, |prefix| prefix.chars().count())
+            + 1;
+        LineCol { line, column }
+    }
+
+    /// Converts a span into the 1-based lines and columns of its start and
+    /// end.
+    pub fn span(&self, span: TokenSpan) -> (LineCol, LineCol) {
+        (self.line_col(span.start()), self.line_col(span.end()))
+    }
+
+    /// Returns the text of a 1-based line, without the line terminator.
+    pub fn line_text(&self, line: usize) -> Option<&'a str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(self.src.len(), |next| next - 1);
+        Some(self.src[start..end].trim_end_matches('\r'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn lc(line: usize, column: usize) -> LineCol {
+        LineCol { line, column }
+    }
+
+    #[test]
+    fn line_col() {
+        let index = LineIndex::new("var a;\nprint a;\n\nend");
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_col(0), lc(1, 1));
+        assert_eq!(index.line_col(4), lc(1, 5));
+        assert_eq!(index.line_col(6), lc(1, 7));
+        assert_eq!(index.line_col(7), lc(2, 1));
+        assert_eq!(index.line_col(15), lc(2, 9));
+        assert_eq!(index.line_col(16), lc(3, 1));
+        assert_eq!(index.line_col(17), lc(4, 1));
+        assert_eq!(index.line_col(20), lc(4, 4));
+        assert_eq!(index.line_col(100), lc(4, 4));
+    }
+
+    #[test]
+    fn columns_count_chars() {
+        let index = LineIndex::new("print \"ünï\" + x;");
+        assert_eq!(index.line_col(14), lc(1, 13));
+        assert_eq!(lc(1, 13).to_string(), "1:13");
+    }
+
+    #[test]
+    fn spans() {
+        let index = LineIndex::new("fun f() {\n  return;\n}");
+        assert_eq!(index.span(TokenSpan::new(12, 18)), (lc(2, 3), lc(2, 9)));
+        assert_eq!(index.span(TokenSpan::new(8, 23)), (lc(1, 9), lc(3, 2)));
+    }
+
+    #[test]
+    fn advance() {
+        assert_eq!(LineCol::START.advance("var ü"), lc(1, 6));
+        assert_eq!(lc(2, 4).advance("a\nb\n\ncd"), lc(5, 3));
+        assert_eq!(lc(2, 4).advance(""), lc(2, 4));
+    }
+
+    #[test]
+    fn line_text() {
+        let index = LineIndex::new("first\r\nsecond\nthird");
+        assert_eq!(index.line_text(1), Some("first"));
+        assert_eq!(index.line_text(2), Some("second"));
+        assert_eq!(index.line_text(3), Some("third"));
+        assert_eq!(index.line_text(0), None);
+        assert_eq!(index.line_text(4), None);
+    }
+}
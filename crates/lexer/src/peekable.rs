@@ -0,0 +1,84 @@
+use {
+    crate::{token::Token, Lexer, Result},
+    std::collections::VecDeque,
+};
+
+/// Lexer with up to two tokens of lookahead, see [`Lexer::peekable`].
+///
+/// Tokens are pulled from the lexer on demand and buffered until consumed.
+/// Like the lexer itself, it yields no `Eof` token: peeking past the end
+/// returns `None`.
+pub struct PeekableLexer<'a> {
+    lexer: Lexer<'a>,
+
+    /// Tokens pulled from the lexer but not consumed yet, at most two.
+    buffer: VecDeque<Result<Token<'a>>>,
+}
+
+impl<'a> PeekableLexer<'a> {
+    pub(crate) fn new(lexer: Lexer<'a>) -> Self {
+        Self {
+            lexer,
+            buffer: VecDeque::with_capacity(2),
+        }
+    }
+
+    /// Returns the source code being tokenized.
+    pub fn source(&self) -> &'a str {
+        self.lexer.source()
+    }
+
+    /// Returns the next token without consuming it.
+    pub fn peek(&mut self) -> Option<&Result<Token<'a>>> {
+        self.fill(1);
+        self.buffer.front()
+    }
+
+    /// Returns the token after the next one without consuming anything.
+    pub fn peek2(&mut self) -> Option<&Result<Token<'a>>> {
+        self.fill(2);
+        self.buffer.get(1)
+    }
+
+    /// Pulls tokens from the lexer until `n` are buffered, or the input ends.
+    fn fill(&mut self, n: usize) {
+        while self.buffer.len() < n {
+            match self.lexer.next() {
+                Some(token) => self.buffer.push_back(token),
+                None => break,
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for PeekableLexer<'a> {
+    type Item = Result<Token<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buffer.pop_front().or_else(|| self.lexer.next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::token::TokenKind, pretty_assertions::assert_eq};
+
+    fn kind(token: Option<&Result<Token<'_>>>) -> Option<TokenKind> {
+        token.map(|token| token.as_ref().unwrap().kind)
+    }
+
+    #[test]
+    fn lookahead() {
+        let mut tokens = Lexer::new("a = 1").peekable();
+        assert_eq!(kind(tokens.peek2()), Some(TokenKind::Equal));
+        assert_eq!(kind(tokens.peek()), Some(TokenKind::Identifier));
+        assert_eq!(kind(tokens.next().as_ref()), Some(TokenKind::Identifier));
+        assert_eq!(kind(tokens.peek()), Some(TokenKind::Equal));
+        assert_eq!(kind(tokens.peek2()), Some(TokenKind::Number));
+        tokens.next();
+        assert_eq!(kind(tokens.peek2()), None);
+        assert_eq!(kind(tokens.next().as_ref()), Some(TokenKind::Number));
+        assert_eq!(kind(tokens.peek()), None);
+        assert!(tokens.next().is_none());
+    }
+}
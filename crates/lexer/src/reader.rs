@@ -0,0 +1,233 @@
+use {
+    crate::{
+        line_index::LineCol,
+        token::{Literal, Token, TokenKind, TokenSpan},
+        Error,
+        Lexer,
+        Result,
+    },
+    std::{
+        collections::VecDeque,
+        io::{self, BufRead},
+    },
+};
+
+/// Token that owns its lexeme, as produced by a [`ReaderLexer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedToken {
+    pub kind: TokenKind,
+    pub lexeme: String,
+    pub span: TokenSpan,
+    /// Line and column of the start of the token.
+    pub line_col: LineCol,
+}
+
+impl OwnedToken {
+    /// Returns the token borrowing its lexeme.
+    pub fn as_token(&self) -> Token<'_> {
+        Token {
+            kind: self.kind,
+            lexeme: &self.lexeme,
+            span: self.span,
+            line_col: self.line_col,
+            literal: self.literal(),
+        }
+    }
+
+    /// Value of the token, for literals.
+    pub fn literal(&self) -> Option<Literal<'_>> {
+        Literal::parse(self.kind, &self.lexeme)
+    }
+}
+
+impl From<Token<'_>> for OwnedToken {
+    fn from(token: Token<'_>) -> Self {
+        Self {
+            kind: token.kind,
+            lexeme: token.lexeme.to_string(),
+            span: token.span,
+            line_col: token.line_col,
+        }
+    }
+}
+
+/// Lexer reading the source code from a buffered reader, e.g. a pipe or a
+/// large file, rather than from a string holding all of it.
+///
+/// The input is read line by line, and only the lines of the token being
+/// lexed are kept in memory, so a string or comment spanning many lines is
+/// buffered whole. Spans and positions of tokens are relative to the whole
+/// input, but the source code attached to errors is only the chunk of lines
+/// they were found in.
+///
+/// Like [`Lexer`], it yields no `Eof` token. Reading fails if the input is not
+/// valid UTF-8.
+pub struct ReaderLexer<R> {
+    reader: R,
+
+    /// Lines read but not lexed yet, as they end inside a token.
+    buffer: String,
+
+    /// Length of the buffer when it was last found to end inside a token.
+    open: Option<usize>,
+
+    /// Offset of the start of the buffer in the whole input.
+    offset: usize,
+
+    /// Number of lines before the buffer.
+    lines: usize,
+
+    /// Tokens lexed from the last chunk of lines, not yielded yet.
+    ready: VecDeque<Result<OwnedToken>>,
+
+    /// Whether the end of the input was reached.
+    done: bool,
+}
+
+impl<R: BufRead> ReaderLexer<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: String::new(),
+            open: None,
+            offset: 0,
+            lines: 0,
+            ready: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Lexes the buffered lines, unless they end inside a string, comment or
+    /// interpolation that the next lines may terminate.
+    ///
+    /// Lines are read whole, so other tokens can't continue past the buffer.
+    /// While the buffer is open, it is only lexed again once a new line may
+    /// close the token, so that long strings and comments are lexed in linear
+    /// time.
+    fn lex_buffer(&mut self) {
+        if let Some(open) = self.open {
+            if !self.done && !may_close(&self.buffer[open..]) {
+                self.open = Some(self.buffer.len());
+                return;
+            }
+        }
+
+        let items: Vec<_> = if self.offset == 0 {
+            Lexer::new(&self.buffer).collect()
+        } else {
+            Lexer::chunk(&self.buffer).collect()
+        };
+        let unterminated = items
+            .iter()
+            .any(|item| item.as_ref().is_err_and(Error::is_unterminated));
+        if unterminated && !self.done {
+            self.open = Some(self.buffer.len());
+            return;
+        }
+
+        let (offset, lines) = (self.offset, self.lines);
+        self.ready.extend(items.into_iter().map(|item| {
+            item.map(|token| OwnedToken {
+                span: TokenSpan::new(token.span.start() + offset, token.span.end() + offset),
+                line_col: LineCol {
+                    line: token.line_col.line + lines,
+                    ..token.line_col
+                },
+                ..token.into()
+            })
+        }));
+        self.offset += self.buffer.len();
+        self.lines += self.buffer.matches('\n').count();
+        self.buffer.clear();
+        self.open = None;
+    }
+}
+
+/// Returns whether the lines may close a string, comment or interpolation
+/// left open before them.
+fn may_close(lines: &str) -> bool {
+    lines.contains(['"', '}']) || lines.contains("*/")
+}
+
+impl<R: BufRead> Iterator for ReaderLexer<R> {
+    type Item = io::Result<Result<OwnedToken>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.ready.pop_front() {
+                return Some(Ok(token));
+            }
+            if self.done {
+                return None;
+            }
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => self.done = true,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            self.lex_buffer();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn tokens(input: &str) -> Vec<Result<OwnedToken>> {
+        ReaderLexer::new(input.as_bytes())
+            .map(|item| item.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn same_as_lexer() {
+        let input =
+            "#!lox\nvar a = \"multi\nline\";\n/* block\n comment */ print\n\"${\na\n} ü\" + 1;";
+        let expected: Vec<_> = Lexer::new(input)
+            .map(|token| token.map(OwnedToken::from))
+            .collect();
+        assert_eq!(tokens(input), expected);
+    }
+
+    #[test]
+    fn open_tokens() {
+        // Lines that can't close the open token are skipped over.
+        for input in [
+            "/* a\n\"b\n/* c\n*/ d\n*/ \"e\n\n${\n{f}\n} g\n\" h\n",
+            "\"a\nb\n\nc",
+        ] {
+            let expected: Vec<_> = Lexer::new(input)
+                .map(|token| token.map(OwnedToken::from))
+                .collect();
+            assert_eq!(tokens(input), expected);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let input = "a\n\"unterminated\nstring";
+        let tokens = tokens(input);
+        assert_eq!(tokens[0].as_ref().unwrap().lexeme, "a");
+        assert_eq!(
+            tokens[1],
+            Err(Error::UnterminatedString {
+                src: "\"unterminated\nstring".to_string(),
+                at: (0, 20).into(),
+            })
+        );
+
+        // A shebang is only allowed on the first line.
+        let tokens = self::tokens("a\n#!b");
+        assert!(matches!(
+            tokens[1],
+            Err(Error::UnexpectedChar { c: '#', .. })
+        ));
+
+        let mut lexer = ReaderLexer::new(&[b'a', b' ', 0xff][..]);
+        assert_eq!(
+            lexer.next().unwrap().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
@@ -1,7 +1,8 @@
-use core::fmt;
+use {crate::line_index::LineCol, core::fmt, miette::SourceSpan};
 
 /// Types of tokens that the lexer can produce.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum TokenKind {
     // Single-character tokens.
     LeftParen,
@@ -15,6 +16,9 @@ pub enum TokenKind {
     Semicolon,
     Slash,
     Star,
+    Percent,
+    Question,
+    Colon,
 
     // One or two character tokens.
     Bang,
@@ -25,9 +29,21 @@ pub enum TokenKind {
     GreaterEqual,
     Less,
     LessEqual,
+    PlusEqual,
+    StarStar,
+    /// Null-safe property access, e.g. `a?.b`.
+    QuestionDot,
+    PlusPlus,
+    MinusMinus,
 
     // Literals.
     String,
+    /// Start of an interpolated string, up to its first `${`, e.g. `"a ${`.
+    InterpolationStart,
+    /// Text between two interpolated expressions, e.g. `} b ${`.
+    InterpolationMiddle,
+    /// End of an interpolated string, after its last expression, e.g. `} c"`.
+    InterpolationEnd,
     Identifier,
     Number,
 
@@ -49,6 +65,11 @@ pub enum TokenKind {
     Var,
     While,
 
+    // Comments, only produced if requested, see
+    // [`Lexer::with_comments`](crate::Lexer::with_comments).
+    LineComment,
+    BlockComment,
+
     // Special tokens.
     Eof,
 }
@@ -84,11 +105,19 @@ impl TokenKind {
             }
         })
     }
+
+    pub fn is_comment(&self) -> bool {
+        matches!(self, Self::LineComment | Self::BlockComment)
+    }
 }
 
-impl From<char> for TokenKind {
-    fn from(c: char) -> Self {
-        match c {
+/// Token kind of a single-character token. The character is given back as the
+/// error if no token consists of it alone.
+impl TryFrom<char> for TokenKind {
+    type Error = char;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        Ok(match c {
             '(' => Self::LeftParen,
             ')' => Self::RightParen,
             '{' => Self::LeftBrace,
@@ -100,12 +129,15 @@ impl From<char> for TokenKind {
             ';' => Self::Semicolon,
             '/' => Self::Slash,
             '*' => Self::Star,
+            '%' => Self::Percent,
+            '?' => Self::Question,
+            ':' => Self::Colon,
             '!' => Self::Bang,
             '=' => Self::Equal,
             '>' => Self::Greater,
             '<' => Self::Less,
-            _ => panic!("Invalid character: {}", c),
-        }
+            _ => return Err(c),
+        })
     }
 }
 
@@ -123,6 +155,9 @@ impl fmt::Display for TokenKind {
             Self::Semicolon => ";",
             Self::Slash => "/",
             Self::Star => "*",
+            Self::Percent => "%",
+            Self::Question => "?",
+            Self::Colon => ":",
             Self::Bang => "!",
             Self::BangEqual => "!=",
             Self::Equal => "=",
@@ -131,7 +166,15 @@ impl fmt::Display for TokenKind {
             Self::GreaterEqual => ">=",
             Self::Less => "<",
             Self::LessEqual => "<=",
+            Self::PlusEqual => "+=",
+            Self::StarStar => "**",
+            Self::QuestionDot => "?.",
+            Self::PlusPlus => "++",
+            Self::MinusMinus => "--",
             Self::String => "string",
+            Self::InterpolationStart => "start of interpolated string",
+            Self::InterpolationMiddle => "middle of interpolated string",
+            Self::InterpolationEnd => "end of interpolated string",
             Self::Identifier => "identifier",
             Self::Number => "number",
             Self::And => "and",
@@ -150,14 +193,54 @@ impl fmt::Display for TokenKind {
             Self::True => "true",
             Self::Var => "var",
             Self::While => "while",
+            Self::LineComment => "line comment",
+            Self::BlockComment => "block comment",
             Self::Eof => "<EOF>",
         };
         write!(f, "{out}")
     }
 }
 
+/// Value of a literal token, parsed by the lexer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Literal<'a> {
+    Number(f64),
+
+    /// Text of a string, or of a part of an interpolated string, without the
+    /// delimiters. Lox has no escape sequences, so it is borrowed from the
+    /// source as-is.
+    String(&'a str),
+}
+
+// Number literals are never NaN, so equality is reflexive.
+impl Eq for Literal<'_> {}
+
+impl<'a> Literal<'a> {
+    /// Parses the value of a token of the given kind, if it is a literal.
+    ///
+    /// Returns `None` for other kinds, or if the lexeme is not a valid literal
+    /// of its kind.
+    pub fn parse(kind: TokenKind, lexeme: &'a str) -> Option<Self> {
+        let text = |start: usize, end: usize| {
+            let text = lexeme.get(start..lexeme.len().checked_sub(end)?)?;
+            Some(Self::String(text))
+        };
+        match kind {
+            TokenKind::Number if lexeme.contains('_') => {
+                lexeme.replace('_', "").parse().ok().map(Self::Number)
+            }
+            TokenKind::Number => lexeme.parse().ok().map(Self::Number),
+            TokenKind::String | TokenKind::InterpolationEnd => text(1, 1),
+            TokenKind::InterpolationStart | TokenKind::InterpolationMiddle => text(1, 2),
+            _ => None,
+        }
+    }
+}
+
 /// Represents a span of bytes in the source code.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TokenSpan(usize, usize);
 
 impl TokenSpan {
@@ -182,39 +265,94 @@ impl TokenSpan {
     pub fn range(&self) -> std::ops::Range<usize> {
         self.0..self.1
     }
+
+    /// Returns the smallest span covering both `self` and `other`.
+    pub fn merge(&self, other: TokenSpan) -> Self {
+        Self(self.0.min(other.0), self.1.max(other.1))
+    }
+}
+
+impl From<TokenSpan> for SourceSpan {
+    fn from(span: TokenSpan) -> Self {
+        (span.start(), span.length()).into()
+    }
 }
 
 impl fmt::Display for TokenSpan {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "({}:{})", self.0, self.0 + self.1)
+        write!(f, "({}:{})", self.0, self.1)
     }
 }
 
 /// Token is a lexeme wrapped up with some extra information (useful for
 /// successive parsing).
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Token<'a> {
     pub kind: TokenKind,
     pub lexeme: &'a str,
     pub span: TokenSpan,
+    /// Line and column of the start of the token.
+    pub line_col: LineCol,
+    /// Value of the token, for literals.
+    pub literal: Option<Literal<'a>>,
 }
 
 impl<'a> Token<'a> {
-    pub fn eof(offset: usize) -> Self {
+    /// End of input token, at the given offset and position.
+    pub fn eof(offset: usize, line_col: LineCol) -> Self {
         Self {
             kind: TokenKind::Eof,
             lexeme: "<eof>",
             span: TokenSpan::new(offset, offset),
+            line_col,
+            literal: None,
         }
     }
 }
 
+/// Token with the whitespace and comments around it, as produced by
+/// [`Lexer::lossless`](crate::Lexer::lossless).
+///
+/// Trailing trivia is the whitespace and comments following the token on the
+/// same line, excluding the line break. Leading trivia is everything else
+/// between the previous token and this one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TriviaToken<'a> {
+    pub leading: &'a str,
+    pub token: Token<'a>,
+    pub trailing: &'a str,
+}
+
+impl TriviaToken<'_> {
+    /// Returns the span of the token including its trivia.
+    pub fn full_span(&self) -> TokenSpan {
+        TokenSpan::new(
+            self.token.span.start() - self.leading.len(),
+            self.token.span.end() + self.trailing.len(),
+        )
+    }
+}
+
 impl fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{{type: {}, lexeme: {}({})}}",
-            self.kind, self.lexeme, self.span,
+            "{{type: {}, lexeme: {}({}), at {}}}",
+            self.kind, self.lexeme, self.span, self.line_col,
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn single_char_kinds() {
+        assert_eq!(TokenKind::try_from(';'), Ok(TokenKind::Semicolon));
+        assert_eq!(TokenKind::try_from('<'), Ok(TokenKind::Less));
+        assert_eq!(TokenKind::try_from('#'), Err('#'));
+        assert_eq!(TokenKind::try_from('a'), Err('a'));
+    }
+}
@@ -0,0 +1,46 @@
+use bumpalo::Bump;
+
+/// Memory arena owning the nodes of an AST.
+///
+/// Nodes are bump-allocated, instead of being boxed one by one, and the whole
+/// tree is freed at once when the arena is dropped. The arena doesn't run
+/// destructors, so only types that don't need dropping may be allocated.
+#[derive(Default)]
+pub struct Arena {
+    bump: Bump,
+}
+
+impl Arena {
+    /// Creates an empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the value into the arena.
+    pub fn alloc<T>(&self, value: T) -> &T {
+        const {
+            assert!(
+                !std::mem::needs_drop::<T>(),
+                "arena values are never dropped"
+            )
+        };
+        self.bump.alloc(value)
+    }
+
+    /// Copies the string into the arena, e.g. the source code the AST borrows
+    /// from, so that it is freed along with the AST.
+    pub fn alloc_str(&self, string: &str) -> &str {
+        self.bump.alloc_str(string)
+    }
+
+    /// Moves the values into the arena, as a contiguous slice.
+    pub fn alloc_slice<T>(&self, values: Vec<T>) -> &[T] {
+        const {
+            assert!(
+                !std::mem::needs_drop::<T>(),
+                "arena values are never dropped"
+            )
+        };
+        self.bump.alloc_slice_fill_iter(values)
+    }
+}
@@ -0,0 +1,87 @@
+use crate::grammar::{BinaryOperator, Expr, LiteralValue, LogicalOperator, UnaryOperator};
+
+/// Evaluates an expression made of literals only, e.g. for constant folding,
+/// or to detect constant conditions.
+///
+/// Returns `None` if the expression isn't constant, if evaluating it would be
+/// a runtime error, or if it would produce a new string, as there is nowhere
+/// to allocate it.
+pub fn evaluate<'a>(expr: &Expr<'a>) -> Option<LiteralValue<'a>> {
+    use {
+        BinaryOperator::*,
+        LiteralValue::{Boolean, Number},
+    };
+
+    let value = match expr {
+        Expr::Literal(literal) => literal.value,
+        Expr::Grouping(grouping) => evaluate(grouping.expression)?,
+        Expr::Unary(unary) => match (unary.operator, evaluate(unary.right)?) {
+            (UnaryOperator::Minus, Number(n)) => Number(-n),
+            (UnaryOperator::Minus, _) => return None,
+            (UnaryOperator::Bang, value) => Boolean(!is_truthy(value)),
+        },
+        Expr::Logical(logical) => {
+            let left = evaluate(logical.left)?;
+            match (logical.operator, is_truthy(left)) {
+                (LogicalOperator::Or, true) | (LogicalOperator::And, false) => left,
+                _ => evaluate(logical.right)?,
+            }
+        }
+        Expr::Binary(binary) => {
+            let (left, right) = (evaluate(binary.left)?, evaluate(binary.right)?);
+            match (binary.operator, left, right) {
+                (EqualEqual, ..) => Boolean(left == right),
+                (BangEqual, ..) => Boolean(left != right),
+                (Less, Number(a), Number(b)) => Boolean(a < b),
+                (LessEqual, Number(a), Number(b)) => Boolean(a <= b),
+                (Greater, Number(a), Number(b)) => Boolean(a > b),
+                (GreaterEqual, Number(a), Number(b)) => Boolean(a >= b),
+                (Plus, Number(a), Number(b)) => Number(a + b),
+                (Minus, Number(a), Number(b)) => Number(a - b),
+                (Star, Number(a), Number(b)) => Number(a * b),
+                // Division by zero is an error, unless configured otherwise.
+                (Slash, Number(a), Number(b)) if b != 0.0 => Number(a / b),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Whether a value is considered true in a condition: everything but `nil` and
+/// `false` is.
+pub fn is_truthy(value: LiteralValue<'_>) -> bool {
+    !matches!(value, LiteralValue::Nil | LiteralValue::Boolean(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::Parser, lexer::Lexer, pretty_assertions::assert_eq};
+
+    fn constant(input: &str) -> Option<LiteralValue<'_>> {
+        let arena = Box::leak(Box::default());
+        let expr = Parser::new(Lexer::new(input), arena)
+            .parse_expression()
+            .unwrap();
+        evaluate(&expr)
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(constant("(1 + 2) * -3"), Some(LiteralValue::Number(-9.0)));
+        assert_eq!(constant("1 < 2 == !nil"), Some(LiteralValue::Boolean(true)));
+        assert_eq!(
+            constant("\"a\" == \"a\""),
+            Some(LiteralValue::Boolean(true))
+        );
+        assert_eq!(constant("false or \"a\""), Some(LiteralValue::String("a")));
+        assert_eq!(constant("false and x"), Some(LiteralValue::Boolean(false)));
+
+        assert_eq!(constant("x or true"), None);
+        assert_eq!(constant("1 / 0"), None);
+        assert_eq!(constant("-\"a\""), None);
+        assert_eq!(constant("\"a\" + \"b\""), None);
+        assert_eq!(constant("f()"), None);
+    }
+}
@@ -0,0 +1,87 @@
+use {
+    miette::{Diagnostic, SourceSpan},
+    thiserror::Error,
+};
+
+/// Error type for the parser.
+#[derive(Error, Diagnostic, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    LexerError(#[from] lexer::Error),
+
+    #[error("Expected {expected}, found {found}")]
+    #[diagnostic(code(parser::unexpected_token))]
+    UnexpectedToken {
+        #[source_code]
+        src: String,
+        #[label("expected {expected}")]
+        at: SourceSpan,
+        expected: String,
+        found: String,
+    },
+
+    #[error("Expected expression, found {found}")]
+    #[diagnostic(code(parser::expected_expression))]
+    ExpectedExpression {
+        #[source_code]
+        src: String,
+        #[label("here")]
+        at: SourceSpan,
+        found: String,
+    },
+
+    #[error("Invalid literal '{lexeme}'")]
+    #[diagnostic(code(parser::invalid_literal))]
+    InvalidLiteral {
+        #[source_code]
+        src: String,
+        #[label("not a valid {kind}")]
+        at: SourceSpan,
+        kind: &'static str,
+        lexeme: String,
+    },
+
+    #[error("Binary operator '{operator}' is missing its left-hand operand")]
+    #[diagnostic(code(parser::missing_left_operand))]
+    MissingLeftOperand {
+        #[source_code]
+        src: String,
+        #[label("expected expression before '{operator}'")]
+        at: SourceSpan,
+        operator: String,
+    },
+
+    #[error("Expected '}}' after block")]
+    #[diagnostic(code(parser::unterminated_block))]
+    UnterminatedBlock {
+        #[source_code]
+        src: String,
+        #[label("block starts here")]
+        at: SourceSpan,
+        #[label("expected '}}' here")]
+        end: SourceSpan,
+    },
+
+    #[error("Can't have more than {max} {what}")]
+    #[diagnostic(code(parser::too_many_arguments))]
+    TooManyArguments {
+        #[source_code]
+        src: String,
+        #[label("here")]
+        at: SourceSpan,
+        what: &'static str,
+        max: usize,
+    },
+
+    #[error("Invalid assignment target")]
+    #[diagnostic(code(parser::invalid_assignment_target))]
+    InvalidAssignmentTarget {
+        #[source_code]
+        src: String,
+        #[label("can't assign to this")]
+        at: SourceSpan,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
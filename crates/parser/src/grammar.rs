@@ -1,41 +1,184 @@
-use core::fmt;
+use {
+    core::fmt,
+    lexer::token::{Token, TokenSpan},
+};
 
 /// Expression types.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Expr<'a> {
+    Assign(Assign<'a>),
     Binary(Binary<'a>),
+    Call(Call<'a>),
+    Get(Get<'a>),
     Grouping(Grouping<'a>),
     Literal(Literal<'a>),
+    Logical(Logical<'a>),
+    Sequence(Sequence<'a>),
+    Set(Set<'a>),
+    Super(Super<'a>),
+    This(This),
     Unary(Unary<'a>),
+    Update(Update<'a>),
+    Variable(Variable<'a>),
+}
+
+impl Expr<'_> {
+    /// Returns the span covering the whole expression.
+    pub fn span(&self) -> TokenSpan {
+        match self {
+            Expr::Assign(assign) => assign.name.span.merge(assign.value.span()),
+            Expr::Binary(binary) => binary.left.span().merge(binary.right.span()),
+            Expr::Call(call) => call.callee.span().merge(call.span),
+            Expr::Get(get) => get.object.span().merge(get.name.span),
+            Expr::Grouping(grouping) => grouping.span,
+            Expr::Literal(literal) => literal.span,
+            Expr::Logical(logical) => logical.left.span().merge(logical.right.span()),
+            Expr::Sequence(sequence) => sequence.left.span().merge(sequence.right.span()),
+            Expr::Set(set) => set.object.span().merge(set.value.span()),
+            Expr::Super(super_) => super_.span.merge(super_.method.span),
+            Expr::This(this) => this.span,
+            Expr::Unary(unary) => unary.span.merge(unary.right.span()),
+            Expr::Update(update) => update.span.merge(update.target.span()),
+            Expr::Variable(variable) => variable.name.span,
+        }
+    }
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Assign<'a> {
+    pub name: Token<'a>,
+    pub value: &'a Expr<'a>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Binary<'a> {
-    pub left: Box<Expr<'a>>,
+    pub left: &'a Expr<'a>,
     pub operator: BinaryOperator,
-    pub right: Box<Expr<'a>>,
+    pub right: &'a Expr<'a>,
+    /// Span of the operator token.
+    pub span: TokenSpan,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Call<'a> {
+    pub callee: &'a Expr<'a>,
+    pub arguments: &'a [Expr<'a>],
+    /// Span of the closing parenthesis.
+    pub span: TokenSpan,
+}
+
+/// Property access.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Get<'a> {
+    pub object: &'a Expr<'a>,
+    pub name: Token<'a>,
+    /// Whether the access is null-safe, i.e. `a?.b`, yielding `nil` rather
+    /// than failing if the object is `nil`, and skipping the accesses and
+    /// calls chained after it, e.g. the `.c()` of `a?.b.c()`.
+    pub optional: bool,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Grouping<'a> {
-    pub expression: Box<Expr<'a>>,
+    pub expression: &'a Expr<'a>,
+    /// Span from the opening to the closing parenthesis.
+    pub span: TokenSpan,
 }
 
 #[derive(Debug)]
-pub enum Literal<'a> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Literal<'a> {
+    pub value: LiteralValue<'a>,
+    /// Span of the literal token.
+    pub span: TokenSpan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum LiteralValue<'a> {
     Number(f64),
     String(&'a str),
     Boolean(bool),
     Nil,
 }
 
+/// Short-circuiting logical operation.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Logical<'a> {
+    pub left: &'a Expr<'a>,
+    pub operator: LogicalOperator,
+    pub right: &'a Expr<'a>,
+    /// Span of the operator token.
+    pub span: TokenSpan,
+}
+
+/// Comma operator: evaluates both operands and yields the right one.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Sequence<'a> {
+    pub left: &'a Expr<'a>,
+    pub right: &'a Expr<'a>,
+    /// Span of the comma.
+    pub span: TokenSpan,
+}
+
+/// Property assignment.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Set<'a> {
+    pub object: &'a Expr<'a>,
+    pub name: Token<'a>,
+    pub value: &'a Expr<'a>,
+}
+
+/// Superclass method access, i.e. `super.method`.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Super<'a> {
+    pub method: Token<'a>,
+    /// Span of the `super` keyword.
+    pub span: TokenSpan,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct This {
+    /// Span of the `this` keyword.
+    pub span: TokenSpan,
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Unary<'a> {
     pub operator: UnaryOperator,
-    pub right: Box<Expr<'a>>,
+    pub right: &'a Expr<'a>,
+    /// Span of the operator token.
+    pub span: TokenSpan,
 }
 
+/// Increment or decrement of a variable or a field, e.g. `++a` or `a.b--`.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Update<'a> {
+    pub operator: UpdateOperator,
+    /// Variable or field being updated.
+    pub target: &'a Expr<'a>,
+    /// Whether the operator comes before the target, in which case the
+    /// expression yields the updated value rather than the previous one.
+    pub prefix: bool,
+    /// Span of the operator token.
+    pub span: TokenSpan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum BinaryOperator {
     EqualEqual,
     BangEqual,
@@ -67,7 +210,25 @@ impl fmt::Display for BinaryOperator {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum LogicalOperator {
+    And,
+    Or,
+}
+
+impl fmt::Display for LogicalOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let out = match self {
+            Self::And => "and",
+            Self::Or => "or",
+        };
+        write!(f, "{}", out)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum UnaryOperator {
     Minus,
     Bang,
@@ -83,6 +244,152 @@ impl fmt::Display for UnaryOperator {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum UpdateOperator {
+    Increment,
+    Decrement,
+}
+
+impl fmt::Display for UpdateOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let out = match self {
+            Self::Increment => "++",
+            Self::Decrement => "--",
+        };
+        write!(f, "{}", out)
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Variable<'a> {
+    pub name: Token<'a>,
+}
+
+/// Statement types.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Stmt<'a> {
+    Block(Block<'a>),
+    Class(Class<'a>),
+    Expression(Expression<'a>),
+    Function(Function<'a>),
+    If(If<'a>),
+    Print(Print<'a>),
+    Return(Return<'a>),
+    Var(Var<'a>),
+    While(While<'a>),
+}
+
+impl Stmt<'_> {
+    /// Returns the span of the statement.
+    ///
+    /// Only the tokens recorded in the AST are covered: the span of a class or
+    /// function declaration is that of its name, and trailing semicolons are
+    /// left out.
+    pub fn span(&self) -> TokenSpan {
+        match self {
+            Stmt::Block(block) => block.span,
+            Stmt::Class(class) => class.name.span,
+            Stmt::Expression(expression) => expression.expression.span(),
+            Stmt::Function(function) => function.name.span,
+            Stmt::If(if_) => {
+                let branch = if_.else_branch.unwrap_or(if_.then_branch);
+                if_.span.merge(branch.span())
+            }
+            Stmt::Print(print) => print.span.merge(print.expression.span()),
+            Stmt::Return(return_) => match &return_.value {
+                Some(value) => return_.span.merge(value.span()),
+                None => return_.span,
+            },
+            Stmt::Var(var) => match &var.initializer {
+                Some(initializer) => var.name.span.merge(initializer.span()),
+                None => var.name.span,
+            },
+            Stmt::While(while_) => while_.span.merge(while_.body.span()),
+        }
+    }
+}
+
+/// Block of statements, introducing a new lexical scope.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Block<'a> {
+    pub statements: &'a [Stmt<'a>],
+    /// Span from the opening to the closing brace.
+    pub span: TokenSpan,
+}
+
+/// Class declaration.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Class<'a> {
+    pub name: Token<'a>,
+    pub superclass: Option<Variable<'a>>,
+    pub methods: &'a [Function<'a>],
+}
+
+/// Expression evaluated for its side effects.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Expression<'a> {
+    pub expression: Expr<'a>,
+}
+
+/// Function (or method) declaration.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Function<'a> {
+    pub name: Token<'a>,
+    pub params: &'a [Token<'a>],
+    pub body: &'a [Stmt<'a>],
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct If<'a> {
+    pub condition: Expr<'a>,
+    pub then_branch: &'a Stmt<'a>,
+    pub else_branch: Option<&'a Stmt<'a>>,
+    /// Span of the `if` keyword.
+    pub span: TokenSpan,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Print<'a> {
+    pub expression: Expr<'a>,
+    /// Span of the `print` keyword.
+    pub span: TokenSpan,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Return<'a> {
+    pub value: Option<Expr<'a>>,
+    /// Span of the `return` keyword.
+    pub span: TokenSpan,
+}
+
+/// Variable declaration.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Var<'a> {
+    pub name: Token<'a>,
+    pub initializer: Option<Expr<'a>>,
+}
+
+/// Loop statement, also used to represent desugared `for` loops.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct While<'a> {
+    pub condition: Expr<'a>,
+    pub body: &'a Stmt<'a>,
+    /// Span of the `while` (or `for`) keyword.
+    pub span: TokenSpan,
+}
+
 /// Implementors of this trait can visit a data structure `U` and return a
 /// result of type `V`.
 pub trait Visitor<U, V> {
@@ -93,18 +400,46 @@ pub trait Visitor<U, V> {
 pub trait ExprVisitor<'a, V>
 where
     Self: Sized
+        + Visitor<Assign<'a>, V>
         + Visitor<Binary<'a>, V>
+        + Visitor<Call<'a>, V>
+        + Visitor<Get<'a>, V>
         + Visitor<Grouping<'a>, V>
         + Visitor<Literal<'a>, V>
-        + Visitor<Unary<'a>, V>,
+        + Visitor<Logical<'a>, V>
+        + Visitor<Sequence<'a>, V>
+        + Visitor<Set<'a>, V>
+        + Visitor<Super<'a>, V>
+        + Visitor<This, V>
+        + Visitor<Unary<'a>, V>
+        + Visitor<Update<'a>, V>
+        + Visitor<Variable<'a>, V>,
 {
     fn visit_expr(&mut self, data: &Expr<'a>) -> V {
-        match data {
-            Expr::Binary(binary) => binary.accept(self),
-            Expr::Grouping(grouping) => grouping.accept(self),
-            Expr::Literal(literal) => literal.accept(self),
-            Expr::Unary(unary) => unary.accept(self),
-        }
+        visit_expr(self, data)
+    }
+}
+
+/// Dispatches the expression to the visitor method of its type.
+///
+/// Visitors overriding `visit_expr`, e.g. to do some work for every
+/// expression, call it to visit the expression itself.
+pub fn visit_expr<'a, V, T: ExprVisitor<'a, V>>(visitor: &mut T, data: &Expr<'a>) -> V {
+    match data {
+        Expr::Assign(assign) => assign.accept(visitor),
+        Expr::Binary(binary) => binary.accept(visitor),
+        Expr::Call(call) => call.accept(visitor),
+        Expr::Get(get) => get.accept(visitor),
+        Expr::Grouping(grouping) => grouping.accept(visitor),
+        Expr::Literal(literal) => literal.accept(visitor),
+        Expr::Logical(logical) => logical.accept(visitor),
+        Expr::Sequence(sequence) => sequence.accept(visitor),
+        Expr::Set(set) => set.accept(visitor),
+        Expr::Super(super_) => super_.accept(visitor),
+        Expr::This(this) => this.accept(visitor),
+        Expr::Unary(unary) => unary.accept(visitor),
+        Expr::Update(update) => update.accept(visitor),
+        Expr::Variable(variable) => variable.accept(visitor),
     }
 }
 
@@ -117,6 +452,51 @@ where
     }
 }
 
+/// Statement visitor must be able to visit all statement types.
+pub trait StmtVisitor<'a, V>
+where
+    Self: Sized
+        + Visitor<Block<'a>, V>
+        + Visitor<Class<'a>, V>
+        + Visitor<Expression<'a>, V>
+        + Visitor<Function<'a>, V>
+        + Visitor<If<'a>, V>
+        + Visitor<Print<'a>, V>
+        + Visitor<Return<'a>, V>
+        + Visitor<Var<'a>, V>
+        + Visitor<While<'a>, V>,
+{
+    fn visit_stmt(&mut self, data: &Stmt<'a>) -> V {
+        visit_stmt(self, data)
+    }
+}
+
+/// Dispatches the statement to the visitor method of its type.
+///
+/// Visitors overriding `visit_stmt` call it to visit the statement itself.
+pub fn visit_stmt<'a, V, T: StmtVisitor<'a, V>>(visitor: &mut T, data: &Stmt<'a>) -> V {
+    match data {
+        Stmt::Block(block) => block.accept(visitor),
+        Stmt::Class(class) => class.accept(visitor),
+        Stmt::Expression(expression) => expression.accept(visitor),
+        Stmt::Function(function) => function.accept(visitor),
+        Stmt::If(if_) => if_.accept(visitor),
+        Stmt::Print(print) => print.accept(visitor),
+        Stmt::Return(return_) => return_.accept(visitor),
+        Stmt::Var(var) => var.accept(visitor),
+        Stmt::While(while_) => while_.accept(visitor),
+    }
+}
+
+impl<'a, T, V> Visitor<Stmt<'a>, V> for T
+where
+    T: StmtVisitor<'a, V>,
+{
+    fn visit(&mut self, data: &Stmt<'a>) -> V {
+        self.visit_stmt(data)
+    }
+}
+
 /// Defines a data structure that can be visited by a visitor.
 pub trait Visitable<T>
 where
@@ -128,70 +508,64 @@ where
 }
 
 impl<T> Visitable<T> for Expr<'_> {}
+impl<T> Visitable<T> for Assign<'_> {}
 impl<T> Visitable<T> for Binary<'_> {}
+impl<T> Visitable<T> for Call<'_> {}
+impl<T> Visitable<T> for Get<'_> {}
 impl<T> Visitable<T> for Grouping<'_> {}
 impl<T> Visitable<T> for Literal<'_> {}
+impl<T> Visitable<T> for Logical<'_> {}
+impl<T> Visitable<T> for Sequence<'_> {}
+impl<T> Visitable<T> for Set<'_> {}
+impl<T> Visitable<T> for Super<'_> {}
+impl<T> Visitable<T> for This {}
 impl<T> Visitable<T> for Unary<'_> {}
+impl<T> Visitable<T> for Update<'_> {}
+impl<T> Visitable<T> for Variable<'_> {}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    // Sample visitor implementation that prints the expression.
-    struct AstPrinter;
-
-    // Implementing this marker trait enforces implementation of the Visitor for
-    // other Visitable types.
-    impl ExprVisitor<'_, String> for AstPrinter {}
-
-    impl Visitor<Binary<'_>, String> for AstPrinter {
-        fn visit(&mut self, data: &Binary<'_>) -> String {
-            format!(
-                "({} {} {})",
-                data.operator,
-                data.left.accept(self),
-                data.right.accept(self)
-            )
-        }
-    }
-
-    impl Visitor<Grouping<'_>, String> for AstPrinter {
-        fn visit(&mut self, data: &Grouping<'_>) -> String {
-            format!("(group {})", data.expression.accept(self))
-        }
-    }
+impl<T> Visitable<T> for Stmt<'_> {}
+impl<T> Visitable<T> for Block<'_> {}
+impl<T> Visitable<T> for Class<'_> {}
+impl<T> Visitable<T> for Expression<'_> {}
+impl<T> Visitable<T> for Function<'_> {}
+impl<T> Visitable<T> for If<'_> {}
+impl<T> Visitable<T> for Print<'_> {}
+impl<T> Visitable<T> for Return<'_> {}
+impl<T> Visitable<T> for Var<'_> {}
+impl<T> Visitable<T> for While<'_> {}
 
-    impl Visitor<Literal<'_>, String> for AstPrinter {
-        fn visit(&mut self, data: &Literal<'_>) -> String {
-            match data {
-                Literal::Number(n) => n.to_string(),
-                Literal::String(s) => s.to_string(),
-                Literal::Boolean(b) => b.to_string(),
-                Literal::Nil => "nil".to_string(),
-            }
-        }
-    }
-
-    impl Visitor<Unary<'_>, String> for AstPrinter {
-        fn visit(&mut self, data: &Unary<'_>) -> String {
-            format!("({} {})", data.operator, data.right.accept(self))
-        }
-    }
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use crate::Arena;
 
     #[test]
-    fn test_printer() {
-        let expr = Expr::Binary(Binary {
-            left: Box::new(Expr::Unary(Unary {
-                operator: UnaryOperator::Minus,
-                right: Box::new(Expr::Literal(Literal::Number(123.0))),
-            })),
-            operator: BinaryOperator::Star,
-            right: Box::new(Expr::Grouping(Grouping {
-                expression: Box::new(Expr::Literal(Literal::Number(45.67))),
-            })),
-        });
-        let mut printer = AstPrinter;
-        let result = expr.accept(&mut printer);
-        assert_eq!(result, "(* (- 123) (group 45.67))");
+    fn serialize() {
+        let arena = Arena::new();
+        let statements = crate::Parser::new(lexer::Lexer::new("print -a;"), &arena)
+            .parse()
+            .unwrap();
+        let expected = serde_json::json!([{
+            "Print": {
+                "expression": {
+                    "Unary": {
+                        "operator": "Minus",
+                        "right": {
+                            "Variable": {
+                                "name": {
+                                    "kind": "Identifier",
+                                    "lexeme": "a",
+                                    "span": [7, 8],
+                                    "line_col": { "line": 1, "column": 8 },
+                                    "literal": null
+                                }
+                            }
+                        },
+                        "span": [6, 7]
+                    }
+                },
+                "span": [0, 5]
+            }
+        }]);
+        assert_eq!(serde_json::to_value(&statements).unwrap(), expected);
     }
 }
@@ -1,3 +1,16 @@
+pub mod arena;
+pub mod constant;
+pub mod error;
 pub mod grammar;
 pub mod parser;
+pub mod printer;
+pub mod token_source;
+pub mod transform;
+pub mod walk;
 
+pub use {
+    arena::Arena,
+    error::{Error, Result},
+    parser::Parser,
+    token_source::{TokenSource, Tokens},
+};
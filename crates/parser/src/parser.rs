@@ -1,5 +1,1456 @@
-use lexer::Lexer;
+use {
+    crate::{
+        arena::Arena,
+        grammar::{
+            Assign,
+            Binary,
+            BinaryOperator,
+            Block,
+            Call,
+            Class,
+            Expr,
+            Expression,
+            Function,
+            Get,
+            Grouping,
+            If,
+            Literal,
+            LiteralValue,
+            Logical,
+            LogicalOperator,
+            Print,
+            Return,
+            Sequence,
+            Set,
+            Stmt,
+            Super,
+            This,
+            Unary,
+            UnaryOperator,
+            Update,
+            UpdateOperator,
+            Var,
+            Variable,
+            While,
+        },
+        token_source::TokenSource,
+        Error,
+        Result,
+    },
+    lexer::{
+        token::{Literal as TokenLiteral, Token, TokenKind, TokenSpan},
+        Lexer,
+        LineIndex,
+    },
+};
 
-pub struct Parser<'a> {
-    lexer: Lexer<'a>,
+/// Maximum number of arguments (and parameters) of a call.
+const MAX_ARGUMENTS: usize = 255;
+
+/// Recursive descent parser that turns a stream of tokens into an AST.
+pub struct Parser<'a, S = Lexer<'a>> {
+    /// Source of tokens.
+    tokens: S,
+
+    /// Arena the AST nodes are allocated in.
+    arena: &'a Arena,
+
+    /// Lookahead token, pulled from the token source on demand.
+    peeked: Option<Token<'a>>,
+
+    /// Span of the most recently consumed token.
+    last_span: TokenSpan,
+
+    /// End of input token, created once the token source is exhausted.
+    eof: Option<Token<'a>>,
+
+    /// Errors reported so far, both by the lexer and by the parser.
+    errors: Vec<Error>,
+}
+
+impl<'a, S: TokenSource<'a>> Parser<'a, S> {
+    /// Creates a new parser consuming tokens from the given source, usually a
+    /// [`Lexer`], and allocating the AST in the given arena.
+    pub fn new(tokens: S, arena: &'a Arena) -> Self {
+        Self {
+            tokens,
+            arena,
+            peeked: None,
+            last_span: TokenSpan::new(0, 0),
+            eof: None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Parses the whole token stream as a program.
+    ///
+    /// The parser doesn't stop at the first error: it skips to the next
+    /// statement boundary and keeps going, so that all errors in the input are
+    /// reported at once.
+    ///
+    /// program -> declaration* EOF
+    pub fn parse(&mut self) -> std::result::Result<Vec<Stmt<'a>>, Vec<Error>> {
+        let (statements, errors) = self.parse_partial();
+        if errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Parses the whole token stream as a program, returning the statements
+    /// that were parsed successfully along with all reported errors.
+    ///
+    /// Statements that failed to parse are left out, so tools such as editor
+    /// integrations can still work with the rest of the program.
+    pub fn parse_partial(&mut self) -> (Vec<Stmt<'a>>, Vec<Error>) {
+        let mut statements = Vec::new();
+        while self.peek().kind != TokenKind::Eof {
+            statements.extend(self.declaration());
+        }
+        (statements, std::mem::take(&mut self.errors))
+    }
+
+    /// Parses the whole token stream as a single expression, with no trailing
+    /// semicolon.
+    ///
+    /// This lets the REPL evaluate bare expressions, such as `1 + 2`.
+    pub fn parse_expression(&mut self) -> std::result::Result<Expr<'a>, Vec<Error>> {
+        let result = self
+            .expression()
+            .and_then(|expr| self.consume(TokenKind::Eof, "end of input").map(|_| expr));
+        match result {
+            Ok(expr) if self.errors.is_empty() => Ok(expr),
+            Ok(_) => Err(std::mem::take(&mut self.errors)),
+            Err(e) => {
+                self.errors.push(e);
+                Err(std::mem::take(&mut self.errors))
+            }
+        }
+    }
+
+    /// declaration -> classDecl | funDecl | varDecl | statement
+    ///
+    /// Errors are recorded, and the parser synchronizes on the next statement
+    /// boundary, in which case `None` is returned.
+    fn declaration(&mut self) -> Option<Stmt<'a>> {
+        let stmt = match self.peek().kind {
+            TokenKind::Class => self.class_declaration(),
+            TokenKind::Fun => {
+                self.advance();
+                self.function("function").map(Stmt::Function)
+            }
+            TokenKind::Var => {
+                self.advance();
+                self.var_declaration()
+            }
+            _ => self.statement(),
+        };
+        match stmt {
+            Ok(stmt) => Some(stmt),
+            Err(e) => {
+                self.errors.push(e);
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    /// Discards tokens until the beginning of the next statement.
+    fn synchronize(&mut self) {
+        while self.peek().kind != TokenKind::Eof {
+            if self.advance().kind == TokenKind::Semicolon {
+                return;
+            }
+            if matches!(
+                self.peek().kind,
+                TokenKind::Class
+                    | TokenKind::Fun
+                    | TokenKind::Var
+                    | TokenKind::For
+                    | TokenKind::If
+                    | TokenKind::While
+                    | TokenKind::Print
+                    | TokenKind::Return
+            ) {
+                return;
+            }
+        }
+    }
+
+    /// classDecl -> "class" IDENTIFIER ( "<" IDENTIFIER )? "{" function* "}"
+    fn class_declaration(&mut self) -> Result<Stmt<'a>> {
+        self.advance();
+        let name = self.consume(TokenKind::Identifier, "class name")?;
+        let superclass = match self.advance_if(&[TokenKind::Less]) {
+            Some(_) => Some(Variable {
+                name: self.consume(TokenKind::Identifier, "superclass name")?,
+            }),
+            None => None,
+        };
+        let left_brace = self.consume(TokenKind::LeftBrace, "'{' before class body")?;
+        let mut methods = Vec::new();
+        while !matches!(self.peek().kind, TokenKind::RightBrace | TokenKind::Eof) {
+            methods.push(self.function("method")?);
+        }
+        if self.advance_if(&[TokenKind::RightBrace]).is_none() {
+            return Err(Error::UnterminatedBlock {
+                src: self.src(),
+                at: left_brace.span.into(),
+                end: self.peek().span.into(),
+            });
+        }
+        Ok(Stmt::Class(Class {
+            name,
+            superclass,
+            methods: self.arena.alloc_slice(methods),
+        }))
+    }
+
+    /// function -> IDENTIFIER "(" parameters? ")" block
+    /// parameters -> IDENTIFIER ( "," IDENTIFIER )*
+    fn function(&mut self, kind: &str) -> Result<Function<'a>> {
+        let name = self.consume(TokenKind::Identifier, &format!("{kind} name"))?;
+        self.consume(TokenKind::LeftParen, &format!("'(' after {kind} name"))?;
+        let mut params = Vec::new();
+        if self.peek().kind != TokenKind::RightParen {
+            loop {
+                let param = self.consume(TokenKind::Identifier, "parameter name")?;
+                if params.len() == MAX_ARGUMENTS {
+                    self.errors.push(Error::TooManyArguments {
+                        src: self.src(),
+                        at: param.span.into(),
+                        what: "parameters",
+                        max: MAX_ARGUMENTS,
+                    });
+                }
+                params.push(param);
+                if self.advance_if(&[TokenKind::Comma]).is_none() {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenKind::RightParen, "')' after parameters")?;
+        let left_brace = self.consume(TokenKind::LeftBrace, &format!("'{{' before {kind} body"))?;
+        let body = self.block(left_brace)?.statements;
+        Ok(Function {
+            name,
+            params: self.arena.alloc_slice(params),
+            body,
+        })
+    }
+
+    /// varDecl -> "var" IDENTIFIER ( "=" expression )? ";"
+    fn var_declaration(&mut self) -> Result<Stmt<'a>> {
+        let name = self.consume(TokenKind::Identifier, "variable name")?;
+        let initializer = match self.advance_if(&[TokenKind::Equal]) {
+            Some(_) => Some(self.expression()?),
+            None => None,
+        };
+        self.consume(TokenKind::Semicolon, "';' after variable declaration")?;
+        Ok(Stmt::Var(Var { name, initializer }))
+    }
+
+    /// statement -> exprStmt | forStmt | ifStmt | printStmt | returnStmt |
+    /// whileStmt | block
+    fn statement(&mut self) -> Result<Stmt<'a>> {
+        let token = self.peek();
+        match token.kind {
+            TokenKind::For => self.for_statement(),
+            TokenKind::If => self.if_statement(),
+            TokenKind::Print => self.print_statement(),
+            TokenKind::Return => self.return_statement(),
+            TokenKind::While => self.while_statement(),
+            TokenKind::LeftBrace => {
+                self.advance();
+                self.block(token).map(Stmt::Block)
+            }
+            _ => self.expression_statement(),
+        }
+    }
+
+    /// forStmt -> "for" "(" ( varDecl | exprStmt | ";" ) expression? ";"
+    /// expression? ")" statement
+    ///
+    /// The loop is desugared into a `while` loop, wrapped into a block when
+    /// there is an initializer.
+    fn for_statement(&mut self) -> Result<Stmt<'a>> {
+        let keyword = self.advance();
+        self.consume(TokenKind::LeftParen, "'(' after 'for'")?;
+        let initializer = match self.peek().kind {
+            TokenKind::Semicolon => {
+                self.advance();
+                None
+            }
+            TokenKind::Var => {
+                self.advance();
+                Some(self.var_declaration()?)
+            }
+            _ => Some(self.expression_statement()?),
+        };
+        let condition = match self.peek().kind {
+            TokenKind::Semicolon => None,
+            _ => Some(self.expression()?),
+        };
+        let semicolon = self.consume(TokenKind::Semicolon, "';' after loop condition")?;
+        let increment = match self.peek().kind {
+            TokenKind::RightParen => None,
+            _ => Some(self.expression()?),
+        };
+        self.consume(TokenKind::RightParen, "')' after for clauses")?;
+        let mut body = self.statement()?;
+        let span = keyword.span.merge(self.last_span);
+
+        if let Some(increment) = increment {
+            body = Stmt::Block(Block {
+                statements: self.arena.alloc_slice(vec![
+                    body,
+                    Stmt::Expression(Expression {
+                        expression: increment,
+                    }),
+                ]),
+                span,
+            });
+        }
+        // Missing condition means an infinite loop.
+        let condition = condition.unwrap_or(Expr::Literal(Literal {
+            value: LiteralValue::Boolean(true),
+            span: semicolon.span,
+        }));
+        body = Stmt::While(While {
+            condition,
+            body: self.arena.alloc(body),
+            span: keyword.span,
+        });
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(Block {
+                statements: self.arena.alloc_slice(vec![initializer, body]),
+                span,
+            });
+        }
+        Ok(body)
+    }
+
+    /// ifStmt -> "if" "(" expression ")" statement ( "else" statement )?
+    fn if_statement(&mut self) -> Result<Stmt<'a>> {
+        let keyword = self.advance();
+        self.consume(TokenKind::LeftParen, "'(' after 'if'")?;
+        let condition = self.expression()?;
+        self.consume(TokenKind::RightParen, "')' after if condition")?;
+        let then_branch = self.arena.alloc(self.statement()?);
+        // The `else` binds to the nearest `if`.
+        let else_branch = match self.advance_if(&[TokenKind::Else]) {
+            Some(_) => Some(self.arena.alloc(self.statement()?)),
+            None => None,
+        };
+        Ok(Stmt::If(If {
+            condition,
+            then_branch,
+            else_branch,
+            span: keyword.span,
+        }))
+    }
+
+    /// whileStmt -> "while" "(" expression ")" statement
+    fn while_statement(&mut self) -> Result<Stmt<'a>> {
+        let keyword = self.advance();
+        self.consume(TokenKind::LeftParen, "'(' after 'while'")?;
+        let condition = self.expression()?;
+        self.consume(TokenKind::RightParen, "')' after condition")?;
+        let body = self.arena.alloc(self.statement()?);
+        Ok(Stmt::While(While {
+            condition,
+            body,
+            span: keyword.span,
+        }))
+    }
+
+    /// block -> "{" declaration* "}"
+    fn block(&mut self, left_brace: Token<'a>) -> Result<Block<'a>> {
+        let mut statements = Vec::new();
+        while !matches!(self.peek().kind, TokenKind::RightBrace | TokenKind::Eof) {
+            statements.extend(self.declaration());
+        }
+        let Some(right_brace) = self.advance_if(&[TokenKind::RightBrace]) else {
+            return Err(Error::UnterminatedBlock {
+                src: self.src(),
+                at: left_brace.span.into(),
+                end: self.peek().span.into(),
+            });
+        };
+        Ok(Block {
+            statements: self.arena.alloc_slice(statements),
+            span: left_brace.span.merge(right_brace.span),
+        })
+    }
+
+    /// printStmt -> "print" expression ";"
+    fn print_statement(&mut self) -> Result<Stmt<'a>> {
+        let keyword = self.advance();
+        let expression = self.expression()?;
+        self.consume(TokenKind::Semicolon, "';' after value")?;
+        Ok(Stmt::Print(Print {
+            expression,
+            span: keyword.span,
+        }))
+    }
+
+    /// returnStmt -> "return" expression? ";"
+    fn return_statement(&mut self) -> Result<Stmt<'a>> {
+        let keyword = self.advance();
+        let value = match self.peek().kind {
+            TokenKind::Semicolon => None,
+            _ => Some(self.expression()?),
+        };
+        self.consume(TokenKind::Semicolon, "';' after return value")?;
+        Ok(Stmt::Return(Return {
+            value,
+            span: keyword.span,
+        }))
+    }
+
+    /// exprStmt -> expression ";"
+    fn expression_statement(&mut self) -> Result<Stmt<'a>> {
+        let expression = self.expression()?;
+        self.consume(TokenKind::Semicolon, "';' after expression")?;
+        Ok(Stmt::Expression(Expression { expression }))
+    }
+
+    /// expression -> assignment ( "," assignment )*
+    fn expression(&mut self) -> Result<Expr<'a>> {
+        self.parse_precedence(Precedence::Comma)
+    }
+
+    /// Parses an expression containing only operators that bind at least as
+    /// tightly as `min`.
+    ///
+    /// The operand is parsed by `prefix`, then infix and postfix operators are
+    /// folded into it, in a loop, for as long as their precedence (as given by
+    /// `Precedence::of`) allows. Deeply nested expressions therefore don't
+    /// recurse through every precedence level.
+    fn parse_precedence(&mut self, min: Precedence) -> Result<Expr<'a>> {
+        let mut expr = self.prefix()?;
+        loop {
+            let token = self.peek();
+            match Precedence::of(token.kind) {
+                Some(precedence) if precedence >= min => {
+                    self.advance();
+                    expr = self.infix(expr, token, precedence)?;
+                }
+                _ => break,
+            }
+        }
+        Ok(expr)
+    }
+
+    /// prefix -> ( "!" | "-" | "++" | "--" ) prefix | primary
+    fn prefix(&mut self) -> Result<Expr<'a>> {
+        // Error production: a binary operator at the start of an expression.
+        // Its right operand is parsed and discarded, so that the error is
+        // reported once, rather than again for the operand.
+        let token = self.peek();
+        if let Some(precedence) = Precedence::of(token.kind).filter(|_| {
+            !matches!(
+                token.kind,
+                TokenKind::Minus
+                    | TokenKind::LeftParen
+                    | TokenKind::Dot
+                    | TokenKind::Equal
+                    | TokenKind::PlusPlus
+                    | TokenKind::MinusMinus
+            )
+        }) {
+            self.advance();
+            self.parse_precedence(precedence.next())?;
+            return Err(Error::MissingLeftOperand {
+                src: self.src(),
+                at: token.span.into(),
+                operator: token.lexeme.to_string(),
+            });
+        }
+        if let Some(token) = self.advance_if(&[TokenKind::Bang, TokenKind::Minus]) {
+            let operator = match token.kind {
+                TokenKind::Bang => UnaryOperator::Bang,
+                _ => UnaryOperator::Minus,
+            };
+            return Ok(Expr::Unary(Unary {
+                operator,
+                right: self.arena.alloc(self.parse_precedence(Precedence::Unary)?),
+                span: token.span,
+            }));
+        }
+        if let Some(token) = self.advance_if(&[TokenKind::PlusPlus, TokenKind::MinusMinus]) {
+            let target = self.parse_precedence(Precedence::Unary)?;
+            return Ok(self.update(target, token, true));
+        }
+        self.primary()
+    }
+
+    /// Parses the rest of an infix or postfix expression, whose left operand
+    /// and operator `token` (of the given precedence) were already consumed.
+    fn infix(
+        &mut self,
+        left: Expr<'a>,
+        token: Token<'a>,
+        precedence: Precedence,
+    ) -> Result<Expr<'a>> {
+        let expr = match token.kind {
+            // Right-associative: the right operand may be another assignment.
+            TokenKind::Equal => {
+                let value = self.parse_precedence(Precedence::Assignment)?;
+                self.assignment(left, value)
+            }
+            TokenKind::And | TokenKind::Or => Expr::Logical(Logical {
+                left: self.arena.alloc(left),
+                operator: match token.kind {
+                    TokenKind::And => LogicalOperator::And,
+                    _ => LogicalOperator::Or,
+                },
+                right: self.arena.alloc(self.parse_precedence(precedence.next())?),
+                span: token.span,
+            }),
+            TokenKind::Comma => Expr::Sequence(Sequence {
+                left: self.arena.alloc(left),
+                right: self.arena.alloc(self.parse_precedence(precedence.next())?),
+                span: token.span,
+            }),
+            TokenKind::LeftParen => self.finish_call(left)?,
+            TokenKind::PlusPlus | TokenKind::MinusMinus => self.update(left, token, false),
+            TokenKind::Dot => Expr::Get(Get {
+                object: self.arena.alloc(left),
+                name: self.consume(TokenKind::Identifier, "property name after '.'")?,
+                optional: false,
+            }),
+            TokenKind::QuestionDot => Expr::Get(Get {
+                object: self.arena.alloc(left),
+                name: self.consume(TokenKind::Identifier, "property name after '?.'")?,
+                optional: true,
+            }),
+            kind => Expr::Binary(Binary {
+                left: self.arena.alloc(left),
+                operator: binary_operator(kind),
+                right: self.arena.alloc(self.parse_precedence(precedence.next())?),
+                span: token.span,
+            }),
+        };
+        Ok(expr)
+    }
+
+    /// assignment -> ( call "." )? IDENTIFIER "=" assignment
+    fn assignment(&mut self, target: Expr<'a>, value: Expr<'a>) -> Expr<'a> {
+        match target {
+            Expr::Variable(variable) => Expr::Assign(Assign {
+                name: variable.name,
+                value: self.arena.alloc(value),
+            }),
+            // A null-safe access has nothing to assign to if the chain is
+            // short-circuited.
+            Expr::Get(get) if !is_optional_chain(&target) => Expr::Set(Set {
+                object: get.object,
+                name: get.name,
+                value: self.arena.alloc(value),
+            }),
+            _ => {
+                // The parser isn't in a confused state, so there is no need to
+                // synchronize: report the error and carry on.
+                self.errors.push(Error::InvalidAssignmentTarget {
+                    src: self.src(),
+                    at: target.span().into(),
+                });
+                target
+            }
+        }
+    }
+
+    /// Increments or decrements the target with the operator `token`, if the
+    /// target is a variable or a field.
+    fn update(&mut self, target: Expr<'a>, token: Token<'a>, prefix: bool) -> Expr<'a> {
+        let updatable = match target {
+            Expr::Variable(_) => true,
+            Expr::Get(_) => !is_optional_chain(&target),
+            _ => false,
+        };
+        if !updatable {
+            self.errors.push(Error::InvalidAssignmentTarget {
+                src: self.src(),
+                at: target.span().into(),
+            });
+            return target;
+        }
+        Expr::Update(Update {
+            operator: match token.kind {
+                TokenKind::PlusPlus => UpdateOperator::Increment,
+                _ => UpdateOperator::Decrement,
+            },
+            target: self.arena.alloc(target),
+            prefix,
+            span: token.span,
+        })
+    }
+
+    /// arguments -> assignment ( "," assignment )*
+    ///
+    /// Arguments are parsed above the comma precedence, so that commas
+    /// separate arguments rather than form sequence expressions.
+    fn finish_call(&mut self, callee: Expr<'a>) -> Result<Expr<'a>> {
+        let mut arguments = Vec::new();
+        if self.peek().kind != TokenKind::RightParen {
+            loop {
+                let argument = self.parse_precedence(Precedence::Assignment)?;
+                if arguments.len() == MAX_ARGUMENTS {
+                    self.errors.push(Error::TooManyArguments {
+                        src: self.src(),
+                        at: argument.span().into(),
+                        what: "arguments",
+                        max: MAX_ARGUMENTS,
+                    });
+                }
+                arguments.push(argument);
+                if self.advance_if(&[TokenKind::Comma]).is_none() {
+                    break;
+                }
+            }
+        }
+        let right_paren = self.consume(TokenKind::RightParen, "')' after arguments")?;
+        Ok(Expr::Call(Call {
+            callee: self.arena.alloc(callee),
+            arguments: self.arena.alloc_slice(arguments),
+            span: right_paren.span,
+        }))
+    }
+
+    /// primary -> NUMBER | STRING | "true" | "false" | "nil" | "this" | "("
+    /// expression ")" | IDENTIFIER | "super" "." IDENTIFIER
+    fn primary(&mut self) -> Result<Expr<'a>> {
+        let token = self.peek();
+        if !matches!(
+            token.kind,
+            TokenKind::Number
+                | TokenKind::String
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::Nil
+                | TokenKind::LeftParen
+                | TokenKind::Identifier
+                | TokenKind::This
+                | TokenKind::Super
+        ) {
+            return Err(Error::ExpectedExpression {
+                src: self.src(),
+                at: token.span.into(),
+                found: describe(&token),
+            });
+        }
+        self.advance();
+        let value = match token.kind {
+            TokenKind::Number | TokenKind::String => {
+                // Tokens that don't come from the lexer may lack their value,
+                // or have an invalid lexeme.
+                match token
+                    .literal
+                    .or_else(|| TokenLiteral::parse(token.kind, token.lexeme))
+                {
+                    Some(TokenLiteral::Number(value)) => LiteralValue::Number(value),
+                    Some(TokenLiteral::String(value)) => LiteralValue::String(value),
+                    None => {
+                        return Err(Error::InvalidLiteral {
+                            src: self.src(),
+                            at: token.span.into(),
+                            kind: if token.kind == TokenKind::Number {
+                                "number"
+                            } else {
+                                "string"
+                            },
+                            lexeme: token.lexeme.to_string(),
+                        })
+                    }
+                }
+            }
+            TokenKind::True => LiteralValue::Boolean(true),
+            TokenKind::False => LiteralValue::Boolean(false),
+            TokenKind::Nil => LiteralValue::Nil,
+            TokenKind::Identifier => return Ok(Expr::Variable(Variable { name: token })),
+            TokenKind::This => return Ok(Expr::This(This { span: token.span })),
+            TokenKind::Super => {
+                self.consume(TokenKind::Dot, "'.' after 'super'")?;
+                let method = self.consume(TokenKind::Identifier, "superclass method name")?;
+                return Ok(Expr::Super(Super {
+                    method,
+                    span: token.span,
+                }));
+            }
+            TokenKind::LeftParen => {
+                let expression = self.expression()?;
+                let right_paren = self.consume(TokenKind::RightParen, "')' after expression")?;
+                return Ok(Expr::Grouping(Grouping {
+                    expression: self.arena.alloc(expression),
+                    span: TokenSpan::new(token.span.start(), right_paren.span.end()),
+                }));
+            }
+            _ => unreachable!("checked above"),
+        };
+        Ok(Expr::Literal(Literal {
+            value,
+            span: token.span,
+        }))
+    }
+
+    /// Returns a copy of the source code, to be attached to diagnostics.
+    fn src(&self) -> String {
+        self.tokens.source().to_string()
+    }
+
+    /// Returns the next token without consuming it.
+    ///
+    /// Lexer errors are recorded and the offending input is skipped, so the
+    /// parser always sees a well-formed token.
+    fn peek(&mut self) -> Token<'a> {
+        if let Some(token) = self.peeked {
+            return token;
+        }
+        let token = loop {
+            match self.tokens.next() {
+                Some(Ok(token)) if token.kind.is_comment() => continue,
+                Some(Ok(token)) => break token,
+                Some(Err(e)) => self.errors.push(e.into()),
+                None => break self.eof(),
+            }
+        };
+        self.peeked = Some(token);
+        token
+    }
+
+    fn eof(&mut self) -> Token<'a> {
+        let src = self.tokens.source();
+        *self
+            .eof
+            .get_or_insert_with(|| Token::eof(src.len(), LineIndex::new(src).line_col(src.len())))
+    }
+
+    /// Consumes the next token.
+    fn advance(&mut self) -> Token<'a> {
+        let token = self.peek();
+        self.peeked = None;
+        self.last_span = token.span;
+        token
+    }
+
+    /// Consumes the next token if it is of one of the given kinds.
+    fn advance_if(&mut self, kinds: &[TokenKind]) -> Option<Token<'a>> {
+        if kinds.contains(&self.peek().kind) {
+            return Some(self.advance());
+        }
+        None
+    }
+
+    /// Consumes the next token, which must be of the given kind.
+    fn consume(&mut self, kind: TokenKind, expected: &str) -> Result<Token<'a>> {
+        let token = self.peek();
+        if token.kind != kind {
+            return Err(Error::UnexpectedToken {
+                src: self.src(),
+                at: token.span.into(),
+                expected: expected.to_string(),
+                found: describe(&token),
+            });
+        }
+        Ok(self.advance())
+    }
+}
+
+/// Binding power of infix and postfix operators, from the loosest to the
+/// tightest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Comma,
+    Assignment,
+    Or,
+    And,
+    Equality,
+    Comparison,
+    Term,
+    Factor,
+    Unary,
+    Call,
+}
+
+impl Precedence {
+    /// Returns the precedence of the given token when used as an infix or
+    /// postfix operator, or `None` if it can't be used as one.
+    fn of(kind: TokenKind) -> Option<Self> {
+        let precedence = match kind {
+            TokenKind::Comma => Self::Comma,
+            TokenKind::Equal => Self::Assignment,
+            TokenKind::Or => Self::Or,
+            TokenKind::And => Self::And,
+            TokenKind::BangEqual | TokenKind::EqualEqual => Self::Equality,
+            TokenKind::Greater
+            | TokenKind::GreaterEqual
+            | TokenKind::Less
+            | TokenKind::LessEqual => Self::Comparison,
+            TokenKind::Minus | TokenKind::Plus => Self::Term,
+            TokenKind::Slash | TokenKind::Star => Self::Factor,
+            TokenKind::LeftParen
+            | TokenKind::Dot
+            | TokenKind::QuestionDot
+            | TokenKind::PlusPlus
+            | TokenKind::MinusMinus => Self::Call,
+            _ => return None,
+        };
+        Some(precedence)
+    }
+
+    /// Returns the next tighter precedence, used for the right operand of
+    /// left-associative operators.
+    fn next(self) -> Self {
+        match self {
+            Self::Comma => Self::Assignment,
+            Self::Assignment => Self::Or,
+            Self::Or => Self::And,
+            Self::And => Self::Equality,
+            Self::Equality => Self::Comparison,
+            Self::Comparison => Self::Term,
+            Self::Term => Self::Factor,
+            Self::Factor => Self::Unary,
+            Self::Unary | Self::Call => Self::Call,
+        }
+    }
+}
+
+/// Maps an operator token to the corresponding binary operator.
+/// Whether the expression is a chain of property accesses and calls with a
+/// null-safe access, e.g. `a?.b.c`, whose evaluation may be short-circuited.
+fn is_optional_chain(expr: &Expr<'_>) -> bool {
+    match expr {
+        Expr::Get(get) => get.optional || is_optional_chain(get.object),
+        Expr::Call(call) => is_optional_chain(call.callee),
+        _ => false,
+    }
+}
+
+fn binary_operator(kind: TokenKind) -> BinaryOperator {
+    match kind {
+        TokenKind::EqualEqual => BinaryOperator::EqualEqual,
+        TokenKind::BangEqual => BinaryOperator::BangEqual,
+        TokenKind::Less => BinaryOperator::Less,
+        TokenKind::LessEqual => BinaryOperator::LessEqual,
+        TokenKind::Greater => BinaryOperator::Greater,
+        TokenKind::GreaterEqual => BinaryOperator::GreaterEqual,
+        TokenKind::Plus => BinaryOperator::Plus,
+        TokenKind::Minus => BinaryOperator::Minus,
+        TokenKind::Star => BinaryOperator::Star,
+        TokenKind::Slash => BinaryOperator::Slash,
+        _ => unreachable!("not a binary operator: {kind}"),
+    }
+}
+
+/// Describes a token for use in error messages.
+fn describe(token: &Token<'_>) -> String {
+    match token.kind {
+        TokenKind::Eof => "end of input".to_string(),
+        _ => format!("'{}'", token.lexeme),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{grammar::Visitable, printer::AstPrinter},
+        lexer::{LanguageLevel, LexerConfig},
+        miette::Diagnostic,
+        pretty_assertions::assert_eq,
+    };
+
+    /// Creates a parser over the input. The arena is leaked, so that helpers
+    /// can return the AST.
+    fn parser(input: &str) -> Parser<'_> {
+        Parser::new(Lexer::new(input), Box::leak(Box::default()))
+    }
+
+    /// Creates a parser over the input, at the extended language level.
+    fn extended(input: &str) -> Parser<'_> {
+        let lexer = Lexer::new(input).with_config(LexerConfig {
+            level: LanguageLevel::Extended,
+            ..LexerConfig::default()
+        });
+        Parser::new(lexer, Box::leak(Box::default()))
+    }
+
+    /// Parses a single expression, returning the first reported error if any.
+    fn parse(input: &str) -> Result<Expr<'_>> {
+        parser(input)
+            .parse_expression()
+            .map_err(|mut errors| errors.remove(0))
+    }
+
+    /// Parses a program, returning the first reported error if any.
+    fn parse_program(input: &str) -> Result<Vec<Stmt<'_>>> {
+        parser(input).parse().map_err(|mut errors| errors.remove(0))
+    }
+
+    fn print_stmt(stmt: &Stmt<'_>) -> String {
+        stmt.accept(&mut AstPrinter)
+    }
+
+    fn assert_program(input: &str, expected: &[&str]) {
+        let statements = parse_program(input).unwrap();
+        let printed: Vec<_> = statements.iter().map(print_stmt).collect();
+        assert_eq!(printed, expected);
+    }
+
+    fn assert_ast(input: &str, expected: &str) {
+        let expr = parse(input).unwrap();
+        assert_eq!(expr.accept(&mut AstPrinter), expected);
+    }
+
+    #[test]
+    fn literals() {
+        assert_ast("123", "123");
+        assert_ast("45.67", "45.67");
+        assert_ast("1_000.5", "1000.5");
+        assert_ast("\"hello\"", "hello");
+        assert_ast("true", "true");
+        assert_ast("false", "false");
+        assert_ast("nil", "nil");
+    }
+
+    #[test]
+    fn precedence() {
+        assert_ast("1 + 2 * 3", "(+ 1 (* 2 3))");
+        assert_ast("(1 + 2) * 3", "(* (group (+ 1 2)) 3)");
+        assert_ast("1 < 2 == 3 >= 4", "(== (< 1 2) (>= 3 4))");
+        assert_ast("-1 - -2", "(- (- 1) (- 2))");
+        assert_ast("!!true != false", "(!= (! (! true)) false)");
+    }
+
+    #[test]
+    fn associativity() {
+        assert_ast("1 - 2 - 3", "(- (- 1 2) 3)");
+        assert_ast("8 / 4 / 2", "(/ (/ 8 4) 2)");
+        assert_ast("1 == 2 == 3", "(== (== 1 2) 3)");
+    }
+
+    #[test]
+    fn spans() {
+        let Expr::Binary(binary) = parse("(1) + 22").unwrap() else {
+            panic!("expected binary expression");
+        };
+        assert_eq!(binary.span, TokenSpan::new(4, 5));
+        let Expr::Grouping(grouping) = binary.left else {
+            panic!("expected grouping");
+        };
+        assert_eq!(grouping.span, TokenSpan::new(0, 3));
+        let Expr::Literal(literal) = binary.right else {
+            panic!("expected literal");
+        };
+        assert_eq!(literal.span, TokenSpan::new(6, 8));
+    }
+
+    #[test]
+    fn statement_spans() {
+        let input = "print 1; if (a) b; else { c; } var d = 2; while (e) f();";
+        let spans: Vec<_> = parse_program(input)
+            .unwrap()
+            .iter()
+            .map(|stmt| stmt.span())
+            .collect();
+        assert_eq!(spans, &[
+            TokenSpan::new(0, 7),
+            TokenSpan::new(9, 30),
+            TokenSpan::new(35, 40),
+            TokenSpan::new(42, 55),
+        ]);
+    }
+
+    #[test]
+    fn comments() {
+        let input = "print /* one */ 1; // done";
+        let statements = Parser::new(Lexer::new(input).with_comments(true), &Arena::new())
+            .parse()
+            .map(|statements| statements.iter().map(print_stmt).collect::<Vec<_>>());
+        assert_eq!(statements.unwrap(), &["(print 1)"]);
+    }
+
+    #[test]
+    fn errors() {
+        let input = "(1 + 2";
+        assert_eq!(parse(input).unwrap_err(), Error::UnexpectedToken {
+            src: input.to_string(),
+            at: (6, 0).into(),
+            expected: "')' after expression".to_string(),
+            found: "end of input".to_string()
+        });
+        let input = "1 + ;";
+        assert_eq!(parse(input).unwrap_err(), Error::ExpectedExpression {
+            src: input.to_string(),
+            at: (4, 1).into(),
+            found: "';'".to_string()
+        });
+        let input = "1 2";
+        assert_eq!(parse(input).unwrap_err(), Error::UnexpectedToken {
+            src: input.to_string(),
+            at: (2, 1).into(),
+            expected: "end of input".to_string(),
+            found: "'2'".to_string()
+        });
+        assert!(matches!(
+            parse("1 + @").unwrap_err(),
+            Error::LexerError(lexer::Error::UnexpectedChar { c: '@', .. })
+        ));
+    }
+
+    #[test]
+    fn statements() {
+        assert_program("", &[]);
+        assert_program("print 1 + 2;", &["(print (+ 1 2))"]);
+        assert_program("1 + 2; print \"one\";\n-3;", &[
+            "(; (+ 1 2))",
+            "(print one)",
+            "(; (- 3))",
+        ]);
+
+        let statements = parse_program("  print nil;").unwrap();
+        let Stmt::Print(print) = &statements[0] else {
+            panic!("expected print statement");
+        };
+        assert_eq!(print.span, TokenSpan::new(2, 7));
+    }
+
+    #[test]
+    fn statement_errors() {
+        let input = "print 1";
+        assert_eq!(parse_program(input).unwrap_err(), Error::UnexpectedToken {
+            src: input.to_string(),
+            at: (7, 0).into(),
+            expected: "';' after value".to_string(),
+            found: "end of input".to_string()
+        });
+        let input = "1 + 2 print 3;";
+        assert_eq!(parse_program(input).unwrap_err(), Error::UnexpectedToken {
+            src: input.to_string(),
+            at: (6, 5).into(),
+            expected: "';' after expression".to_string(),
+            found: "'print'".to_string()
+        });
+    }
+
+    #[test]
+    fn synchronization() {
+        // Every broken statement is reported, and parsing resumes after it.
+        let input = "print 1 +; var = 2; print 3; { 4 + ; print 5; } a + b = c; @ print 6";
+        let errors = parser(input).parse().unwrap_err();
+        let labels: Vec<_> = errors
+            .iter()
+            .map(|e| (e.to_string(), e.labels().unwrap().next().unwrap().offset()))
+            .collect();
+        assert_eq!(labels, &[
+            ("Expected expression, found ';'".to_string(), 9),
+            ("Expected variable name, found '='".to_string(), 15),
+            ("Expected expression, found ';'".to_string(), 35),
+            ("Invalid assignment target".to_string(), 48),
+            ("Unexpected character: @".to_string(), 59),
+            (
+                "Expected ';' after value, found end of input".to_string(),
+                68
+            ),
+        ]);
+
+        // Statements parsed after the recovery point are well-formed.
+        let mut parser = parser("var = 1; print 2;");
+        assert!(parser.declaration().is_none());
+        assert_eq!(print_stmt(&parser.declaration().unwrap()), "(print 2)");
+    }
+
+    #[test]
+    fn expression_entry_point() {
+        let expr = parser("1 + 2").parse_expression().unwrap();
+        assert_eq!(expr.accept(&mut AstPrinter), "(+ 1 2)");
+
+        let input = "1 + 2;";
+        assert_eq!(parser(input).parse_expression().unwrap_err(), &[
+            Error::UnexpectedToken {
+                src: input.to_string(),
+                at: (5, 1).into(),
+                expected: "end of input".to_string(),
+                found: "';'".to_string()
+            }
+        ]);
+        let input = "(a + b = c";
+        assert_eq!(parser(input).parse_expression().unwrap_err(), &[
+            Error::InvalidAssignmentTarget {
+                src: input.to_string(),
+                at: (1, 5).into(),
+            },
+            Error::UnexpectedToken {
+                src: input.to_string(),
+                at: (10, 0).into(),
+                expected: "')' after expression".to_string(),
+                found: "end of input".to_string()
+            },
+        ]);
+    }
+
+    #[test]
+    fn partial_ast() {
+        let input = "print 1; var = 2; { print 3; print; } print 4";
+        let (statements, errors) = parser(input).parse_partial();
+        let printed: Vec<_> = statements.iter().map(print_stmt).collect();
+        assert_eq!(printed, &["(print 1)", "(block (print 3))"]);
+        assert_eq!(errors.len(), 3);
+
+        let (statements, errors) = parser("print 1;").parse_partial();
+        assert_eq!(statements.len(), 1);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn missing_left_operand() {
+        let input = "+ 3;";
+        assert_eq!(
+            parse_program(input).unwrap_err(),
+            Error::MissingLeftOperand {
+                src: input.to_string(),
+                at: (0, 1).into(),
+                operator: "+".to_string()
+            }
+        );
+        let input = "print == 1 + 2; print and;";
+        let errors = parser(input).parse().unwrap_err();
+        assert_eq!(errors, &[
+            Error::MissingLeftOperand {
+                src: input.to_string(),
+                at: (6, 2).into(),
+                operator: "==".to_string()
+            },
+            Error::ExpectedExpression {
+                src: input.to_string(),
+                at: (25, 1).into(),
+                found: "';'".to_string()
+            },
+        ]);
+    }
+
+    #[test]
+    fn variables() {
+        assert_program("var a; var b = 1 + 2; print a + b;", &[
+            "(var a)",
+            "(var b (+ 1 2))",
+            "(print (+ a b))",
+        ]);
+        assert_program("a = b = c;", &["(; (= a (= b c)))"]);
+        assert_program("a = 1 == 2;", &["(; (= a (== 1 2)))"]);
+        assert_eq!(parse("a = 1").unwrap().span(), TokenSpan::new(0, 5));
+    }
+
+    #[test]
+    fn variable_errors() {
+        let input = "var 1 = 2;";
+        assert_eq!(parse_program(input).unwrap_err(), Error::UnexpectedToken {
+            src: input.to_string(),
+            at: (4, 1).into(),
+            expected: "variable name".to_string(),
+            found: "'1'".to_string()
+        });
+        let input = "var a = 1";
+        assert_eq!(parse_program(input).unwrap_err(), Error::UnexpectedToken {
+            src: input.to_string(),
+            at: (9, 0).into(),
+            expected: "';' after variable declaration".to_string(),
+            found: "end of input".to_string()
+        });
+        let input = "a + b = c;";
+        assert_eq!(
+            parse_program(input).unwrap_err(),
+            Error::InvalidAssignmentTarget {
+                src: input.to_string(),
+                at: (0, 5).into()
+            }
+        );
+        let input = "(a) = 1;";
+        assert_eq!(
+            parse_program(input).unwrap_err(),
+            Error::InvalidAssignmentTarget {
+                src: input.to_string(),
+                at: (0, 3).into()
+            }
+        );
+    }
+
+    #[test]
+    fn blocks() {
+        assert_program("{}", &["(block)"]);
+        assert_program("{ var a = 1; { print a; } } a;", &[
+            "(block (var a 1) (block (print a)))",
+            "(; a)",
+        ]);
+
+        let statements = parse_program("{ 1; }").unwrap();
+        let Stmt::Block(block) = &statements[0] else {
+            panic!("expected block");
+        };
+        assert_eq!(block.span, TokenSpan::new(0, 6));
+
+        let input = "print 0;\n{ var a = 1;\n{ }";
+        assert_eq!(
+            parse_program(input).unwrap_err(),
+            Error::UnterminatedBlock {
+                src: input.to_string(),
+                at: (9, 1).into(),
+                end: (input.len(), 0).into()
+            }
+        );
+    }
+
+    #[test]
+    fn if_statements() {
+        assert_program("if (a) print 1;", &["(if a (print 1))"]);
+        assert_program("if (a) print 1; else { print 2; }", &[
+            "(if a (print 1) (block (print 2)))"
+        ]);
+        // Dangling else binds to the nearest if.
+        assert_program("if (a) if (b) 1; else 2;", &["(if a (if b (; 1) (; 2)))"]);
+        let input = "if a) 1;";
+        assert_eq!(parse_program(input).unwrap_err(), Error::UnexpectedToken {
+            src: input.to_string(),
+            at: (3, 1).into(),
+            expected: "'(' after 'if'".to_string(),
+            found: "'a'".to_string()
+        });
+    }
+
+    #[test]
+    fn while_statements() {
+        assert_program("while (a < 10) a = a + 1;", &[
+            "(while (< a 10) (; (= a (+ a 1))))"
+        ]);
+        let input = "while (true print 1;";
+        assert_eq!(parse_program(input).unwrap_err(), Error::UnexpectedToken {
+            src: input.to_string(),
+            at: (12, 5).into(),
+            expected: "')' after condition".to_string(),
+            found: "'print'".to_string()
+        });
+    }
+
+    #[test]
+    fn for_statements() {
+        assert_program("for (var i = 0; i < 3; i = i + 1) print i;", &[
+            "(block (var i 0) (while (< i 3) (block (print i) (; (= i (+ i 1))))))",
+        ]);
+        assert_program("for (i = 0; i < 3;) print i;", &["(block (; (= i 0)) \
+                                                          (while (< i 3) (print \
+                                                          i)))"]);
+        assert_program("for (;;) {}", &["(while true (block))"]);
+
+        let statements = parse_program("for (;;) { 1; }").unwrap();
+        let Stmt::While(stmt) = &statements[0] else {
+            panic!("expected while loop");
+        };
+        assert_eq!(stmt.span, TokenSpan::new(0, 3));
+        assert_eq!(stmt.condition.span(), TokenSpan::new(6, 7));
+
+        let statements = parse_program("for (var i = 0;;) { 1; }").unwrap();
+        let Stmt::Block(block) = &statements[0] else {
+            panic!("expected block");
+        };
+        assert_eq!(block.span, TokenSpan::new(0, 24));
+
+        let input = "for (var i = 0; i < 3; i = i + 1 print i;";
+        assert_eq!(parse_program(input).unwrap_err(), Error::UnexpectedToken {
+            src: input.to_string(),
+            at: (33, 5).into(),
+            expected: "')' after for clauses".to_string(),
+            found: "'print'".to_string()
+        });
+    }
+
+    #[test]
+    fn logical_operators() {
+        assert_ast("a or b", "(or a b)");
+        assert_ast("a and b or c and d", "(or (and a b) (and c d))");
+        assert_ast("a or b or c", "(or (or a b) c)");
+        assert_ast("a == b and !c", "(and (== a b) (! c))");
+        assert_ast("x = a or b", "(= x (or a b))");
+
+        let Expr::Logical(logical) = parse("a and b").unwrap() else {
+            panic!("expected logical expression");
+        };
+        assert_eq!(logical.operator, LogicalOperator::And);
+        assert_eq!(logical.span, TokenSpan::new(2, 5));
+    }
+
+    #[test]
+    fn calls() {
+        assert_ast("f()", "(call f)");
+        assert_ast("f(1, a + b)(2)", "(call (call f 1 (+ a b)) 2)");
+        assert_ast("-f(1)", "(- (call f 1))");
+        assert_eq!(parse("f(1, 2)").unwrap().span(), TokenSpan::new(0, 7));
+
+        let many = vec!["a"; 256].join(", ");
+        let input = &format!("f({many})");
+        assert_eq!(parse(input).unwrap_err(), Error::TooManyArguments {
+            src: input.to_string(),
+            at: (767, 1).into(),
+            what: "arguments",
+            max: 255
+        });
+        let input = "f(1, 2";
+        assert_eq!(parse(input).unwrap_err(), Error::UnexpectedToken {
+            src: input.to_string(),
+            at: (6, 0).into(),
+            expected: "')' after arguments".to_string(),
+            found: "end of input".to_string()
+        });
+    }
+
+    #[test]
+    fn sequences() {
+        assert_ast("1, 2", "(, 1 2)");
+        assert_ast("a = 1, b = 2, c", "(, (, (= a 1) (= b 2)) c)");
+        assert_ast("a = (1, 2)", "(= a (group (, 1 2)))");
+        assert_ast("f(1, 2)", "(call f 1 2)");
+        assert_ast("f((1, 2), 3)", "(call f (group (, 1 2)) 3)");
+        assert_eq!(parse("a, b").unwrap().span(), TokenSpan::new(0, 4));
+        assert_program("for (a, b; c; d, e) x;", &[
+            "(block (; (, a b)) (while c (block (; x) (; (, d e)))))"
+        ]);
+    }
+
+    #[test]
+    fn functions() {
+        assert_program("fun f() {} fun add(a, b) { return a + b; }", &[
+            "(fun f ())",
+            "(fun add (a b) (return (+ a b)))",
+        ]);
+        assert_program("fun f() { return; }", &["(fun f () (return))"]);
+
+        let many = vec!["a"; 256].join(", ");
+        let input = &format!("fun f({many}) {{}}");
+        assert_eq!(parse_program(input).unwrap_err(), Error::TooManyArguments {
+            src: input.to_string(),
+            at: (771, 1).into(),
+            what: "parameters",
+            max: 255
+        });
+        let input = "fun f(a b) {}";
+        assert_eq!(parse_program(input).unwrap_err(), Error::UnexpectedToken {
+            src: input.to_string(),
+            at: (8, 1).into(),
+            expected: "')' after parameters".to_string(),
+            found: "'b'".to_string()
+        });
+        let input = "fun (a) {}";
+        assert_eq!(parse_program(input).unwrap_err(), Error::UnexpectedToken {
+            src: input.to_string(),
+            at: (4, 1).into(),
+            expected: "function name".to_string(),
+            found: "'('".to_string()
+        });
+    }
+
+    #[test]
+    fn classes() {
+        assert_program(
+            "class A {} class B < A { init(x) { this.x = x; } get() { return this.x; } }",
+            &[
+                "(class A)",
+                "(class B < A (fun init (x) (; (.= this x x))) (fun get () (return (. this x))))",
+            ],
+        );
+        assert_program("class C < B { m() { return super.m(); } }", &[
+            "(class C < B (fun m () (return (call (super m)))))",
+        ]);
+        let input = "class A { m() {}";
+        assert_eq!(
+            parse_program(input).unwrap_err(),
+            Error::UnterminatedBlock {
+                src: input.to_string(),
+                at: (8, 1).into(),
+                end: (16, 0).into(),
+            }
+        );
+        let input = "class A < {}";
+        assert_eq!(parse_program(input).unwrap_err(), Error::UnexpectedToken {
+            src: input.to_string(),
+            at: (10, 1).into(),
+            expected: "superclass name".to_string(),
+            found: "'{'".to_string()
+        });
+    }
+
+    #[test]
+    fn properties() {
+        assert_ast("a.b.c", "(. (. a b) c)");
+        assert_ast("a.b(1).c", "(. (call (. a b) 1) c)");
+        assert_ast("a.b.c = 1", "(.= (. a b) c 1)");
+        assert_ast("a.b = c.d = 2", "(.= a b (.= c d 2))");
+        assert_eq!(parse("a.b = 1").unwrap().span(), TokenSpan::new(0, 7));
+        let input = "a.b() = 1";
+        assert_eq!(parse(input).unwrap_err(), Error::InvalidAssignmentTarget {
+            src: input.to_string(),
+            at: (0, 5).into()
+        });
+        let input = "super";
+        assert_eq!(parse(input).unwrap_err(), Error::UnexpectedToken {
+            src: input.to_string(),
+            at: (5, 0).into(),
+            expected: "'.' after 'super'".to_string(),
+            found: "end of input".to_string()
+        });
+    }
+
+    #[test]
+    fn null_safe_properties() {
+        let parse = |input| extended(input).parse_expression();
+        let print = |input| parse(input).unwrap().accept(&mut AstPrinter);
+        assert_eq!(print("a?.b.c"), "(. (?. a b) c)");
+        assert_eq!(print("a?.b(1)?.c"), "(?. (call (?. a b) 1) c)");
+        // Parentheses end the chain.
+        assert_eq!(print("(a?.b).c = 1"), "(.= (group (?. a b)) c 1)");
+
+        // Nothing to assign to if the chain is short-circuited.
+        for (input, at) in [
+            ("a?.b = 1", (0, 4)),
+            ("a?.b.c = 1", (0, 6)),
+            ("a?.b().c++", (0, 8)),
+        ] {
+            assert_eq!(parse(input).unwrap_err(), &[
+                Error::InvalidAssignmentTarget {
+                    src: input.to_string(),
+                    at: at.into(),
+                }
+            ]);
+        }
+    }
+
+    #[test]
+    fn updates() {
+        let parse = |input| extended(input).parse_expression();
+        let print = |input| parse(input).unwrap().accept(&mut AstPrinter);
+        assert_eq!(print("++a"), "(pre++ a)");
+        assert_eq!(print("a--"), "(post-- a)");
+        assert_eq!(print("-a++"), "(- (post++ a))");
+        assert_eq!(
+            print("--a.b + c.d++"),
+            "(+ (pre-- (. a b)) (post++ (. c d)))"
+        );
+        assert_eq!(print("a++ - --b"), "(- (post++ a) (pre-- b))");
+        assert_eq!(parse("++a.b").unwrap().span(), TokenSpan::new(0, 5));
+        assert_eq!(parse("a.b--").unwrap().span(), TokenSpan::new(0, 5));
+
+        // Only variables and fields can be updated.
+        for (input, at) in [("1++", (0, 1)), ("--a()", (2, 3)), ("a++--", (0, 3))] {
+            assert_eq!(parse(input).unwrap_err(), &[
+                Error::InvalidAssignmentTarget {
+                    src: input.to_string(),
+                    at: at.into(),
+                }
+            ]);
+        }
+
+        // Classic Lox has no such operators.
+        assert_ast("- -a", "(- (- a))");
+        assert!(parse_program("a++;").is_err());
+    }
 }
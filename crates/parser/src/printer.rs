@@ -0,0 +1,320 @@
+use crate::grammar::{
+    Assign,
+    Binary,
+    Block,
+    Call,
+    Class,
+    ExprVisitor,
+    Expression,
+    Function,
+    Get,
+    Grouping,
+    If,
+    Literal,
+    LiteralValue,
+    Logical,
+    Print,
+    Return,
+    Sequence,
+    Set,
+    Stmt,
+    StmtVisitor,
+    Super,
+    This,
+    Unary,
+    Update,
+    Var,
+    Variable,
+    Visitable,
+    Visitor,
+    While,
+};
+
+/// Prints the AST as s-expressions, e.g. `(print (+ 1 (* 2 3)))`.
+///
+/// Useful for debugging, and to dump the AST of a program.
+#[derive(Debug, Default)]
+pub struct AstPrinter;
+
+impl AstPrinter {
+    /// Prints every statement of the program on its own line.
+    pub fn print_program(&mut self, statements: &[Stmt<'_>]) -> String {
+        let lines: Vec<_> = statements.iter().map(|stmt| stmt.accept(self)).collect();
+        lines.join("\n")
+    }
+}
+
+// Implementing these marker traits enforces implementation of the Visitor for
+// all node types.
+impl ExprVisitor<'_, String> for AstPrinter {}
+
+impl StmtVisitor<'_, String> for AstPrinter {}
+
+impl Visitor<Assign<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Assign<'_>) -> String {
+        format!("(= {} {})", data.name.lexeme, data.value.accept(self))
+    }
+}
+
+impl Visitor<Binary<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Binary<'_>) -> String {
+        format!(
+            "({} {} {})",
+            data.operator,
+            data.left.accept(self),
+            data.right.accept(self)
+        )
+    }
+}
+
+impl Visitor<Call<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Call<'_>) -> String {
+        let mut out = format!("(call {}", data.callee.accept(self));
+        for argument in data.arguments {
+            out.push(' ');
+            out.push_str(&argument.accept(self));
+        }
+        out.push(')');
+        out
+    }
+}
+
+impl Visitor<Get<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Get<'_>) -> String {
+        let operator = if data.optional { "?." } else { "." };
+        format!(
+            "({operator} {} {})",
+            data.object.accept(self),
+            data.name.lexeme
+        )
+    }
+}
+
+impl Visitor<Grouping<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Grouping<'_>) -> String {
+        format!("(group {})", data.expression.accept(self))
+    }
+}
+
+impl Visitor<Literal<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Literal<'_>) -> String {
+        match data.value {
+            LiteralValue::Number(n) => n.to_string(),
+            LiteralValue::String(s) => s.to_string(),
+            LiteralValue::Boolean(b) => b.to_string(),
+            LiteralValue::Nil => "nil".to_string(),
+        }
+    }
+}
+
+impl Visitor<Logical<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Logical<'_>) -> String {
+        format!(
+            "({} {} {})",
+            data.operator,
+            data.left.accept(self),
+            data.right.accept(self)
+        )
+    }
+}
+
+impl Visitor<Sequence<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Sequence<'_>) -> String {
+        format!("(, {} {})", data.left.accept(self), data.right.accept(self))
+    }
+}
+
+impl Visitor<Set<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Set<'_>) -> String {
+        format!(
+            "(.= {} {} {})",
+            data.object.accept(self),
+            data.name.lexeme,
+            data.value.accept(self)
+        )
+    }
+}
+
+impl Visitor<Super<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Super<'_>) -> String {
+        format!("(super {})", data.method.lexeme)
+    }
+}
+
+impl Visitor<This, String> for AstPrinter {
+    fn visit(&mut self, _data: &This) -> String {
+        "this".to_string()
+    }
+}
+
+impl Visitor<Unary<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Unary<'_>) -> String {
+        format!("({} {})", data.operator, data.right.accept(self))
+    }
+}
+
+impl Visitor<Update<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Update<'_>) -> String {
+        let fix = if data.prefix { "pre" } else { "post" };
+        format!("({fix}{} {})", data.operator, data.target.accept(self))
+    }
+}
+
+impl Visitor<Variable<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Variable<'_>) -> String {
+        data.name.lexeme.to_string()
+    }
+}
+
+impl Visitor<Block<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Block<'_>) -> String {
+        let mut out = "(block".to_string();
+        for stmt in data.statements {
+            out.push(' ');
+            out.push_str(&stmt.accept(self));
+        }
+        out.push(')');
+        out
+    }
+}
+
+impl Visitor<Class<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Class<'_>) -> String {
+        let mut out = format!("(class {}", data.name.lexeme);
+        if let Some(superclass) = &data.superclass {
+            out.push_str(&format!(" < {}", superclass.name.lexeme));
+        }
+        for method in data.methods {
+            out.push(' ');
+            out.push_str(&method.accept(self));
+        }
+        out.push(')');
+        out
+    }
+}
+
+impl Visitor<Expression<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Expression<'_>) -> String {
+        format!("(; {})", data.expression.accept(self))
+    }
+}
+
+impl Visitor<Function<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Function<'_>) -> String {
+        let params: Vec<_> = data.params.iter().map(|param| param.lexeme).collect();
+        let mut out = format!("(fun {} ({})", data.name.lexeme, params.join(" "));
+        for stmt in data.body {
+            out.push(' ');
+            out.push_str(&stmt.accept(self));
+        }
+        out.push(')');
+        out
+    }
+}
+
+impl Visitor<If<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &If<'_>) -> String {
+        match data.else_branch {
+            Some(else_branch) => format!(
+                "(if {} {} {})",
+                data.condition.accept(self),
+                data.then_branch.accept(self),
+                else_branch.accept(self)
+            ),
+            None => format!(
+                "(if {} {})",
+                data.condition.accept(self),
+                data.then_branch.accept(self)
+            ),
+        }
+    }
+}
+
+impl Visitor<Print<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Print<'_>) -> String {
+        format!("(print {})", data.expression.accept(self))
+    }
+}
+
+impl Visitor<Return<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Return<'_>) -> String {
+        match &data.value {
+            Some(value) => format!("(return {})", value.accept(self)),
+            None => "(return)".to_string(),
+        }
+    }
+}
+
+impl Visitor<Var<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &Var<'_>) -> String {
+        match &data.initializer {
+            Some(initializer) => {
+                format!("(var {} {})", data.name.lexeme, initializer.accept(self))
+            }
+            None => format!("(var {})", data.name.lexeme),
+        }
+    }
+}
+
+impl Visitor<While<'_>, String> for AstPrinter {
+    fn visit(&mut self, data: &While<'_>) -> String {
+        format!(
+            "(while {} {})",
+            data.condition.accept(self),
+            data.body.accept(self)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            grammar::{BinaryOperator, Expr, UnaryOperator},
+            Arena,
+            Parser,
+        },
+        lexer::{token::TokenSpan, Lexer},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_printer() {
+        let arena = Arena::new();
+        let span = |start, end| TokenSpan::new(start, end);
+        let expr = Expr::Binary(Binary {
+            left: arena.alloc(Expr::Unary(Unary {
+                operator: UnaryOperator::Minus,
+                right: arena.alloc(Expr::Literal(Literal {
+                    value: LiteralValue::Number(123.0),
+                    span: span(1, 4),
+                })),
+                span: span(0, 1),
+            })),
+            operator: BinaryOperator::Star,
+            right: arena.alloc(Expr::Grouping(Grouping {
+                expression: arena.alloc(Expr::Literal(Literal {
+                    value: LiteralValue::Number(45.67),
+                    span: span(8, 13),
+                })),
+                span: span(7, 14),
+            })),
+            span: span(5, 6),
+        });
+        let mut printer = AstPrinter;
+        let result = expr.accept(&mut printer);
+        assert_eq!(result, "(* (- 123) (group 45.67))");
+    }
+
+    #[test]
+    fn print_program() {
+        let input = "class A < B { m() {} } fun f(a, b) { return a; } { var x; } if (x) print 1;";
+        let arena = Arena::new();
+        let statements = Parser::new(Lexer::new(input), &arena).parse().unwrap();
+        assert_eq!(
+            AstPrinter.print_program(&statements),
+            "(class A < B (fun m ()))\n(fun f (a b) (return a))\n(block (var x))\n(if x (print 1))"
+        );
+    }
+}
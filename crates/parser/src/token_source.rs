@@ -0,0 +1,165 @@
+use lexer::{token::Token, Lexer, PeekableLexer};
+
+/// Fallible stream of tokens the parser consumes.
+///
+/// Besides the tokens, the source must give access to the code they were
+/// produced from, which is attached to diagnostics. Lexer errors are passed
+/// through as-is: the parser records them and moves on to the next token.
+pub trait TokenSource<'a>: Iterator<Item = lexer::Result<Token<'a>>> {
+    /// Returns the source code the tokens refer to.
+    fn source(&self) -> &'a str;
+}
+
+impl<'a> TokenSource<'a> for Lexer<'a> {
+    fn source(&self) -> &'a str {
+        Lexer::source(self)
+    }
+}
+
+impl<'a> TokenSource<'a> for PeekableLexer<'a> {
+    fn source(&self) -> &'a str {
+        PeekableLexer::source(self)
+    }
+}
+
+/// Token source over an arbitrary iterator of tokens, e.g. a pre-collected
+/// `Vec` or a synthetic stream.
+pub struct Tokens<'a, I> {
+    /// Source code the tokens refer to.
+    src: &'a str,
+
+    /// Remaining tokens.
+    tokens: I,
+}
+
+impl<'a, I> Tokens<'a, I>
+where
+    I: Iterator<Item = lexer::Result<Token<'a>>>,
+{
+    /// Creates a token source yielding the given tokens, which refer to `src`.
+    pub fn new(src: &'a str, tokens: impl IntoIterator<IntoIter = I>) -> Self {
+        Self {
+            src,
+            tokens: tokens.into_iter(),
+        }
+    }
+}
+
+impl<'a, I> Iterator for Tokens<'a, I>
+where
+    I: Iterator<Item = lexer::Result<Token<'a>>>,
+{
+    type Item = lexer::Result<Token<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.tokens.next()
+    }
+}
+
+impl<'a, I> TokenSource<'a> for Tokens<'a, I>
+where
+    I: Iterator<Item = lexer::Result<Token<'a>>>,
+{
+    fn source(&self) -> &'a str {
+        self.src
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{grammar::Visitable, printer::AstPrinter, Arena, Error, Parser},
+        lexer::{
+            token::{TokenKind, TokenSpan},
+            LineCol,
+        },
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn collected_tokens() {
+        let src = "1 + 2 * 3";
+        let tokens: Vec<_> = Lexer::new(src).collect();
+        let arena = Arena::new();
+        let expr = Parser::new(Tokens::new(src, tokens), &arena)
+            .parse_expression()
+            .unwrap();
+        assert_eq!(expr.accept(&mut AstPrinter), "(+ 1 (* 2 3))");
+    }
+
+    #[test]
+    fn synthetic_tokens() {
+        let src = "a ? or";
+        let token = |kind, lexeme, start, end| Token {
+            kind,
+            lexeme,
+            span: TokenSpan::new(start, end),
+            line_col: LineCol {
+                line: 1,
+                column: start + 1,
+            },
+            literal: None,
+        };
+        let error = || lexer::Error::UnexpectedChar {
+            src: src.to_string(),
+            at: (2, 1).into(),
+            c: '?',
+        };
+        let tokens = [
+            Ok(token(TokenKind::Identifier, "a", 0, 1)),
+            Err(error()),
+            Ok(token(TokenKind::Or, "or", 4, 6)),
+        ];
+        let arena = Arena::new();
+        let errors = Parser::new(Tokens::new(src, tokens), &arena)
+            .parse_expression()
+            .unwrap_err();
+        assert_eq!(errors, &[
+            Error::LexerError(error()),
+            Error::ExpectedExpression {
+                src: src.to_string(),
+                at: (6, 0).into(),
+                found: "end of input".to_string()
+            },
+        ]);
+    }
+
+    #[test]
+    fn invalid_literals() {
+        let src = "x \"";
+        let token = |kind, lexeme, start, end| Token {
+            kind,
+            lexeme,
+            span: TokenSpan::new(start, end),
+            line_col: LineCol {
+                line: 1,
+                column: start + 1,
+            },
+            literal: None,
+        };
+        let arena = Arena::new();
+        let parse = |token| {
+            Parser::new(Tokens::new(src, [Ok(token)]), &arena)
+                .parse_expression()
+                .unwrap_err()
+        };
+        assert_eq!(parse(token(TokenKind::Number, "x", 0, 1)), &[
+            Error::InvalidLiteral {
+                src: src.to_string(),
+                at: (0, 1).into(),
+                kind: "number",
+                lexeme: "x".to_string(),
+            }
+        ]);
+        // Too short to have both quotes.
+        assert_eq!(parse(token(TokenKind::String, "\"", 2, 3)), &[
+            Error::InvalidLiteral {
+                src: src.to_string(),
+                at: (2, 1).into(),
+                kind: "string",
+                lexeme: "\"".to_string(),
+            }
+        ]);
+    }
+}
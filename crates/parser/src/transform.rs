@@ -0,0 +1,268 @@
+use crate::{
+    grammar::{
+        Assign,
+        Binary,
+        Block,
+        Call,
+        Class,
+        Expr,
+        Expression,
+        Function,
+        Get,
+        Grouping,
+        If,
+        Literal,
+        Logical,
+        Print,
+        Return,
+        Sequence,
+        Set,
+        Stmt,
+        Super,
+        This,
+        Unary,
+        Update,
+        Var,
+        Variable,
+        While,
+    },
+    Arena,
+};
+
+/// AST rewriting pass, e.g. for desugaring or optimizations such as constant
+/// folding.
+///
+/// The AST is immutable, so transforming a node produces a new one, allocated
+/// in the arena returned by `arena()`. By default a node is rebuilt from its
+/// transformed children; a pass overrides `transform_expr` or `transform_stmt`,
+/// and calls the matching `transform_*` function to transform the children
+/// first when needed.
+pub trait Transformer<'a>: Sized {
+    /// Arena the new nodes are allocated in.
+    fn arena(&self) -> &'a Arena;
+
+    fn transform_stmt(&mut self, stmt: &Stmt<'a>) -> Stmt<'a> {
+        transform_stmt(self, stmt)
+    }
+
+    fn transform_function(&mut self, function: &Function<'a>) -> Function<'a> {
+        transform_function(self, function)
+    }
+
+    fn transform_expr(&mut self, expr: &Expr<'a>) -> Expr<'a> {
+        transform_expr(self, expr)
+    }
+}
+
+/// Transforms all statements of a program.
+pub fn transform_program<'a, T: Transformer<'a>>(
+    transformer: &mut T,
+    statements: &[Stmt<'a>],
+) -> Vec<Stmt<'a>> {
+    statements
+        .iter()
+        .map(|stmt| transformer.transform_stmt(stmt))
+        .collect()
+}
+
+/// Rebuilds the statement from its transformed children.
+pub fn transform_stmt<'a, T: Transformer<'a>>(transformer: &mut T, stmt: &Stmt<'a>) -> Stmt<'a> {
+    let arena = transformer.arena();
+    match stmt {
+        Stmt::Block(block) => Stmt::Block(Block {
+            statements: transform_slice(arena, block.statements, |s| transformer.transform_stmt(s)),
+            span: block.span,
+        }),
+        Stmt::Class(class) => Stmt::Class(Class {
+            name: class.name,
+            superclass: class.superclass.as_ref().map(|superclass| Variable {
+                name: superclass.name,
+            }),
+            methods: transform_slice(arena, class.methods, |m| transformer.transform_function(m)),
+        }),
+        Stmt::Expression(expression) => Stmt::Expression(Expression {
+            expression: transformer.transform_expr(&expression.expression),
+        }),
+        Stmt::Function(function) => Stmt::Function(transformer.transform_function(function)),
+        Stmt::If(if_) => Stmt::If(If {
+            condition: transformer.transform_expr(&if_.condition),
+            then_branch: arena.alloc(transformer.transform_stmt(if_.then_branch)),
+            else_branch: if_
+                .else_branch
+                .map(|else_branch| arena.alloc(transformer.transform_stmt(else_branch))),
+            span: if_.span,
+        }),
+        Stmt::Print(print) => Stmt::Print(Print {
+            expression: transformer.transform_expr(&print.expression),
+            span: print.span,
+        }),
+        Stmt::Return(return_) => Stmt::Return(Return {
+            value: return_
+                .value
+                .as_ref()
+                .map(|value| transformer.transform_expr(value)),
+            span: return_.span,
+        }),
+        Stmt::Var(var) => Stmt::Var(Var {
+            name: var.name,
+            initializer: var
+                .initializer
+                .as_ref()
+                .map(|initializer| transformer.transform_expr(initializer)),
+        }),
+        Stmt::While(while_) => Stmt::While(While {
+            condition: transformer.transform_expr(&while_.condition),
+            body: arena.alloc(transformer.transform_stmt(while_.body)),
+            span: while_.span,
+        }),
+    }
+}
+
+/// Rebuilds the function from its transformed body.
+pub fn transform_function<'a, T: Transformer<'a>>(
+    transformer: &mut T,
+    function: &Function<'a>,
+) -> Function<'a> {
+    Function {
+        name: function.name,
+        params: function.params,
+        body: transform_slice(transformer.arena(), function.body, |s| {
+            transformer.transform_stmt(s)
+        }),
+    }
+}
+
+/// Rebuilds the expression from its transformed children.
+pub fn transform_expr<'a, T: Transformer<'a>>(transformer: &mut T, expr: &Expr<'a>) -> Expr<'a> {
+    let arena = transformer.arena();
+    let mut transform = |expr: &Expr<'a>| arena.alloc(transformer.transform_expr(expr));
+    match expr {
+        Expr::Assign(assign) => Expr::Assign(Assign {
+            name: assign.name,
+            value: transform(assign.value),
+        }),
+        Expr::Binary(binary) => Expr::Binary(Binary {
+            left: transform(binary.left),
+            operator: binary.operator,
+            right: transform(binary.right),
+            span: binary.span,
+        }),
+        Expr::Call(call) => Expr::Call(Call {
+            callee: transform(call.callee),
+            arguments: transform_slice(arena, call.arguments, |a| transformer.transform_expr(a)),
+            span: call.span,
+        }),
+        Expr::Get(get) => Expr::Get(Get {
+            object: transform(get.object),
+            name: get.name,
+            optional: get.optional,
+        }),
+        Expr::Grouping(grouping) => Expr::Grouping(Grouping {
+            expression: transform(grouping.expression),
+            span: grouping.span,
+        }),
+        Expr::Literal(literal) => Expr::Literal(Literal {
+            value: literal.value,
+            span: literal.span,
+        }),
+        Expr::Logical(logical) => Expr::Logical(Logical {
+            left: transform(logical.left),
+            operator: logical.operator,
+            right: transform(logical.right),
+            span: logical.span,
+        }),
+        Expr::Sequence(sequence) => Expr::Sequence(Sequence {
+            left: transform(sequence.left),
+            right: transform(sequence.right),
+            span: sequence.span,
+        }),
+        Expr::Set(set) => Expr::Set(Set {
+            object: transform(set.object),
+            name: set.name,
+            value: transform(set.value),
+        }),
+        Expr::Super(super_) => Expr::Super(Super {
+            method: super_.method,
+            span: super_.span,
+        }),
+        Expr::This(this) => Expr::This(This { span: this.span }),
+        Expr::Unary(unary) => Expr::Unary(Unary {
+            operator: unary.operator,
+            right: transform(unary.right),
+            span: unary.span,
+        }),
+        Expr::Update(update) => Expr::Update(Update {
+            operator: update.operator,
+            target: transform(update.target),
+            prefix: update.prefix,
+            span: update.span,
+        }),
+        Expr::Variable(variable) => Expr::Variable(Variable {
+            name: variable.name,
+        }),
+    }
+}
+
+/// Transforms every node of the slice into a new slice in the arena.
+fn transform_slice<'a, N, F>(arena: &'a Arena, nodes: &[N], transform: F) -> &'a [N]
+where
+    F: FnMut(&N) -> N,
+{
+    arena.alloc_slice(nodes.iter().map(transform).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{constant, grammar::Visitable, printer::AstPrinter, Parser},
+        lexer::{token::TokenSpan, Lexer},
+        pretty_assertions::assert_eq,
+    };
+
+    /// Folds expressions made of literals, other than strings.
+    struct ConstantFolder<'a> {
+        arena: &'a Arena,
+    }
+
+    impl<'a> Transformer<'a> for ConstantFolder<'a> {
+        fn arena(&self) -> &'a Arena {
+            self.arena
+        }
+
+        fn transform_expr(&mut self, expr: &Expr<'a>) -> Expr<'a> {
+            let expr = transform_expr(self, expr);
+            match constant::evaluate(&expr) {
+                Some(value) if !matches!(expr, Expr::Literal(_)) => Expr::Literal(Literal {
+                    value,
+                    span: expr.span(),
+                }),
+                _ => expr,
+            }
+        }
+    }
+
+    #[test]
+    fn constant_folding() {
+        let input = "
+            print 1 + 2 * 3;
+            fun f(a) { return (10 - 4) / 2 + a * (1 + 1); }
+            while (x < 2 * 8) x = \"a\" + 1;
+        ";
+        let arena = Arena::new();
+        let statements = Parser::new(Lexer::new(input), &arena).parse().unwrap();
+        let folded = transform_program(&mut ConstantFolder { arena: &arena }, &statements);
+        let printed: Vec<_> = folded.iter().map(|s| s.accept(&mut AstPrinter)).collect();
+        assert_eq!(printed, &[
+            "(print 7)",
+            "(fun f (a) (return (+ 3 (* a 2))))",
+            "(while (< x 16) (; (= x (+ a 1))))",
+        ]);
+
+        // Folded nodes span the whole original expression.
+        let Stmt::Print(print) = &folded[0] else {
+            panic!("expected print statement");
+        };
+        assert_eq!(print.expression.span(), TokenSpan::new(19, 28));
+    }
+}
@@ -0,0 +1,254 @@
+use crate::grammar::{
+    Assign,
+    Binary,
+    Block,
+    Call,
+    Class,
+    Expr,
+    Expression,
+    Function,
+    Get,
+    Grouping,
+    If,
+    Literal,
+    Logical,
+    Print,
+    Return,
+    Sequence,
+    Set,
+    Stmt,
+    Super,
+    This,
+    Unary,
+    Update,
+    Var,
+    Variable,
+    While,
+};
+
+/// Read-only AST traversal with default implementations.
+///
+/// Unlike `ExprVisitor` and `StmtVisitor`, which require an impl for every
+/// node type, every hook here defaults to walking the children of the node. A
+/// pass overrides only the hooks it is interested in, and calls the matching
+/// `walk_*` function if it still needs to descend into children.
+pub trait Walk<'a>: Sized {
+    fn walk_stmt(&mut self, stmt: &Stmt<'a>) {
+        walk_stmt(self, stmt)
+    }
+
+    fn walk_block(&mut self, block: &Block<'a>) {
+        walk_block(self, block)
+    }
+
+    fn walk_class(&mut self, class: &Class<'a>) {
+        walk_class(self, class)
+    }
+
+    fn walk_expression(&mut self, expression: &Expression<'a>) {
+        self.walk_expr(&expression.expression)
+    }
+
+    fn walk_function(&mut self, function: &Function<'a>) {
+        walk_function(self, function)
+    }
+
+    fn walk_if(&mut self, if_: &If<'a>) {
+        walk_if(self, if_)
+    }
+
+    fn walk_print(&mut self, print: &Print<'a>) {
+        self.walk_expr(&print.expression)
+    }
+
+    fn walk_return(&mut self, return_: &Return<'a>) {
+        if let Some(value) = &return_.value {
+            self.walk_expr(value);
+        }
+    }
+
+    fn walk_var(&mut self, var: &Var<'a>) {
+        if let Some(initializer) = &var.initializer {
+            self.walk_expr(initializer);
+        }
+    }
+
+    fn walk_while(&mut self, while_: &While<'a>) {
+        self.walk_expr(&while_.condition);
+        self.walk_stmt(while_.body);
+    }
+
+    fn walk_expr(&mut self, expr: &Expr<'a>) {
+        walk_expr(self, expr)
+    }
+
+    fn walk_assign(&mut self, assign: &Assign<'a>) {
+        self.walk_expr(assign.value)
+    }
+
+    fn walk_binary(&mut self, binary: &Binary<'a>) {
+        self.walk_expr(binary.left);
+        self.walk_expr(binary.right);
+    }
+
+    fn walk_call(&mut self, call: &Call<'a>) {
+        walk_call(self, call)
+    }
+
+    fn walk_get(&mut self, get: &Get<'a>) {
+        self.walk_expr(get.object)
+    }
+
+    fn walk_grouping(&mut self, grouping: &Grouping<'a>) {
+        self.walk_expr(grouping.expression)
+    }
+
+    fn walk_literal(&mut self, _literal: &Literal<'a>) {}
+
+    fn walk_logical(&mut self, logical: &Logical<'a>) {
+        self.walk_expr(logical.left);
+        self.walk_expr(logical.right);
+    }
+
+    fn walk_sequence(&mut self, sequence: &Sequence<'a>) {
+        self.walk_expr(sequence.left);
+        self.walk_expr(sequence.right);
+    }
+
+    fn walk_set(&mut self, set: &Set<'a>) {
+        self.walk_expr(set.object);
+        self.walk_expr(set.value);
+    }
+
+    fn walk_super(&mut self, _super: &Super<'a>) {}
+
+    fn walk_this(&mut self, _this: &This) {}
+
+    fn walk_unary(&mut self, unary: &Unary<'a>) {
+        self.walk_expr(unary.right)
+    }
+
+    fn walk_update(&mut self, update: &Update<'a>) {
+        self.walk_expr(update.target)
+    }
+
+    fn walk_variable(&mut self, _variable: &Variable<'a>) {}
+}
+
+/// Dispatches to the hook of the statement's node type.
+pub fn walk_stmt<'a, W: Walk<'a>>(walker: &mut W, stmt: &Stmt<'a>) {
+    match stmt {
+        Stmt::Block(block) => walker.walk_block(block),
+        Stmt::Class(class) => walker.walk_class(class),
+        Stmt::Expression(expression) => walker.walk_expression(expression),
+        Stmt::Function(function) => walker.walk_function(function),
+        Stmt::If(if_) => walker.walk_if(if_),
+        Stmt::Print(print) => walker.walk_print(print),
+        Stmt::Return(return_) => walker.walk_return(return_),
+        Stmt::Var(var) => walker.walk_var(var),
+        Stmt::While(while_) => walker.walk_while(while_),
+    }
+}
+
+pub fn walk_block<'a, W: Walk<'a>>(walker: &mut W, block: &Block<'a>) {
+    for stmt in block.statements {
+        walker.walk_stmt(stmt);
+    }
+}
+
+pub fn walk_class<'a, W: Walk<'a>>(walker: &mut W, class: &Class<'a>) {
+    if let Some(superclass) = &class.superclass {
+        walker.walk_variable(superclass);
+    }
+    for method in class.methods {
+        walker.walk_function(method);
+    }
+}
+
+pub fn walk_function<'a, W: Walk<'a>>(walker: &mut W, function: &Function<'a>) {
+    for stmt in function.body {
+        walker.walk_stmt(stmt);
+    }
+}
+
+pub fn walk_if<'a, W: Walk<'a>>(walker: &mut W, if_: &If<'a>) {
+    walker.walk_expr(&if_.condition);
+    walker.walk_stmt(if_.then_branch);
+    if let Some(else_branch) = if_.else_branch {
+        walker.walk_stmt(else_branch);
+    }
+}
+
+/// Dispatches to the hook of the expression's node type.
+pub fn walk_expr<'a, W: Walk<'a>>(walker: &mut W, expr: &Expr<'a>) {
+    match expr {
+        Expr::Assign(assign) => walker.walk_assign(assign),
+        Expr::Binary(binary) => walker.walk_binary(binary),
+        Expr::Call(call) => walker.walk_call(call),
+        Expr::Get(get) => walker.walk_get(get),
+        Expr::Grouping(grouping) => walker.walk_grouping(grouping),
+        Expr::Literal(literal) => walker.walk_literal(literal),
+        Expr::Logical(logical) => walker.walk_logical(logical),
+        Expr::Sequence(sequence) => walker.walk_sequence(sequence),
+        Expr::Set(set) => walker.walk_set(set),
+        Expr::Super(super_) => walker.walk_super(super_),
+        Expr::This(this) => walker.walk_this(this),
+        Expr::Unary(unary) => walker.walk_unary(unary),
+        Expr::Update(update) => walker.walk_update(update),
+        Expr::Variable(variable) => walker.walk_variable(variable),
+    }
+}
+
+pub fn walk_call<'a, W: Walk<'a>>(walker: &mut W, call: &Call<'a>) {
+    walker.walk_expr(call.callee);
+    for argument in call.arguments {
+        walker.walk_expr(argument);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{Arena, Parser},
+        lexer::Lexer,
+        pretty_assertions::assert_eq,
+    };
+
+    #[derive(Default)]
+    struct Counter<'a> {
+        literals: usize,
+        variables: Vec<&'a str>,
+    }
+
+    impl<'a> Walk<'a> for Counter<'a> {
+        fn walk_literal(&mut self, _literal: &Literal<'a>) {
+            self.literals += 1;
+        }
+
+        fn walk_variable(&mut self, variable: &Variable<'a>) {
+            self.variables.push(variable.name.lexeme);
+        }
+
+        // Doesn't descend into functions.
+        fn walk_function(&mut self, _function: &Function<'a>) {}
+    }
+
+    #[test]
+    fn default_traversal() {
+        let input = "
+            var a = 1;
+            class B < A { m() { return 2; } }
+            fun f(x) { print x + 3; }
+            while (a < 10) { if (b) a = a + 1; else print f(c.d, 4, -5); }
+        ";
+        let arena = Arena::new();
+        let statements = Parser::new(Lexer::new(input), &arena).parse().unwrap();
+        let mut counter = Counter::default();
+        for stmt in &statements {
+            counter.walk_stmt(stmt);
+        }
+        assert_eq!(counter.literals, 5);
+        assert_eq!(counter.variables, &["A", "a", "b", "a", "f", "c"]);
+    }
+}
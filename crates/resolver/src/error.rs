@@ -0,0 +1,176 @@
+use {
+    miette::{Diagnostic, SourceSpan},
+    thiserror::Error,
+};
+
+/// Error type for the resolver.
+#[derive(Error, Diagnostic, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Can't read local variable in its own initializer")]
+    #[diagnostic(code(resolver::read_in_own_initializer))]
+    ReadInOwnInitializer {
+        #[source_code]
+        src: String,
+        #[label("'{name}' is not initialized yet")]
+        at: SourceSpan,
+        name: String,
+    },
+
+    #[error("Can't use 'this' outside of a class")]
+    #[diagnostic(code(resolver::this_outside_class))]
+    ThisOutsideClass {
+        #[source_code]
+        src: String,
+        #[label("not within a method")]
+        at: SourceSpan,
+    },
+
+    #[error("Can't use 'super' outside of a class")]
+    #[diagnostic(code(resolver::super_outside_class))]
+    SuperOutsideClass {
+        #[source_code]
+        src: String,
+        #[label("not within a method")]
+        at: SourceSpan,
+    },
+
+    #[error("Can't use 'super' in a class with no superclass")]
+    #[diagnostic(code(resolver::super_without_superclass))]
+    SuperWithoutSuperclass {
+        #[source_code]
+        src: String,
+        #[label("'{class}' has no superclass")]
+        at: SourceSpan,
+        class: String,
+    },
+
+    #[error("Already a variable with this name in this scope")]
+    #[diagnostic(code(resolver::already_declared))]
+    AlreadyDeclared {
+        #[source_code]
+        src: String,
+        #[label("'{name}' redeclared here")]
+        at: SourceSpan,
+        #[label("first declared here")]
+        previous: SourceSpan,
+        name: String,
+    },
+
+    #[error("Expected {expected} arguments but got {found}")]
+    #[diagnostic(code(resolver::arity_mismatch))]
+    ArityMismatch {
+        #[source_code]
+        src: String,
+        #[label("called with {found} arguments")]
+        at: SourceSpan,
+        #[label("'{name}' is declared with {expected} parameters")]
+        declaration: SourceSpan,
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("A class can't inherit from itself")]
+    #[diagnostic(code(resolver::inherit_from_self))]
+    InheritFromSelf {
+        #[source_code]
+        src: String,
+        #[label("'{name}' is the class being declared")]
+        at: SourceSpan,
+        name: String,
+    },
+
+    #[error("Can't return from top-level code")]
+    #[diagnostic(code(resolver::return_outside_function))]
+    ReturnOutsideFunction {
+        #[source_code]
+        src: String,
+        #[label("not within a function")]
+        at: SourceSpan,
+    },
+
+    #[error("Can't return a value from an initializer")]
+    #[diagnostic(code(resolver::return_value_from_initializer))]
+    ReturnValueFromInitializer {
+        #[source_code]
+        src: String,
+        #[label("initializers always return the instance")]
+        at: SourceSpan,
+    },
+}
+
+/// Warning type for the resolver, for programs that can still be run.
+#[derive(Error, Diagnostic, Debug, PartialEq, Eq)]
+pub enum Warning {
+    #[error("Unreachable code")]
+    #[diagnostic(code(resolver::unreachable_code), severity(Warning))]
+    UnreachableCode {
+        #[source_code]
+        src: String,
+        #[label("any code following this return is unreachable")]
+        return_: SourceSpan,
+        #[label("unreachable code")]
+        at: SourceSpan,
+    },
+
+    #[error("Condition is always {value}")]
+    #[diagnostic(code(resolver::dead_branch), severity(Warning))]
+    DeadBranch {
+        #[source_code]
+        src: String,
+        #[label("always {value}")]
+        condition: SourceSpan,
+        #[label("so this is never run")]
+        at: SourceSpan,
+        value: bool,
+        #[help]
+        fix: String,
+    },
+
+    #[error("Variable '{name}' shadows a variable of an enclosing scope")]
+    #[diagnostic(code(resolver::shadowing), severity(Warning))]
+    Shadowing {
+        #[source_code]
+        src: String,
+        #[label("declared here")]
+        at: SourceSpan,
+        #[label("shadowed variable")]
+        shadowed: SourceSpan,
+        name: String,
+    },
+
+    #[error("Unused suppression of '{name}'")]
+    #[diagnostic(code(resolver::unused_suppression), severity(Warning))]
+    UnusedSuppression {
+        #[source_code]
+        src: String,
+        #[label("no such warning in the next statement")]
+        at: SourceSpan,
+        name: String,
+    },
+}
+
+impl Warning {
+    /// Returns the name of the warning, as used to suppress it, e.g. in
+    /// `// lox-ignore(shadowing)`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UnreachableCode { .. } => "unreachable_code",
+            Self::DeadBranch { .. } => "dead_branch",
+            Self::Shadowing { .. } => "shadowing",
+            Self::UnusedSuppression { .. } => "unused_suppression",
+        }
+    }
+
+    /// Returns the span the warning is about.
+    pub fn at(&self) -> SourceSpan {
+        match self {
+            Self::UnreachableCode { at, .. }
+            | Self::DeadBranch { at, .. }
+            | Self::Shadowing { at, .. }
+            | Self::UnusedSuppression { at, .. } => *at,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,12 @@
+pub mod error;
+pub mod resolution;
+pub mod resolver;
+mod suppression;
+pub mod symbols;
+
+pub use {
+    error::{Error, Result, Warning},
+    resolution::Resolution,
+    resolver::{Lints, Resolver},
+    symbols::SymbolTable,
+};
@@ -0,0 +1,242 @@
+use lexer::token::TokenSpan;
+
+/// Index of a scope in a [`Resolution`]. The global scope is `0`.
+pub type ScopeId = usize;
+
+/// Index of a declaration in a [`Resolution`].
+pub type DeclarationId = usize;
+
+/// Lexical scope: the whole program, a block, or a function body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scope {
+    /// Enclosing scope, `None` for the global scope.
+    pub parent: Option<ScopeId>,
+    pub span: TokenSpan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeclarationKind {
+    Variable,
+    Parameter,
+    Function,
+    Class,
+}
+
+/// Declaration of a named variable, function or class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Declaration<'a> {
+    pub name: &'a str,
+    pub kind: DeclarationKind,
+    /// Span of the name in the declaration.
+    pub span: TokenSpan,
+    /// Scope the name is declared in.
+    pub scope: ScopeId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Use of a name in an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reference<'a> {
+    pub name: &'a str,
+    pub access: Access,
+    pub span: TokenSpan,
+    /// Scope the reference is made from.
+    pub scope: ScopeId,
+    /// Declaration the name refers to, `None` if the program doesn't declare
+    /// it, e.g. for native functions.
+    pub declaration: Option<DeclarationId>,
+}
+
+/// Result of resolving a program: its scopes, declarations, and the
+/// references to them.
+///
+/// References to globals are resolved to the last declaration of the global
+/// that is defined at that point. Globals can also be used before being
+/// declared, e.g. in the body of a function, in which case the reference is
+/// resolved to the first declaration.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Resolution<'a> {
+    scopes: Vec<Scope>,
+    declarations: Vec<Declaration<'a>>,
+    references: Vec<Reference<'a>>,
+}
+
+impl<'a> Resolution<'a> {
+    /// Creates a resolution with only the global scope, spanning the source.
+    pub(crate) fn new(src: &str) -> Self {
+        Self {
+            scopes: vec![Scope {
+                parent: None,
+                span: TokenSpan::new(0, src.len()),
+            }],
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn add_scope(&mut self, scope: Scope) -> ScopeId {
+        self.scopes.push(scope);
+        self.scopes.len() - 1
+    }
+
+    pub(crate) fn add_declaration(&mut self, declaration: Declaration<'a>) -> DeclarationId {
+        self.declarations.push(declaration);
+        self.declarations.len() - 1
+    }
+
+    pub(crate) fn add_reference(&mut self, reference: Reference<'a>) {
+        self.references.push(reference);
+    }
+
+    /// Resolves the references made before any declaration of their name to
+    /// the first global declaration, if any.
+    pub(crate) fn resolve_globals(&mut self) {
+        for reference in &mut self.references {
+            if reference.declaration.is_none() {
+                reference.declaration = self.declarations.iter().position(|declaration| {
+                    declaration.scope == 0 && declaration.name == reference.name
+                });
+            }
+        }
+    }
+
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    pub fn declarations(&self) -> &[Declaration<'a>] {
+        &self.declarations
+    }
+
+    pub fn references(&self) -> &[Reference<'a>] {
+        &self.references
+    }
+
+    /// Returns the declaration of the name at the given offset of the source,
+    /// whether the name is a reference or the declaration itself.
+    pub fn definition_at(&self, offset: usize) -> Option<(DeclarationId, &Declaration<'a>)> {
+        let contains = |span: TokenSpan| span.start() <= offset && offset < span.end();
+        let id = match self.references.iter().find(|r| contains(r.span)) {
+            Some(reference) => reference.declaration?,
+            None => self.declarations.iter().position(|d| contains(d.span))?,
+        };
+        Some((id, &self.declarations[id]))
+    }
+
+    /// Returns the references to a declaration, in source order.
+    pub fn references_to(
+        &self,
+        declaration: DeclarationId,
+    ) -> impl Iterator<Item = &Reference<'a>> + '_ {
+        self.references
+            .iter()
+            .filter(move |reference| reference.declaration == Some(declaration))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::Resolver,
+        lexer::{LanguageLevel, Lexer, LexerConfig},
+        parser::Parser,
+        pretty_assertions::assert_eq,
+    };
+
+    fn resolution(input: &str) -> Resolution<'_> {
+        let arena = Box::leak(Box::default());
+        let statements = Parser::new(Lexer::new(input), arena).parse().unwrap();
+        let mut resolver = Resolver::new(input);
+        resolver.resolve(&statements).unwrap();
+        resolver.take_resolution()
+    }
+
+    #[test]
+    fn declarations_and_references() {
+        let input = "fun f(a) { { var b = a; b = clock(); } } var c = f; f(c);";
+        let resolution = resolution(input);
+
+        let declarations: Vec<_> = resolution
+            .declarations()
+            .iter()
+            .map(|d| (d.name, d.kind, d.scope))
+            .collect();
+        assert_eq!(declarations, &[
+            ("f", DeclarationKind::Function, 0),
+            ("a", DeclarationKind::Parameter, 1),
+            ("b", DeclarationKind::Variable, 2),
+            ("c", DeclarationKind::Variable, 0),
+        ]);
+        assert_eq!(resolution.scopes()[2], Scope {
+            parent: Some(1),
+            span: TokenSpan::new(11, 38),
+        });
+
+        let references: Vec<_> = resolution
+            .references()
+            .iter()
+            .map(|r| (r.name, r.access, r.declaration))
+            .collect();
+        assert_eq!(references, &[
+            ("a", Access::Read, Some(1)),
+            ("b", Access::Write, Some(2)),
+            ("clock", Access::Read, None),
+            ("f", Access::Read, Some(0)),
+            ("f", Access::Read, Some(0)),
+            ("c", Access::Read, Some(3)),
+        ]);
+
+        // From the use of `c`, or its declaration.
+        assert_eq!(resolution.definition_at(54).unwrap().0, 3);
+        assert_eq!(resolution.definition_at(45).unwrap().0, 3);
+
+        // Natives are not declared by the program.
+        assert_eq!(resolution.definition_at(28), None);
+        assert_eq!(resolution.references_to(0).count(), 2);
+    }
+
+    #[test]
+    fn updates() {
+        let input = "var a = 1; { var b = a++; --b; }";
+        let lexer = Lexer::new(input).with_config(LexerConfig {
+            level: LanguageLevel::Extended,
+            ..LexerConfig::default()
+        });
+        let arena = Box::leak(Box::default());
+        let statements = Parser::new(lexer, arena).parse().unwrap();
+        let mut resolver = Resolver::new(input);
+        resolver.resolve(&statements).unwrap();
+
+        // The variable is read, then written.
+        let references: Vec<_> = resolver
+            .take_resolution()
+            .references()
+            .iter()
+            .map(|r| (r.name, r.access))
+            .collect();
+        assert_eq!(references, &[
+            ("a", Access::Read),
+            ("a", Access::Write),
+            ("b", Access::Read),
+            ("b", Access::Write),
+        ]);
+    }
+
+    #[test]
+    fn globals() {
+        // Globals can be used before their declaration, and redeclared.
+        let input = "fun f() { return g; } var g = 1; var g = g;";
+        let resolution = resolution(input);
+        let references: Vec<_> = resolution
+            .references()
+            .iter()
+            .map(|r| r.declaration)
+            .collect();
+        assert_eq!(references, &[Some(1), Some(1)]);
+    }
+}
@@ -0,0 +1,989 @@
+use {
+    crate::{
+        resolution::{
+            Access,
+            Declaration,
+            DeclarationId,
+            DeclarationKind,
+            Reference,
+            Resolution,
+            Scope,
+            ScopeId,
+        },
+        suppression,
+        Error,
+        Warning,
+    },
+    lexer::{
+        token::{Token, TokenSpan},
+        LanguageLevel,
+    },
+    parser::{
+        constant,
+        grammar::{
+            Assign,
+            Block,
+            Call,
+            Class,
+            Expr,
+            Function,
+            If,
+            Return,
+            Stmt,
+            Super,
+            This,
+            Update,
+            Var,
+            Variable,
+            While,
+        },
+        walk::{self, Walk},
+    },
+    std::{collections::HashMap, mem},
+};
+
+/// Kind of function the resolver is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionType {
+    /// Top-level code.
+    None,
+    Function,
+    Method,
+
+    /// The `init` method of a class.
+    Initializer,
+}
+
+/// Kind of class the resolver is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClassType {
+    None,
+    Class,
+
+    /// A class with a superclass.
+    Subclass,
+}
+
+/// Warnings of the resolver which can be turned on or off. Shadowing is
+/// optional, and disabled by default; the others are enabled by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lints {
+    /// Warn when a local variable shadows a variable of an enclosing local
+    /// scope. Shadowing globals is not reported.
+    pub shadowing: bool,
+
+    /// Warn about branches never taken because their condition is constant,
+    /// e.g. the body of `while (false)`.
+    pub dead_branches: bool,
+
+    /// Warn about the statements following an unconditional `return`.
+    pub unreachable_code: bool,
+}
+
+impl Default for Lints {
+    fn default() -> Self {
+        Self {
+            shadowing: false,
+            dead_branches: true,
+            unreachable_code: true,
+        }
+    }
+}
+
+impl Lints {
+    /// Whether the warning with the given name is enabled. Warnings which are
+    /// not lints, and unknown names, count as enabled.
+    pub(crate) fn enabled(&self, name: &str) -> bool {
+        match name {
+            "shadowing" => self.shadowing,
+            "dead_branch" => self.dead_branches,
+            "unreachable_code" => self.unreachable_code,
+            _ => true,
+        }
+    }
+}
+
+/// Variable declared in a local scope.
+#[derive(Debug, Clone, Copy)]
+struct Local {
+    /// Span of the name in the declaration.
+    span: TokenSpan,
+
+    /// Whether the initializer of the variable has been resolved.
+    defined: bool,
+
+    /// Declaration of the variable in the resolution table.
+    declaration: DeclarationId,
+
+    /// Whether the variable is assigned to anywhere in its scope.
+    assigned: bool,
+
+    /// Function the variable is declared as, an index into
+    /// `Resolver::functions`.
+    function: Option<usize>,
+}
+
+/// Global variable, tracked to resolve references to it, and to check calls
+/// to global functions.
+#[derive(Debug, Default, Clone, Copy)]
+struct Global {
+    /// Number of top-level declarations of the variable.
+    declarations: usize,
+
+    /// Last declaration of the variable.
+    declared: Option<DeclarationId>,
+
+    /// Last declaration of the variable whose initializer has been resolved.
+    defined: Option<DeclarationId>,
+
+    /// Whether the variable is assigned to anywhere in the program.
+    assigned: bool,
+
+    /// Function the variable is declared as, an index into
+    /// `Resolver::functions`.
+    function: Option<usize>,
+}
+
+/// Function declaration, with the calls that resolve to it.
+///
+/// Calls are checked against the declaration once the whole scope of the
+/// function has been resolved, and only if nothing can replace the function
+/// in between.
+#[derive(Debug)]
+struct KnownFunction<'a> {
+    name: &'a str,
+    arity: usize,
+
+    /// Span of the name in the declaration.
+    span: TokenSpan,
+
+    /// Span of each call, with its number of arguments.
+    calls: Vec<(TokenSpan, usize)>,
+}
+
+/// Static analysis of a program, run between parsing and interpreting, that
+/// reports the errors that don't need the program to run to be detected.
+pub struct Resolver<'a> {
+    /// Source code, for error reporting.
+    src: &'a str,
+
+    /// Warnings to report, of the ones that can be turned on or off.
+    lints: Lints,
+
+    /// Language level the source is lexed at, to find its comments.
+    level: LanguageLevel,
+
+    /// Stack of the local scopes enclosing the current node, innermost last.
+    /// Globals are not tracked.
+    scopes: Vec<HashMap<&'a str, Local>>,
+
+    /// Global variables declared or assigned to by the program.
+    globals: HashMap<&'a str, Global>,
+
+    /// Functions declared by the program.
+    functions: Vec<KnownFunction<'a>>,
+
+    /// Table of the scopes, declarations and references of the program.
+    resolution: Resolution<'a>,
+
+    /// Innermost scope enclosing the current node, in the resolution table.
+    scope: ScopeId,
+
+    /// Kind of the innermost function enclosing the current node.
+    function: FunctionType,
+
+    /// Kind of the innermost class enclosing the current node.
+    class: ClassType,
+
+    /// Name of the innermost class enclosing the current node.
+    class_name: &'a str,
+
+    /// Errors found so far.
+    errors: Vec<Error>,
+
+    /// Warnings found so far.
+    warnings: Vec<Warning>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            lints: Lints::default(),
+            level: LanguageLevel::default(),
+            scopes: Vec::new(),
+            globals: HashMap::new(),
+            functions: Vec::new(),
+            resolution: Resolution::new(src),
+            scope: 0,
+            function: FunctionType::None,
+            class: ClassType::None,
+            class_name: "",
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Sets which of the warnings that can be turned on or off are reported.
+    pub fn with_lints(mut self, lints: Lints) -> Self {
+        self.lints = lints;
+        self
+    }
+
+    /// Sets the language level the source was parsed at, so that its comments
+    /// are found as the parser saw them.
+    pub fn with_level(mut self, level: LanguageLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Resolves a whole program, reporting all the errors found.
+    ///
+    /// Warnings are collected separately, see [`Resolver::take_warnings`].
+    /// Comments such as `// lox-ignore(shadowing)` suppress the named warnings
+    /// in the statement on the next line.
+    pub fn resolve(&mut self, statements: &[Stmt<'a>]) -> Result<(), Vec<Error>> {
+        let previous = self.warnings.len();
+        for stmt in statements {
+            self.walk_stmt(stmt);
+        }
+        let mut warnings = self.warnings.split_off(previous);
+        suppression::suppress(self.src, statements, self.lints, self.level, &mut warnings);
+        self.warnings.append(&mut warnings);
+        self.finish()
+    }
+
+    /// Resolves a single top-level expression, such as a bare expression in the
+    /// REPL.
+    pub fn resolve_expression(&mut self, expr: &Expr<'a>) -> Result<(), Vec<Error>> {
+        self.walk_expr(expr);
+        self.finish()
+    }
+
+    /// Returns the table of the scopes, declarations and references of the
+    /// programs resolved so far, e.g. for tooling to find where a name is
+    /// declared, and where it is used.
+    pub fn take_resolution(&mut self) -> Resolution<'a> {
+        let mut resolution = mem::replace(&mut self.resolution, Resolution::new(self.src));
+        resolution.resolve_globals();
+        resolution
+    }
+
+    /// Returns the warnings found so far, which don't prevent the program
+    /// from being run.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        mem::take(&mut self.warnings)
+    }
+
+    fn finish(&mut self) -> Result<(), Vec<Error>> {
+        // Only functions declared once, and never reassigned, are known to be
+        // what their name refers to.
+        let mut functions: Vec<_> = mem::take(&mut self.globals)
+            .into_values()
+            .filter(|global| global.declarations == 1 && !global.assigned)
+            .filter_map(|global| global.function)
+            .collect();
+        functions.sort_unstable();
+        for function in functions {
+            self.check_calls(function);
+        }
+        self.functions.clear();
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(mem::take(&mut self.errors))
+        }
+    }
+
+    /// Warns about the statements following an unconditional `return`.
+    fn check_reachable(&mut self, statements: &[Stmt<'a>]) {
+        if !self.lints.unreachable_code {
+            return;
+        }
+        let Some(position) = statements
+            .iter()
+            .position(|stmt| matches!(stmt, Stmt::Return(_)))
+        else {
+            return;
+        };
+        let (Some(first), Some(last)) = (statements.get(position + 1), statements.last()) else {
+            return;
+        };
+        self.warnings.push(Warning::UnreachableCode {
+            src: self.src.to_string(),
+            return_: statements[position].span().into(),
+            at: first.span().merge(last.span()).into(),
+        });
+    }
+
+    /// Returns the truthiness of a condition, if it's constant.
+    fn constant_condition(condition: &Expr<'a>) -> Option<bool> {
+        constant::evaluate(condition).map(constant::is_truthy)
+    }
+
+    fn dead_branch(&mut self, condition: &Expr<'a>, value: bool, branch: &Stmt<'a>, fix: &str) {
+        self.warnings.push(Warning::DeadBranch {
+            src: self.src.to_string(),
+            condition: condition.span().into(),
+            at: branch.span().into(),
+            value,
+            fix: fix.to_string(),
+        });
+    }
+
+    fn begin_scope(&mut self, span: TokenSpan) {
+        self.scopes.push(HashMap::new());
+        self.scope = self.resolution.add_scope(Scope {
+            parent: Some(self.scope),
+            span,
+        });
+    }
+
+    fn end_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        self.scope = self.resolution.scopes()[self.scope].parent.unwrap_or(0);
+        let mut functions: Vec<_> = scope
+            .into_values()
+            .filter(|local| !local.assigned)
+            .filter_map(|local| local.function)
+            .collect();
+        functions.sort_unstable();
+        for function in functions {
+            self.check_calls(function);
+        }
+    }
+
+    /// Reports the calls to a function with the wrong number of arguments.
+    fn check_calls(&mut self, function: usize) {
+        let function = &self.functions[function];
+        for &(span, found) in &function.calls {
+            if found != function.arity {
+                self.errors.push(Error::ArityMismatch {
+                    src: self.src.to_string(),
+                    at: span.into(),
+                    declaration: function.span.into(),
+                    name: function.name.to_string(),
+                    expected: function.arity,
+                    found,
+                });
+            }
+        }
+    }
+
+    /// Records a use of a variable, declared by the innermost local scope that
+    /// declares it, or by the global scope.
+    fn reference(&mut self, name: &Token<'a>, access: Access) {
+        let local = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name.lexeme));
+        let declaration = match local {
+            Some(local) => Some(local.declaration),
+            None => self
+                .globals
+                .get(name.lexeme)
+                .and_then(|global| global.defined),
+        };
+        self.resolution.add_reference(Reference {
+            name: name.lexeme,
+            access,
+            span: name.span,
+            scope: self.scope,
+            declaration,
+        });
+    }
+
+    /// Records that the variable visible from the current scope is assigned,
+    /// so that it's no longer known to hold the function it was declared as.
+    fn assigned(&mut self, name: &'a str) {
+        match self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+        {
+            Some(local) => local.assigned = true,
+            None => self.globals.entry(name).or_default().assigned = true,
+        }
+    }
+
+    /// Returns the function a variable refers to from the current scope, if
+    /// it's declared as one.
+    fn known_function(&self, name: &str) -> Option<usize> {
+        match self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            Some(local) => local.function,
+            None => self.globals.get(name)?.function,
+        }
+    }
+
+    /// Adds a variable to the innermost scope, as not yet initialized.
+    ///
+    /// Unlike globals, local variables can't be redeclared in the same scope.
+    fn declare(&mut self, name: &Token<'a>, kind: DeclarationKind) {
+        let declaration = self.resolution.add_declaration(Declaration {
+            name: name.lexeme,
+            kind,
+            span: name.span,
+            scope: self.scope,
+        });
+        let Some((scope, enclosing)) = self.scopes.split_last_mut() else {
+            let global = self.globals.entry(name.lexeme).or_default();
+            global.declarations += 1;
+            global.declared = Some(declaration);
+            return;
+        };
+        if self.lints.shadowing && !scope.contains_key(name.lexeme) {
+            let shadowed = enclosing
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(name.lexeme));
+            if let Some(shadowed) = shadowed {
+                self.warnings.push(Warning::Shadowing {
+                    src: self.src.to_string(),
+                    at: name.span.into(),
+                    shadowed: shadowed.span.into(),
+                    name: name.lexeme.to_string(),
+                });
+            }
+        }
+        let local = Local {
+            span: name.span,
+            defined: false,
+            declaration,
+            assigned: false,
+            function: None,
+        };
+        if let Some(previous) = scope.insert(name.lexeme, local) {
+            self.errors.push(Error::AlreadyDeclared {
+                src: self.src.to_string(),
+                at: name.span.into(),
+                previous: previous.span.into(),
+                name: name.lexeme.to_string(),
+            });
+        }
+    }
+
+    /// Marks a variable of the innermost scope as initialized.
+    fn define(&mut self, name: &Token<'a>) {
+        match self.scopes.last_mut() {
+            Some(scope) => {
+                if let Some(local) = scope.get_mut(name.lexeme) {
+                    local.defined = true;
+                }
+            }
+            None => {
+                let global = self.globals.entry(name.lexeme).or_default();
+                global.defined = global.declared;
+            }
+        }
+    }
+
+    /// Resolves the parameters and body of a function or method, in a scope of
+    /// their own.
+    fn resolve_function(&mut self, function: &Function<'a>, kind: FunctionType) {
+        let enclosing = mem::replace(&mut self.function, kind);
+        let span = function
+            .body
+            .iter()
+            .fold(function.name.span, |span, stmt| span.merge(stmt.span()));
+        self.begin_scope(span);
+        for param in function.params {
+            self.declare(param, DeclarationKind::Parameter);
+            self.define(param);
+        }
+        self.check_reachable(function.body);
+        walk::walk_function(self, function);
+        self.end_scope();
+        self.function = enclosing;
+    }
+}
+
+impl<'a> Walk<'a> for Resolver<'a> {
+    fn walk_block(&mut self, block: &Block<'a>) {
+        self.begin_scope(block.span);
+        self.check_reachable(block.statements);
+        walk::walk_block(self, block);
+        self.end_scope();
+    }
+
+    fn walk_class(&mut self, class: &Class<'a>) {
+        let kind = match class.superclass {
+            Some(_) => ClassType::Subclass,
+            None => ClassType::Class,
+        };
+        let enclosing = mem::replace(&mut self.class, kind);
+        let enclosing_name = mem::replace(&mut self.class_name, class.name.lexeme);
+        self.declare(&class.name, DeclarationKind::Class);
+        self.define(&class.name);
+        if let Some(superclass) = &class.superclass {
+            if superclass.name.lexeme == class.name.lexeme {
+                self.errors.push(Error::InheritFromSelf {
+                    src: self.src.to_string(),
+                    at: superclass.name.span.into(),
+                    name: class.name.lexeme.to_string(),
+                });
+            }
+            self.walk_variable(superclass);
+        }
+        for method in class.methods {
+            let kind = match method.name.lexeme {
+                "init" => FunctionType::Initializer,
+                _ => FunctionType::Method,
+            };
+            self.resolve_function(method, kind);
+        }
+        self.class = enclosing;
+        self.class_name = enclosing_name;
+    }
+
+    fn walk_assign(&mut self, assign: &Assign<'a>) {
+        self.reference(&assign.name, Access::Write);
+        self.walk_expr(assign.value);
+        self.assigned(assign.name.lexeme);
+    }
+
+    fn walk_call(&mut self, call: &Call<'a>) {
+        if let Expr::Variable(callee) = call.callee {
+            if let Some(function) = self.known_function(callee.name.lexeme) {
+                let span = call.callee.span().merge(call.span);
+                self.functions[function]
+                    .calls
+                    .push((span, call.arguments.len()));
+            }
+        }
+        walk::walk_call(self, call);
+    }
+
+    // The name is defined before resolving the body, so that functions can
+    // refer to themselves recursively.
+    fn walk_function(&mut self, function: &Function<'a>) {
+        let name = function.name.lexeme;
+        self.declare(&function.name, DeclarationKind::Function);
+        self.define(&function.name);
+        let known = Some(self.functions.len());
+        self.functions.push(KnownFunction {
+            name,
+            arity: function.params.len(),
+            span: function.name.span,
+            calls: Vec::new(),
+        });
+        match self.scopes.last_mut() {
+            Some(scope) => scope.get_mut(name).expect("declared").function = known,
+            None => self.globals.get_mut(name).expect("declared").function = known,
+        }
+        self.resolve_function(function, FunctionType::Function);
+    }
+
+    fn walk_if(&mut self, if_: &If<'a>) {
+        let condition = if self.lints.dead_branches {
+            Self::constant_condition(&if_.condition)
+        } else {
+            None
+        };
+        match (condition, if_.else_branch) {
+            (Some(true), Some(else_branch)) => self.dead_branch(
+                &if_.condition,
+                true,
+                else_branch,
+                "keep only the then branch, without the if",
+            ),
+            (Some(false), Some(_)) => self.dead_branch(
+                &if_.condition,
+                false,
+                if_.then_branch,
+                "keep only the else branch, without the if",
+            ),
+            (Some(false), None) => self.dead_branch(
+                &if_.condition,
+                false,
+                if_.then_branch,
+                "remove the if statement",
+            ),
+            _ => {}
+        }
+        walk::walk_if(self, if_);
+    }
+
+    fn walk_return(&mut self, return_: &Return<'a>) {
+        if self.function == FunctionType::None {
+            self.errors.push(Error::ReturnOutsideFunction {
+                src: self.src.to_string(),
+                at: return_.span.into(),
+            });
+        }
+        if let Some(value) = &return_.value {
+            if self.function == FunctionType::Initializer {
+                self.errors.push(Error::ReturnValueFromInitializer {
+                    src: self.src.to_string(),
+                    at: value.span().into(),
+                });
+            }
+            self.walk_expr(value);
+        }
+    }
+
+    fn walk_var(&mut self, var: &Var<'a>) {
+        self.declare(&var.name, DeclarationKind::Variable);
+        if let Some(initializer) = &var.initializer {
+            self.walk_expr(initializer);
+        }
+        self.define(&var.name);
+    }
+
+    fn walk_super(&mut self, super_: &Super<'a>) {
+        let error = match self.class {
+            ClassType::None => Error::SuperOutsideClass {
+                src: self.src.to_string(),
+                at: super_.span.into(),
+            },
+            ClassType::Class => Error::SuperWithoutSuperclass {
+                src: self.src.to_string(),
+                at: super_.span.into(),
+                class: self.class_name.to_string(),
+            },
+            ClassType::Subclass => return,
+        };
+        self.errors.push(error);
+    }
+
+    fn walk_this(&mut self, this: &This) {
+        if self.class == ClassType::None {
+            self.errors.push(Error::ThisOutsideClass {
+                src: self.src.to_string(),
+                at: this.span.into(),
+            });
+        }
+    }
+
+    // A loop with an always true condition is the usual infinite loop, so only
+    // a loop that never runs is reported.
+    fn walk_while(&mut self, while_: &While<'a>) {
+        if self.lints.dead_branches && Self::constant_condition(&while_.condition) == Some(false) {
+            self.dead_branch(&while_.condition, false, while_.body, "remove the loop");
+        }
+        self.walk_expr(&while_.condition);
+        self.walk_stmt(while_.body);
+    }
+
+    fn walk_update(&mut self, update: &Update<'a>) {
+        match update.target {
+            // Read, then written.
+            Expr::Variable(variable) => {
+                self.walk_variable(variable);
+                self.reference(&variable.name, Access::Write);
+                self.assigned(variable.name.lexeme);
+            }
+            target => self.walk_expr(target),
+        }
+    }
+
+    fn walk_variable(&mut self, variable: &Variable<'a>) {
+        let name = &variable.name;
+        self.reference(name, Access::Read);
+        let scope = self.scopes.last();
+        if scope
+            .and_then(|scope| scope.get(name.lexeme))
+            .is_some_and(|local| !local.defined)
+        {
+            self.errors.push(Error::ReadInOwnInitializer {
+                src: self.src.to_string(),
+                at: name.span.into(),
+                name: name.lexeme.to_string(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        lexer::Lexer,
+        parser::{Arena, Parser},
+        pretty_assertions::assert_eq,
+    };
+
+    /// Parses and resolves a program, returning the reported errors and
+    /// warnings.
+    fn resolve_all(input: &str) -> (Vec<Error>, Vec<Warning>) {
+        let arena = Arena::new();
+        let statements = Parser::new(Lexer::new(input), &arena).parse().unwrap();
+        let mut resolver = Resolver::new(input);
+        let errors = resolver.resolve(&statements).err().unwrap_or_default();
+        (errors, resolver.take_warnings())
+    }
+
+    fn resolve(input: &str) -> Vec<Error> {
+        resolve_all(input).0
+    }
+
+    fn warnings(input: &str) -> Vec<Warning> {
+        resolve_all(input).1
+    }
+
+    fn lint(input: &str, lints: Lints) -> Vec<Warning> {
+        let arena = Arena::new();
+        let statements = Parser::new(Lexer::new(input), &arena).parse().unwrap();
+        let mut resolver = Resolver::new(input).with_lints(lints);
+        resolver.resolve(&statements).unwrap();
+        resolver.take_warnings()
+    }
+
+    #[test]
+    fn read_in_own_initializer() {
+        let input = "{ var a = 1; { var a = a + 1; } }";
+        assert_eq!(resolve(input), &[Error::ReadInOwnInitializer {
+            src: input.to_string(),
+            at: (23, 1).into(),
+            name: "a".to_string(),
+        }]);
+
+        // Reading an outer variable, or a global, is fine.
+        assert_eq!(resolve("{ var a = 1; { var b = a; } }"), &[]);
+        assert_eq!(resolve("var a = 1; var a = a;"), &[]);
+        assert_eq!(resolve("{ var a; a = 1; print a; }"), &[]);
+
+        // Errors are reported for all offending uses.
+        let input = "fun f() { var a = a; } class C { m() { var x = x; } }";
+        assert_eq!(resolve(input).len(), 2);
+    }
+
+    #[test]
+    fn already_declared() {
+        let input = "{ var a = 1; var a = 2; }";
+        assert_eq!(resolve(input), &[Error::AlreadyDeclared {
+            src: input.to_string(),
+            at: (17, 1).into(),
+            previous: (6, 1).into(),
+            name: "a".to_string(),
+        }]);
+
+        assert_eq!(resolve("fun f(a, b, a) {}").len(), 1);
+        assert_eq!(resolve("fun f(a) { var a; }").len(), 1);
+        assert_eq!(resolve("{ fun f() {} class f {} }").len(), 1);
+
+        // Globals can be redeclared, and inner scopes can shadow outer ones.
+        assert_eq!(resolve("var a = 1; var a = 2;"), &[]);
+        assert_eq!(
+            resolve("{ var a = 1; { var a = 2; } } fun f(a) { { var a; } }"),
+            &[]
+        );
+    }
+
+    #[test]
+    fn shadowing() {
+        let lints = Lints {
+            shadowing: true,
+            ..Lints::default()
+        };
+        let input = "{ var a = 1; { var b; fun f(a) {} } }";
+        assert_eq!(lint(input, lints), &[Warning::Shadowing {
+            src: input.to_string(),
+            at: (28, 1).into(),
+            shadowed: (6, 1).into(),
+            name: "a".to_string(),
+        }]);
+
+        // The innermost shadowed variable is labeled.
+        let input = "{ var a; { var a; { var a; } } }";
+        let warnings = lint(input, lints);
+        assert_eq!(warnings.len(), 2);
+        assert!(matches!(
+            warnings[1],
+            Warning::Shadowing { shadowed, .. } if shadowed == (15, 1).into()
+        ));
+
+        // Globals are not tracked, and the lint is disabled by default.
+        assert_eq!(lint("var a; { var a; }", lints), &[]);
+        assert_eq!(lint("{ var a; { var a; } }", Lints::default()), &[]);
+    }
+
+    #[test]
+    fn arity_mismatch() {
+        let input = "fun f(a, b) {} f(1);";
+        assert_eq!(resolve(input), &[Error::ArityMismatch {
+            src: input.to_string(),
+            at: (15, 4).into(),
+            declaration: (4, 1).into(),
+            name: "f".to_string(),
+            expected: 2,
+            found: 1,
+        }]);
+
+        // Local functions, and recursive calls.
+        assert_eq!(resolve("{ fun f() { f(1); } f(); f(2); }").len(), 2);
+        assert_eq!(resolve("fun f(a) { return f(a); } f(1);"), &[]);
+
+        // Nothing is reported if the function may be replaced at runtime, or
+        // when the call doesn't resolve to a declaration.
+        assert_eq!(resolve("fun f() {} f = clock; f(1);"), &[]);
+        assert_eq!(resolve("fun f() {} fun g() { f = clock; } f(1);"), &[]);
+        assert_eq!(resolve("fun f() {} var f = clock; f(1);"), &[]);
+        assert_eq!(resolve("fun f() {} { var f = clock; f(1); }"), &[]);
+        assert_eq!(resolve("fun g() { f(1); } fun f() {}"), &[]);
+        assert_eq!(resolve("{ fun f() {} { f = clock; } f(1); }"), &[]);
+    }
+
+    #[test]
+    fn this_outside_class() {
+        let input = "fun f() { return this; }";
+        assert_eq!(resolve(input), &[Error::ThisOutsideClass {
+            src: input.to_string(),
+            at: (17, 4).into(),
+        }]);
+
+        assert_eq!(resolve("print this.x;").len(), 1);
+        assert_eq!(resolve("class C { m() { fun f() { return this; } } }"), &[]);
+
+        // The class type is restored after a nested class.
+        let input = "class C { m() { class D {} return this; } } this;";
+        assert_eq!(resolve(input).len(), 1);
+
+        let input = "this";
+        let arena = Arena::new();
+        let expr = Parser::new(Lexer::new(input), &arena)
+            .parse_expression()
+            .unwrap();
+        assert_eq!(
+            Resolver::new(input).resolve_expression(&expr).unwrap_err(),
+            &[Error::ThisOutsideClass {
+                src: input.to_string(),
+                at: (0, 4).into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn invalid_super() {
+        let input = "fun f() { super.m(); }";
+        assert_eq!(resolve(input), &[Error::SuperOutsideClass {
+            src: input.to_string(),
+            at: (10, 5).into(),
+        }]);
+
+        let input = "class A { m() { super.m(); } }";
+        assert_eq!(resolve(input), &[Error::SuperWithoutSuperclass {
+            src: input.to_string(),
+            at: (16, 5).into(),
+            class: "A".to_string(),
+        }]);
+
+        assert_eq!(resolve("class B < A { m() { return super.m; } }"), &[]);
+
+        // Nested classes have their own superclass, or lack thereof.
+        let input = "class B < A { m() { class C { n() { super.n(); } } super.m(); } }";
+        assert_eq!(resolve(input).len(), 1);
+    }
+
+    #[test]
+    fn inherit_from_self() {
+        let input = "class Foo < Foo {}";
+        assert_eq!(resolve(input), &[Error::InheritFromSelf {
+            src: input.to_string(),
+            at: (12, 3).into(),
+            name: "Foo".to_string(),
+        }]);
+
+        assert_eq!(resolve("{ class Foo < Foo {} }").len(), 1);
+        assert_eq!(resolve("class Foo {} class Bar < Foo {}"), &[]);
+    }
+
+    #[test]
+    fn return_outside_function() {
+        let input = "if (true) { return 1; }";
+        assert_eq!(resolve(input), &[Error::ReturnOutsideFunction {
+            src: input.to_string(),
+            at: (12, 6).into(),
+        }]);
+
+        assert_eq!(
+            resolve("fun f() { { return; } } class C { m() { return 1; } }"),
+            &[]
+        );
+
+        // The function type is restored after a nested function.
+        let input = "fun f() { fun g() {} } return;";
+        assert_eq!(resolve(input).len(), 1);
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let input = "fun f() { print 1; return 2; print 3; g(); }";
+        assert_eq!(warnings(input), &[Warning::UnreachableCode {
+            src: input.to_string(),
+            return_: (19, 8).into(),
+            at: (29, 12).into(),
+        }]);
+
+        let input = "fun f() { if (true) { return; var a = 1; } print 2; }";
+        assert_eq!(warnings(input), &[Warning::UnreachableCode {
+            src: input.to_string(),
+            return_: (22, 6).into(),
+            at: (34, 5).into(),
+        }]);
+
+        // Only unconditional returns make code unreachable.
+        assert_eq!(warnings("fun f() { if (true) return; print 1; }"), &[]);
+        assert_eq!(warnings("fun f() { print 1; return; }"), &[]);
+        // Enabled by default, but can be disabled.
+        let lints = Lints {
+            unreachable_code: false,
+            ..Lints::default()
+        };
+        assert_eq!(lint("fun f() { return; print 1; }", lints), &[]);
+    }
+
+    #[test]
+    fn dead_branches() {
+        let input = "if (1 > 2) print 1; else print 2;";
+        assert_eq!(warnings(input), &[Warning::DeadBranch {
+            src: input.to_string(),
+            condition: (4, 5).into(),
+            at: (11, 7).into(),
+            value: false,
+            fix: "keep only the else branch, without the if".to_string(),
+        }]);
+
+        let input = "while (nil) { print 1; }";
+        assert_eq!(warnings(input), &[Warning::DeadBranch {
+            src: input.to_string(),
+            condition: (7, 3).into(),
+            at: (12, 12).into(),
+            value: false,
+            fix: "remove the loop".to_string(),
+        }]);
+
+        assert_eq!(warnings("if (true) print 1; else print 2;").len(), 1);
+        assert_eq!(warnings("if (!true) print 1;").len(), 1);
+        assert_eq!(warnings("for (; false;) print 1;").len(), 1);
+
+        // No branch is dead.
+        assert_eq!(
+            warnings("if (true) print 1; while (true) {} for (;;) {}"),
+            &[]
+        );
+        assert_eq!(warnings("if (a) print 1; while (a < 1) {}"), &[]);
+        // Enabled by default, but can be disabled.
+        let lints = Lints {
+            dead_branches: false,
+            ..Lints::default()
+        };
+        assert_eq!(lint("if (false) print 1; while (false) {}", lints), &[]);
+    }
+
+    #[test]
+    fn return_value_from_initializer() {
+        let input = "class C { init() { if (true) return; return 42; } }";
+        assert_eq!(resolve(input), &[Error::ReturnValueFromInitializer {
+            src: input.to_string(),
+            at: (44, 2).into(),
+        }]);
+
+        // Functions nested in an initializer may return values.
+        assert_eq!(resolve("class C { init() { fun f() { return 1; } } }"), &[]);
+    }
+}
@@ -0,0 +1,263 @@
+use {
+    crate::{resolver::Lints, Warning},
+    lexer::{
+        token::{TokenKind, TokenSpan},
+        LanguageLevel,
+        Lexer,
+        LexerConfig,
+        LineIndex,
+    },
+    parser::{
+        grammar::Stmt,
+        walk::{self, Walk},
+    },
+};
+
+/// Prefix of the comments suppressing warnings, followed by the names of the
+/// warnings in parentheses, e.g. `// lox-ignore(shadowing, dead_branch)`.
+const PREFIX: &str = "lox-ignore(";
+
+/// Name of a warning in a suppression comment.
+#[derive(Debug)]
+struct Name<'a> {
+    name: &'a str,
+    span: TokenSpan,
+    /// Whether a warning was suppressed by this name.
+    used: bool,
+}
+
+/// Comment suppressing warnings on the statement following it.
+#[derive(Debug)]
+struct Suppression<'a> {
+    names: Vec<Name<'a>>,
+    /// Span of the statement starting on the line after the comment, if any.
+    target: Option<TokenSpan>,
+}
+
+/// Drops the warnings suppressed by the comments of the source, and adds a
+/// warning for every name in a comment which suppressed nothing, unless it's
+/// a disabled lint.
+///
+/// A comment alone on its line suppresses the named warnings within the
+/// statement starting on the next line, including nested statements, e.g. the
+/// body of a function.
+pub(crate) fn suppress<'a>(
+    src: &'a str,
+    statements: &[Stmt<'a>],
+    lints: Lints,
+    level: LanguageLevel,
+    warnings: &mut Vec<Warning>,
+) {
+    let mut suppressions = suppressions(src, statements, level);
+    if suppressions.is_empty() {
+        return;
+    }
+    warnings.retain(|warning| {
+        let at = warning.at().offset();
+        let name = suppressions
+            .iter_mut()
+            .filter(|s| s.target.is_some_and(|t| t.range().contains(&at)))
+            .flat_map(|s| s.names.iter_mut())
+            .find(|name| name.name == warning.name());
+        match name {
+            Some(name) => {
+                name.used = true;
+                false
+            }
+            None => true,
+        }
+    });
+    for name in suppressions.iter().flat_map(|s| &s.names) {
+        if !name.used && lints.enabled(name.name) {
+            warnings.push(Warning::UnusedSuppression {
+                src: src.to_string(),
+                at: name.span.into(),
+                name: name.name.to_string(),
+            });
+        }
+    }
+}
+
+/// Finds the suppression comments of the source, lexed at the given level, and
+/// the statements they apply to.
+fn suppressions<'a>(
+    src: &'a str,
+    statements: &[Stmt<'a>],
+    level: LanguageLevel,
+) -> Vec<Suppression<'a>> {
+    let lines = LineIndex::new(src);
+    let mut suppressions = Vec::new();
+    // Line of the last token which isn't a comment.
+    let mut code_line = 0;
+    let config = LexerConfig {
+        level,
+        comments: true,
+    };
+    for token in Lexer::new(src).with_config(config).flatten() {
+        if token.kind != TokenKind::LineComment {
+            code_line = token.line_col.line;
+            continue;
+        }
+        if token.line_col.line == code_line {
+            continue;
+        }
+        let Some(names) = parse(token.lexeme, token.span.start()) else {
+            continue;
+        };
+        suppressions.push((token.line_col.line, names));
+    }
+    if suppressions.is_empty() {
+        return Vec::new();
+    }
+
+    let mut collector = Statements::default();
+    for stmt in statements {
+        collector.walk_stmt(stmt);
+    }
+    suppressions
+        .into_iter()
+        .map(|(line, names)| Suppression {
+            names,
+            // The earliest statement on the next line, and the outermost one
+            // if several start there, as they are collected outermost first.
+            target: collector
+                .spans
+                .iter()
+                .filter(|(start, _)| lines.line(*start) == line + 1)
+                .min_by_key(|(start, _)| *start)
+                .map(|(start, end)| TokenSpan::new(*start, *end)),
+        })
+        .collect()
+}
+
+/// Parses the names of a suppression comment starting at `offset`, e.g.
+/// `// lox-ignore(shadowing)`.
+fn parse(comment: &str, offset: usize) -> Option<Vec<Name<'_>>> {
+    let list = comment
+        .strip_prefix("//")?
+        .trim_start()
+        .strip_prefix(PREFIX)?
+        .trim_end()
+        .strip_suffix(')')?;
+    let mut start = offset + (list.as_ptr() as usize - comment.as_ptr() as usize);
+    let mut names = Vec::new();
+    for part in list.split(',') {
+        let name = part.trim();
+        let name_start = start + part.len() - part.trim_start().len();
+        if !name.is_empty() {
+            names.push(Name {
+                name,
+                span: TokenSpan::new(name_start, name_start + name.len()),
+                used: false,
+            });
+        }
+        start += part.len() + 1;
+    }
+    Some(names)
+}
+
+/// Collects the start and end of every statement, nested ones included.
+#[derive(Default)]
+struct Statements {
+    spans: Vec<(usize, usize)>,
+}
+
+impl<'a> Walk<'a> for Statements {
+    fn walk_stmt(&mut self, stmt: &Stmt<'a>) {
+        // Declarations only span their name, so they are extended to their
+        // body.
+        let span = match stmt {
+            Stmt::Function(function) => function
+                .body
+                .iter()
+                .fold(function.name.span, |span, stmt| span.merge(stmt.span())),
+            Stmt::Class(class) => class
+                .methods
+                .iter()
+                .flat_map(|method| method.body)
+                .fold(class.name.span, |span, stmt| span.merge(stmt.span())),
+            stmt => stmt.span(),
+        };
+        self.spans.push((span.start(), span.end()));
+        walk::walk_stmt(self, stmt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::Resolver,
+        parser::{Arena, Parser},
+        pretty_assertions::assert_eq,
+    };
+
+    fn lint(input: &str, lints: Lints) -> Vec<Warning> {
+        let arena = Arena::new();
+        let statements = Parser::new(Lexer::new(input), &arena).parse().unwrap();
+        let mut resolver = Resolver::new(input).with_lints(lints);
+        resolver.resolve(&statements).unwrap();
+        resolver.take_warnings()
+    }
+
+    #[test]
+    fn suppressions() {
+        let lints = Lints {
+            shadowing: true,
+            dead_branches: true,
+            unreachable_code: true,
+        };
+        let input = "
+            {
+                var a;
+                // lox-ignore(shadowing)
+                { var a; }
+                { var a; }
+            }
+            // lox-ignore(dead_branch, unreachable_code)
+            fun f() {
+                if (false) print 1;
+                return;
+                print 2;
+            }
+        ";
+        // Only the shadowing in the last block is left.
+        let warnings = lint(input, lints);
+        let last = input.rfind("var a").unwrap() + 4;
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(warnings[0], Warning::Shadowing { at, .. } if at.offset() == last));
+
+        // Names which suppressed nothing, as the comment isn't right before
+        // the statement, or as there is no such warning.
+        let input = "var a;\n// lox-ignore(dead_branch, typo)\n\nif (false) a;";
+        let warnings = lint(input, lints);
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(warnings[1..], [
+            Warning::UnusedSuppression {
+                src: input.to_string(),
+                at: (21, 11).into(),
+                name: "dead_branch".to_string(),
+            },
+            Warning::UnusedSuppression {
+                src: input.to_string(),
+                at: (34, 4).into(),
+                name: "typo".to_string(),
+            },
+        ]);
+
+        // Suppressing a disabled lint is fine.
+        let input = "// lox-ignore(shadowing)\nvar a;";
+        assert_eq!(lint(input, Lints::default()), &[]);
+        // Trailing comments suppress nothing.
+        let input = "var a; // lox-ignore(typo)\nvar b;";
+        assert_eq!(lint(input, lints), &[]);
+
+        // Comments are found as lexed at the level of the source: here in an
+        // interpolation, rather than in a classic string.
+        let input = "var s = \"${\n// lox-ignore(typo)\n1}\";";
+        assert!(super::suppressions(input, &[], LanguageLevel::Classic).is_empty());
+        let found = super::suppressions(input, &[], LanguageLevel::Extended);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].names[0].name, "typo");
+    }
+}
@@ -0,0 +1,188 @@
+use {
+    lexer::token::{Token, TokenSpan},
+    parser::{
+        grammar::{Block, Class, Function, Stmt, Var},
+        walk::{self, Walk},
+    },
+};
+
+/// Index of a symbol in a [`SymbolTable`].
+pub type SymbolId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Class,
+    Method,
+
+    /// Global variable.
+    Variable,
+}
+
+/// Named item of a program, as listed in an outline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol<'a> {
+    pub name: &'a str,
+    pub kind: SymbolKind,
+    /// Span of the name in the declaration.
+    pub span: TokenSpan,
+    /// Function or class the symbol is declared in, if any.
+    pub parent: Option<SymbolId>,
+}
+
+/// Functions, classes, methods and global variables declared by a program, in
+/// source order.
+///
+/// Local variables and parameters are left out, see
+/// [`Resolution`](crate::Resolution) for those.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SymbolTable<'a> {
+    symbols: Vec<Symbol<'a>>,
+}
+
+impl<'a> SymbolTable<'a> {
+    /// Collects the symbols of a program.
+    pub fn new(statements: &[Stmt<'a>]) -> Self {
+        let mut collector = Collector::default();
+        for stmt in statements {
+            collector.walk_stmt(stmt);
+        }
+        collector.table
+    }
+
+    pub fn symbols(&self) -> &[Symbol<'a>] {
+        &self.symbols
+    }
+
+    pub fn get(&self, id: SymbolId) -> Option<&Symbol<'a>> {
+        self.symbols.get(id)
+    }
+
+    /// Returns the symbols with the given name.
+    pub fn lookup<'t>(
+        &'t self,
+        name: &'t str,
+    ) -> impl Iterator<Item = (SymbolId, &'t Symbol<'a>)> + 't {
+        self.iter().filter(move |(_, symbol)| symbol.name == name)
+    }
+
+    /// Returns the symbols declared directly in the given one, or at top level
+    /// for `None`.
+    pub fn children(
+        &self,
+        parent: Option<SymbolId>,
+    ) -> impl Iterator<Item = (SymbolId, &Symbol<'a>)> + '_ {
+        self.iter()
+            .filter(move |(_, symbol)| symbol.parent == parent)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (SymbolId, &Symbol<'a>)> + '_ {
+        self.symbols.iter().enumerate()
+    }
+}
+
+/// Walks a program to build its symbol table.
+#[derive(Default)]
+struct Collector<'a> {
+    table: SymbolTable<'a>,
+
+    /// Innermost function or class being walked.
+    parent: Option<SymbolId>,
+
+    /// Number of blocks and functions enclosing the current node, so that only
+    /// top-level variables are collected.
+    depth: usize,
+}
+
+impl<'a> Collector<'a> {
+    fn add(&mut self, name: &Token<'a>, kind: SymbolKind) -> SymbolId {
+        self.table.symbols.push(Symbol {
+            name: name.lexeme,
+            kind,
+            span: name.span,
+            parent: self.parent,
+        });
+        self.table.symbols.len() - 1
+    }
+
+    fn function(&mut self, function: &Function<'a>, kind: SymbolKind) {
+        let id = self.add(&function.name, kind);
+        let parent = self.parent.replace(id);
+        self.depth += 1;
+        walk::walk_function(self, function);
+        self.depth -= 1;
+        self.parent = parent;
+    }
+}
+
+impl<'a> Walk<'a> for Collector<'a> {
+    fn walk_block(&mut self, block: &Block<'a>) {
+        self.depth += 1;
+        walk::walk_block(self, block);
+        self.depth -= 1;
+    }
+
+    fn walk_class(&mut self, class: &Class<'a>) {
+        let id = self.add(&class.name, SymbolKind::Class);
+        let parent = self.parent.replace(id);
+        for method in class.methods {
+            self.function(method, SymbolKind::Method);
+        }
+        self.parent = parent;
+    }
+
+    fn walk_function(&mut self, function: &Function<'a>) {
+        self.function(function, SymbolKind::Function);
+    }
+
+    fn walk_var(&mut self, var: &Var<'a>) {
+        if self.depth == 0 {
+            self.add(&var.name, SymbolKind::Variable);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        lexer::Lexer,
+        parser::{Arena, Parser},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn symbols() {
+        let input = "
+            var a = 1;
+            class B { init() { var c; } m() { fun f() {} } }
+            fun g(d) { var e; }
+            { var h; fun i() {} }
+        ";
+        let arena = Arena::new();
+        let statements = Parser::new(Lexer::new(input), &arena).parse().unwrap();
+        let table = SymbolTable::new(&statements);
+
+        let symbols: Vec<_> = table
+            .symbols()
+            .iter()
+            .map(|symbol| (symbol.name, symbol.kind, symbol.parent))
+            .collect();
+        assert_eq!(symbols, &[
+            ("a", SymbolKind::Variable, None),
+            ("B", SymbolKind::Class, None),
+            ("init", SymbolKind::Method, Some(1)),
+            ("m", SymbolKind::Method, Some(1)),
+            ("f", SymbolKind::Function, Some(3)),
+            ("g", SymbolKind::Function, None),
+            ("i", SymbolKind::Function, None),
+        ]);
+        assert_eq!(table.get(1).unwrap().span, TokenSpan::new(42, 43));
+
+        let (id, _) = table.lookup("B").next().unwrap();
+        let methods: Vec<_> = table.children(Some(id)).map(|(_, m)| m.name).collect();
+        assert_eq!(methods, &["init", "m"]);
+        assert_eq!(table.children(None).count(), 4);
+        assert_eq!(table.lookup("c").count(), 0);
+    }
+}
@@ -0,0 +1,166 @@
+use {
+    crate::error::Error,
+    miette::{Diagnostic, LabeledSpan, Report, Severity, SourceCode},
+    std::{fmt, io::Write},
+};
+
+/// Sink for the diagnostics of all phases (lexer, parser, resolver and
+/// interpreter), which reports them and counts them by severity.
+pub struct Diagnostics {
+    /// Whether warnings are promoted to errors.
+    deny_warnings: bool,
+    errors: usize,
+    warnings: usize,
+    output: Box<dyn Write>,
+}
+
+impl Diagnostics {
+    /// Creates a sink reporting to the given output, usually stderr.
+    pub fn new(output: impl Write + 'static) -> Self {
+        Self {
+            deny_warnings: false,
+            errors: 0,
+            warnings: 0,
+            output: Box::new(output),
+        }
+    }
+
+    pub fn with_deny_warnings(mut self, deny_warnings: bool) -> Self {
+        self.deny_warnings = deny_warnings;
+        self
+    }
+
+    /// Reports a diagnostic, returning its severity once warnings are
+    /// promoted, if they are denied.
+    pub fn report<E: Into<Error>>(&mut self, diagnostic: E) -> Severity {
+        let diagnostic = diagnostic.into();
+        let report = match diagnostic.severity().unwrap_or(Severity::Error) {
+            Severity::Warning if self.deny_warnings => Report::new(Denied(diagnostic)),
+            _ => Report::new(diagnostic),
+        };
+        let severity = report.severity().unwrap_or(Severity::Error);
+        match severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+            Severity::Advice => {}
+        }
+        // Nowhere left to report a failure to report.
+        let _ = writeln!(self.output, "{report:?}");
+        severity
+    }
+
+    pub fn report_all<E: Into<Error>>(&mut self, diagnostics: impl IntoIterator<Item = E>) {
+        for diagnostic in diagnostics {
+            self.report(diagnostic);
+        }
+    }
+
+    /// Reports the number of errors and warnings since the last reset, if
+    /// any.
+    pub fn report_summary(&mut self) {
+        if let Some(summary) = self.summary() {
+            let _ = writeln!(self.output, "{summary}");
+        }
+    }
+
+    /// Summary of the counts, e.g. `2 errors and 1 warning emitted`.
+    fn summary(&self) -> Option<String> {
+        let count = |n, what| match n {
+            0 => None,
+            1 => Some(format!("1 {what}")),
+            n => Some(format!("{n} {what}s")),
+        };
+        let counts = match (count(self.errors, "error"), count(self.warnings, "warning")) {
+            (Some(errors), Some(warnings)) => format!("{errors} and {warnings}"),
+            (Some(counts), None) | (None, Some(counts)) => counts,
+            (None, None) => return None,
+        };
+        Some(format!("{counts} emitted"))
+    }
+
+    /// Whether errors were reported since the last reset, including promoted
+    /// warnings.
+    pub fn has_errors(&self) -> bool {
+        self.errors > 0
+    }
+
+    /// Resets the counts, e.g. before running the next REPL input.
+    pub fn reset(&mut self) {
+        self.errors = 0;
+        self.warnings = 0;
+    }
+}
+
+/// Warning promoted to an error.
+#[derive(Debug)]
+struct Denied(Error);
+
+impl fmt::Display for Denied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for Denied {}
+
+impl Diagnostic for Denied {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.0.code()
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        Some(Severity::Error)
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.0.help()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.0.source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.0.labels()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::io};
+
+    fn warning() -> resolver::Warning {
+        resolver::Warning::UnreachableCode {
+            src: "return; 1;".to_string(),
+            return_: (0, 6).into(),
+            at: (8, 1).into(),
+        }
+    }
+
+    #[test]
+    fn severities() {
+        let mut diagnostics = Diagnostics::new(io::sink());
+        assert_eq!(diagnostics.report(warning()), Severity::Warning);
+        assert!(!diagnostics.has_errors());
+        let error = Error::Io(io::Error::other("oops"));
+        assert_eq!(diagnostics.report(error), Severity::Error);
+        assert!(diagnostics.has_errors());
+        assert_eq!(diagnostics.report(warning()), Severity::Warning);
+        assert_eq!(
+            diagnostics.summary().unwrap(),
+            "1 error and 2 warnings emitted"
+        );
+
+        diagnostics.reset();
+        assert!(!diagnostics.has_errors());
+        assert_eq!(diagnostics.summary(), None);
+    }
+
+    #[test]
+    fn deny_warnings() {
+        let mut diagnostics = Diagnostics::new(io::sink()).with_deny_warnings(true);
+        assert_eq!(diagnostics.report(warning()), Severity::Error);
+        assert!(diagnostics.has_errors());
+        assert_eq!(diagnostics.summary().unwrap(), "1 error emitted");
+    }
+}
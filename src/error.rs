@@ -5,11 +5,35 @@ use {miette::Diagnostic, thiserror::Error};
 pub enum Error {
     #[error(transparent)]
     #[diagnostic(code(jlox::io_error))]
-    IoError(#[from] std::io::Error),
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to handle Ctrl-C")]
+    #[diagnostic(code(jlox::ctrlc_error))]
+    CtrlC(#[from] ctrlc::Error),
+
+    #[error("Failed to read input")]
+    #[diagnostic(code(jlox::readline_error))]
+    Readline(#[from] rustyline::error::ReadlineError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Lexer(#[from] lexer::Error),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Parser(#[from] parser::Error),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Resolver(#[from] resolver::Error),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Warning(#[from] resolver::Warning),
 
     #[error(transparent)]
     #[diagnostic(transparent)]
-    LexerError(#[from] lexer::Error),
+    Runtime(#[from] interpreter::Error),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
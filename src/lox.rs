@@ -1,61 +1,678 @@
 use {
-    crate::error::{Error, Result},
-    lexer::Lexer,
-    std::io::Write,
+    crate::{diagnostics::Diagnostics, error::Result, repl::ReplHelper},
+    interpreter::{Interpreter, Value},
+    lexer::{token::TokenKind, LanguageLevel, Lexer, LexerConfig},
+    parser::{
+        grammar::{Stmt, Visitable},
+        printer::AstPrinter,
+        Arena,
+        Parser,
+    },
+    resolver::{Lints, Resolver, SymbolTable},
+    rustyline::{error::ReadlineError, Editor},
+    std::{
+        collections::BTreeSet,
+        process::ExitCode,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    },
 };
 
+/// Outcome of running a Lox source code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Success,
+
+    /// The source code has lexer, parser or resolver errors (or warnings, if
+    /// denied), so it wasn't run.
+    CompileError,
+
+    /// The program was aborted by a runtime error.
+    RuntimeError,
+}
+
+/// Stage of the pipeline at which [`Lox::run`] stops, printing the output of
+/// that stage unless it runs the program.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Print the tokens of the source code.
+    Tokens,
+
+    /// Print the AST, as s-expressions.
+    Ast,
+
+    /// Print the AST, as JSON.
+    AstJson,
+
+    /// Resolve and run the program.
+    #[default]
+    Run,
+}
+
+/// Exit codes of jlox, as expected by the test suite of the book.
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Success => ExitCode::SUCCESS,
+            Status::CompileError => ExitCode::from(65),
+            Status::RuntimeError => ExitCode::from(70),
+        }
+    }
+}
+
+/// Commands of the REPL, listed by `:help`.
+const HELP: &str = "\
+:help          show this help
+:tokens <code> print the tokens of the code
+:ast <code>    print the AST of the code, as s-expressions
+:level <level> set the language level, classic or extended
+:env           print the global variables and their values
+:load <file>   run a file in the session, keeping its definitions
+:reload        run the last loaded file again
+:quit          exit the REPL";
+
+/// Size of the native stack of the thread Lox runs on, see [`with_stack`].
+const STACK_SIZE: usize = 64 << 20;
+
+/// Native stack a run may use, leaving room for the frames of the REPL and of
+/// the pipeline below the interpreter.
+const STACK_LIMIT: usize = STACK_SIZE - (1 << 20);
+
+/// Maximum number of nested calls. Usual calls fit in the stack many times
+/// over, and deeply nested ones run into the stack limit first.
+const MAX_CALL_DEPTH: usize = 512;
+
+/// Runs `f` on a thread with a stack of the size [`Lox`] relies on, so that
+/// programs recursing too deeply fail with a runtime error instead of
+/// overflowing the stack.
+pub fn with_stack<T: Send>(f: impl FnOnce() -> T + Send) -> Result<T> {
+    std::thread::scope(|scope| {
+        let thread = std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, f)?;
+        Ok(thread
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+    })
+}
+
 /// Lox language interpreter.
-pub struct Lox {}
+///
+/// The source code run, and its AST, are allocated in an arena borrowed from
+/// the caller: definitions made by a run may be used by later REPL inputs, so
+/// they live as long as the session, and are freed with the arena.
+pub struct Lox<'a> {
+    /// Arena of the sources run and of their ASTs.
+    arena: &'a Arena,
+
+    /// Interpreter state, kept across runs so that the REPL remembers
+    /// definitions from previous inputs.
+    interpreter: Interpreter<'a>,
+
+    /// Raised by Ctrl-C in the REPL, to abort the current run.
+    interrupt: Arc<AtomicBool>,
+
+    /// Reports the errors and warnings of the current run.
+    diagnostics: Diagnostics,
+
+    /// Warnings of the resolver which can be turned on or off.
+    lints: Lints,
+
+    /// Options of the lexers of the source code, and of the REPL input being
+    /// highlighted.
+    lexer_config: LexerConfig,
+
+    /// Whether the REPL input is highlighted.
+    color: bool,
+
+    /// Stage at which running a program stops.
+    stage: Stage,
+
+    /// Whether memory usage statistics are printed once done.
+    mem_stats: bool,
+
+    /// File last loaded into the REPL session, to run again on `:reload`.
+    loaded: Option<String>,
+
+    /// Names declared by the programs run so far, including the ones that are
+    /// not variables, e.g. methods, for completion in the REPL.
+    declared: BTreeSet<&'a str>,
+}
+
+impl<'a> Lox<'a> {
+    /// Create a new Lox interpreter, allocating in the given arena, to be run
+    /// with [`with_stack`].
+    pub fn new(arena: &'a Arena) -> Self {
+        let interrupt = Arc::new(AtomicBool::new(false));
+        Self {
+            arena,
+            interpreter: Interpreter::new()
+                .with_max_call_depth(MAX_CALL_DEPTH)
+                .with_stack_limit(STACK_LIMIT)
+                .with_interrupt(interrupt.clone()),
+            interrupt,
+            diagnostics: Diagnostics::new(std::io::stderr()),
+            lints: Lints::default(),
+            lexer_config: LexerConfig::default(),
+            color: true,
+            stage: Stage::default(),
+            mem_stats: false,
+            loaded: None,
+            declared: BTreeSet::new(),
+        }
+    }
 
-impl Lox {
-    /// Create a new Lox interpreter.
-    pub fn new() -> Self {
-        Self {}
+    /// Promote warnings to errors, so that a program with warnings isn't run.
+    pub fn with_deny_warnings(mut self, deny_warnings: bool) -> Self {
+        self.diagnostics = self.diagnostics.with_deny_warnings(deny_warnings);
+        self
+    }
+
+    /// Sets which warnings of the resolver are reported, e.g. to enable the
+    /// ones about shadowing.
+    pub fn with_lints(mut self, lints: Lints) -> Self {
+        self.lints = lints;
+        self
+    }
+
+    /// Sets the language level of the source code, e.g. to allow the extension
+    /// operators.
+    pub fn with_level(mut self, level: LanguageLevel) -> Self {
+        self.lexer_config.level = level;
+        self
+    }
+
+    /// Sets whether the REPL input is highlighted as it is typed.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets the stage at which running a program stops, e.g. to print its AST
+    /// instead.
+    pub fn with_stage(mut self, stage: Stage) -> Self {
+        self.stage = stage;
+        self
+    }
+
+    /// Prints the memory used by the objects of the programs once done, i.e.
+    /// at the end of a script or of the REPL session.
+    pub fn with_mem_stats(mut self, mem_stats: bool) -> Self {
+        if mem_stats {
+            self.interpreter = self.interpreter.with_memory_stats();
+        }
+        self.mem_stats = mem_stats;
+        self
     }
 
     /// Run a Lox script from a file.
-    pub fn run_file(&self, filename: &str) -> Result<()> {
-        let file_contents = std::fs::read_to_string(filename).unwrap_or_else(|e| {
-            self.error(e);
-            String::new()
-        });
-        self.run(&file_contents)
+    pub fn run_file(&mut self, filename: &str) -> Result<Status> {
+        let file_contents = std::fs::read_to_string(filename)?;
+        let status = self.run(&file_contents);
+        self.diagnostics.report_summary();
+        self.report_mem_stats();
+        Ok(status)
     }
 
     /// Run a Lox REPL.
-    pub fn run_prompt(&self) -> Result<()> {
-        let input = std::io::stdin();
-        let mut stdout = std::io::stdout().lock();
+    ///
+    /// Bare expressions, with no trailing semicolon, are evaluated and their
+    /// value printed. Input with unclosed parentheses or braces, or ending
+    /// inside a string or a comment, goes on with the next lines. Ctrl-C
+    /// aborts the input being typed or run, and returns to the prompt. Tab
+    /// completes keywords and the names known to the session, and the input is
+    /// highlighted, unless disabled.
+    ///
+    /// Lines starting with `:` are commands, see `:help`.
+    pub fn run_prompt(&mut self) -> Result<()> {
+        let interrupt = self.interrupt.clone();
+        ctrlc::set_handler(move || interrupt.store(true, Ordering::Relaxed))?;
+
+        let mut editor = Editor::new()?;
+        editor.set_helper(Some(ReplHelper {
+            color: self.color,
+            ..ReplHelper::default()
+        }));
+        let mut buffer = String::new();
         loop {
-            stdout.write_all(b">>> ")?;
-            stdout.flush()?;
-            let mut buffer = String::new();
-            input.read_line(&mut buffer).unwrap();
-            if buffer.trim().is_empty() {
+            if let Some(helper) = editor.helper_mut() {
+                helper.names = self.names();
+                helper.level = self.lexer_config.level;
+            }
+            let prompt = if buffer.is_empty() { ">>> " } else { "..  " };
+            let line = match editor.readline(prompt) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => {
+                    buffer.clear();
+                    continue;
+                }
+                Err(ReadlineError::Eof) => {
+                    if !buffer.is_empty() {
+                        self.run_line(&buffer)?;
+                    }
+                    break;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if buffer.is_empty() && line.trim().is_empty() {
                 break;
             }
-            self.run(&buffer)?;
+            editor.add_history_entry(&line)?;
+            if buffer.is_empty() && line.starts_with(':') {
+                if !self.run_command(line.trim_end()) {
+                    break;
+                }
+                continue;
+            }
+            buffer.push_str(&line);
+            buffer.push('\n');
+            if is_incomplete(&buffer, self.lexer_config) {
+                continue;
+            }
+            self.run_line(&buffer)?;
+            buffer.clear();
         }
+        self.report_mem_stats();
         Ok(())
     }
 
-    /// Run a Lox source code.
-    pub fn run(&self, source: &str) -> Result<()> {
-        let lexer = Lexer::new(source);
-        for token in lexer.tokens() {
+    /// Prints the memory usage statistics, if enabled.
+    fn report_mem_stats(&mut self) {
+        if !self.mem_stats {
+            return;
+        }
+        let stats = self.interpreter.memory_stats();
+        eprintln!("Peak heap bytes:   {}", stats.peak_bytes);
+        eprintln!("Live objects:      {}", stats.live_objects);
+        eprintln!("Live environments: {}", stats.live_environments);
+        eprintln!("Interned strings:  {}", stats.interned_strings);
+    }
+
+    /// Names known to the session: global variables, including natives, and
+    /// the names declared so far.
+    fn names(&self) -> BTreeSet<&'a str> {
+        let globals = self.interpreter.globals().into_iter().map(|(name, _)| name);
+        globals.chain(self.declared.iter().copied()).collect()
+    }
+
+    /// Run a Lox source code up to the configured stage, reporting any error.
+    pub fn run(&mut self, source: &str) -> Status {
+        self.diagnostics.reset();
+        if self.stage == Stage::Tokens {
+            return self.print_tokens(source);
+        }
+        let (source, arena) = self.alloc(source);
+        let statements = match Parser::new(self.lexer(source), arena).parse() {
+            Ok(statements) => statements,
+            Err(errors) => {
+                self.diagnostics.report_all(errors);
+                return Status::CompileError;
+            }
+        };
+        match self.stage {
+            Stage::Ast => println!("{}", AstPrinter.print_program(&statements)),
+            Stage::AstJson => println!(
+                "{}",
+                serde_json::to_string_pretty(&statements).expect("the AST serializes to JSON")
+            ),
+            Stage::Run => return self.interpret(source, &statements),
+            Stage::Tokens => unreachable!("tokens are printed before parsing"),
+        }
+        Status::Success
+    }
+
+    /// Prints the tokens of the source code, reporting lexer errors.
+    fn print_tokens(&mut self, source: &str) -> Status {
+        for token in self.lexer(source).tokens() {
             match token {
-                Ok(token) => {
-                    println!("{:?}", token);
+                Ok(token) => println!("{token}"),
+                Err(e) => {
+                    self.diagnostics.report(e);
                 }
-                // Print error and continue parsing.
-                // This allows to report multiple errors in a single run.
-                Err(e) => self.error(e),
             }
         }
+        if self.diagnostics.has_errors() {
+            Status::CompileError
+        } else {
+            Status::Success
+        }
+    }
+
+    /// Run a line of REPL input, printing the value of a bare expression.
+    fn run_line(&mut self, line: &str) -> Result<()> {
+        if let Some(value) = self.evaluate_line(line) {
+            println!("{value}");
+        }
         Ok(())
     }
 
-    fn error<E: Into<Error>>(&self, e: E) {
-        eprintln!("{:?}", miette::Report::new(e.into()));
+    /// Runs a line of REPL input, returning the value of a bare expression,
+    /// and reporting any error.
+    fn evaluate_line(&mut self, line: &str) -> Option<Value<'a>> {
+        self.diagnostics.reset();
+        let (source, arena) = self.alloc(line);
+        let errors = match Parser::new(self.lexer(source), arena).parse() {
+            Ok(statements) => {
+                self.interpret(source, &statements);
+                return None;
+            }
+            Err(errors) => errors,
+        };
+
+        // Not valid statements, but it may still be a valid expression.
+        let Ok(expr) = Parser::new(self.lexer(source), arena).parse_expression() else {
+            // Errors are reported as statements, the usual REPL input.
+            self.diagnostics.report_all(errors);
+            return None;
+        };
+        if let Err(errors) = Resolver::new(source)
+            .with_level(self.lexer_config.level)
+            .resolve_expression(&expr)
+        {
+            self.diagnostics.report_all(errors);
+            return None;
+        }
+        self.interrupt.store(false, Ordering::Relaxed);
+        match self.interpreter.evaluate(source, &expr) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.diagnostics.report(e);
+                None
+            }
+        }
+    }
+
+    /// Runs a REPL command, e.g. `:tokens 1 + 2`, returning `false` if the REPL
+    /// should quit.
+    fn run_command(&mut self, line: &str) -> bool {
+        self.diagnostics.reset();
+        let (command, code) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            ":help" => println!("{HELP}"),
+            ":tokens" => {
+                self.print_tokens(code);
+            }
+            ":ast" => self.print_ast(code),
+            ":level" => match code.trim() {
+                "classic" => self.lexer_config.level = LanguageLevel::Classic,
+                "extended" => self.lexer_config.level = LanguageLevel::Extended,
+                _ => println!("Usage: :level <classic|extended>"),
+            },
+            ":env" => {
+                for (name, value) in self.interpreter.globals() {
+                    println!("{name} = {value}");
+                }
+            }
+            ":load" if !code.trim().is_empty() => self.load(code.trim().to_string()),
+            ":load" => println!("Usage: :load <file>"),
+            ":reload" => match self.loaded.clone() {
+                Some(path) => self.load(path),
+                None => println!("No file loaded yet, see :load"),
+            },
+            ":quit" => return false,
+            _ => println!("Unknown command {command}, see :help"),
+        }
+        true
+    }
+
+    /// Runs a file in the REPL session, so that its definitions are available
+    /// to the next inputs.
+    fn load(&mut self, path: String) {
+        match std::fs::read_to_string(&path) {
+            Ok(source) => {
+                self.run(&source);
+            }
+            Err(e) => {
+                self.diagnostics.report(e);
+            }
+        }
+        // Even if it failed, so that it can be fixed and reloaded.
+        self.loaded = Some(path);
+    }
+
+    /// Prints the AST of statements, or of a bare expression.
+    fn print_ast(&mut self, code: &str) {
+        let arena = Arena::new();
+        let errors = match Parser::new(self.lexer(code), &arena).parse() {
+            Ok(statements) => {
+                println!("{}", AstPrinter.print_program(&statements));
+                return;
+            }
+            Err(errors) => errors,
+        };
+        match Parser::new(self.lexer(code), &arena).parse_expression() {
+            Ok(expr) => println!("{}", expr.accept(&mut AstPrinter)),
+            Err(_) => self.diagnostics.report_all(errors),
+        }
+    }
+
+    /// Lexer of source code, at the language level of the session.
+    fn lexer<'s>(&self, source: &'s str) -> Lexer<'s> {
+        Lexer::new(source).with_config(self.lexer_config)
+    }
+
+    /// Definitions made by the source may be used by later REPL inputs, so the
+    /// source and its AST live in the arena of the session.
+    fn alloc(&self, source: &str) -> (&'a str, &'a Arena) {
+        (self.arena.alloc_str(source), self.arena)
+    }
+
+    /// Resolves and runs the statements, unless the resolver reports errors.
+    fn interpret(&mut self, source: &'a str, statements: &[Stmt<'a>]) -> Status {
+        let symbols = SymbolTable::new(statements);
+        self.declared
+            .extend(symbols.symbols().iter().map(|symbol| symbol.name));
+
+        let mut resolver = Resolver::new(source)
+            .with_lints(self.lints)
+            .with_level(self.lexer_config.level);
+        let resolved = resolver.resolve(statements);
+        self.diagnostics.report_all(resolver.take_warnings());
+        if let Err(errors) = resolved {
+            self.diagnostics.report_all(errors);
+        }
+        // Including denied warnings.
+        if self.diagnostics.has_errors() {
+            return Status::CompileError;
+        }
+
+        // Ignore Ctrl-C pressed while no code was running.
+        self.interrupt.store(false, Ordering::Relaxed);
+        match self.interpreter.interpret(source, statements) {
+            Ok(()) => Status::Success,
+            Err(e) => {
+                self.diagnostics.report(e);
+                Status::RuntimeError
+            }
+        }
+    }
+}
+
+/// Whether REPL input is incomplete: it has unclosed parentheses or braces, or
+/// ends inside a string, an interpolation or a comment.
+fn is_incomplete(source: &str, config: LexerConfig) -> bool {
+    let mut depth = 0;
+    for token in Lexer::new(source).with_config(config) {
+        match token.map(|token| token.kind) {
+            Ok(TokenKind::LeftParen | TokenKind::LeftBrace) => depth += 1,
+            Ok(TokenKind::RightParen | TokenKind::RightBrace) => depth -= 1,
+            Err(e) if e.is_unterminated() => return true,
+            _ => {}
+        }
+    }
+    depth > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::{
+            cell::RefCell,
+            fs,
+            io::{self, Write},
+            rc::Rc,
+        },
+    };
+
+    #[test]
+    fn incomplete_input() {
+        let is_incomplete = |source| is_incomplete(source, LexerConfig::default());
+        assert!(!is_incomplete("print 1;\n"));
+        assert!(is_incomplete("fun f() {\n"));
+        assert!(is_incomplete("fun f() {\n  print (1 +\n"));
+        assert!(!is_incomplete("fun f() {\n  print (1 +\n 2);\n}\n"));
+        assert!(is_incomplete("print \"multi\n"));
+        assert!(is_incomplete("print \"${ {\n"));
+        assert!(is_incomplete("/* comment\n"));
+        // Errors other than missing input are reported right away.
+        assert!(!is_incomplete("print ?;\n"));
+        assert!(!is_incomplete("}\n"));
+
+        // Interpolations are only open at the extended level.
+        let source = "print \"${ \"b\n";
+        assert!(!is_incomplete(source));
+        let extended = LexerConfig {
+            level: LanguageLevel::Extended,
+            ..LexerConfig::default()
+        };
+        assert!(super::is_incomplete(source, extended));
+    }
+
+    #[test]
+    fn stages() {
+        let arena = Arena::new();
+        // Printed without running, so `x` isn't defined.
+        let source = "var x = 1; print x;";
+        for stage in [Stage::Tokens, Stage::Ast, Stage::AstJson] {
+            let mut lox = Lox::new(&arena).with_stage(stage);
+            assert_eq!(lox.run(source), Status::Success, "{stage:?}");
+            assert!(!lox.names().contains("x"));
+        }
+        let mut lox = Lox::new(&arena);
+        assert_eq!(lox.run(source), Status::Success);
+        assert!(lox.names().contains("x"));
+
+        // Runtime errors don't happen, but compile errors are still reported,
+        // as far as the stage goes.
+        let run = |stage, source| Lox::new(&arena).with_stage(stage).run(source);
+        assert_eq!(run(Stage::Ast, "print -nil;"), Status::Success);
+        assert_eq!(run(Stage::Ast, "print;"), Status::CompileError);
+        assert_eq!(run(Stage::Tokens, "print;"), Status::Success);
+        assert_eq!(run(Stage::Tokens, "print @;"), Status::CompileError);
+    }
+
+    #[test]
+    fn language_level() {
+        let arena = Arena::new();
+        let source = "var a = 5; a++; print nil?.b;";
+        assert_eq!(Lox::new(&arena).run(source), Status::CompileError);
+        let mut lox = Lox::new(&arena).with_level(LanguageLevel::Extended);
+        assert_eq!(lox.run(source), Status::Success);
+        assert_eq!(lox.evaluate_line("a++\n"), Some(Value::Number(6.0)));
+
+        let mut lox = Lox::new(&arena);
+        lox.run_command(":level extended");
+        assert_eq!(lox.run(source), Status::Success);
+        lox.run_command(":level classic");
+        assert_eq!(lox.run(source), Status::CompileError);
+    }
+
+    /// Output shared with the test, to check the reported diagnostics.
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn echo() {
+        let output = Output::default();
+        let arena = Arena::new();
+        let mut lox = Lox::new(&arena);
+        lox.diagnostics = Diagnostics::new(output.clone());
+        assert_eq!(lox.evaluate_line("1 + 2\n"), Some(Value::Number(3.0)));
+        assert_eq!(lox.evaluate_line("var x;\n"), None);
+        assert_eq!(lox.evaluate_line("x = 5\n"), Some(Value::Number(5.0)));
+        assert_eq!(lox.evaluate_line("print x;\n"), None);
+        assert!(output.0.borrow().is_empty());
+
+        // The errors of the statements are reported, rather than the ones of
+        // the expression, e.g. "Expected expression, found 'var'".
+        assert_eq!(lox.evaluate_line("var = 1\n"), None);
+        assert!(lox.diagnostics.has_errors());
+        let output = String::from_utf8(output.0.take()).unwrap();
+        assert!(
+            output.contains("Expected variable name, found '='"),
+            "{output}"
+        );
+        assert!(!output.contains("Expected expression"), "{output}");
+
+        // As are the runtime errors of an expression.
+        assert_eq!(lox.evaluate_line("-nil\n"), None);
+        assert!(lox.diagnostics.has_errors());
+    }
+
+    #[test]
+    fn load_and_reload() {
+        let path = std::env::temp_dir().join(format!("jlox-load-{}.lox", std::process::id()));
+        fs::write(&path, "fun f() {}").unwrap();
+        let arena = Arena::new();
+        let mut lox = Lox::new(&arena);
+        lox.run_command(&format!(":load {}", path.display()));
+        assert!(lox.names().contains("f"));
+
+        fs::write(&path, "class C { m() {} }").unwrap();
+        lox.run_command(":reload");
+        fs::remove_file(&path).unwrap();
+        let names = lox.names();
+        assert!(names.contains("C") && names.contains("m") && names.contains("f"));
+    }
+
+    #[test]
+    fn deep_recursion() {
+        let heavy = |n: usize| {
+            format!(
+                "class A {{
+                    init(n) {{ this.n = n; }}
+                    go(n) {{
+                        if (n > 0) {{
+                            while (true) {{
+                                {{
+                                    var x = (((1 + 2) * (3 - 4)) / ((5 + 6) * (7 - 8)))
+                                        + (((n * 2) - (n / 2)) * 3);
+                                    if (x != nil) A(n).go(n - 1);
+                                }}
+                                return nil;
+                            }}
+                        }}
+                    }}
+                }}
+                A({n}).go({n});"
+            )
+        };
+        let run = |source: String| with_stack(|| Lox::new(&Arena::new()).run(&source)).unwrap();
+        // Past the depth a debug build used to overflow the stack of the main
+        // thread at.
+        assert_eq!(run(heavy(500)), Status::Success);
+        assert_eq!(run(heavy(MAX_CALL_DEPTH + 1)), Status::RuntimeError);
+
+        // Calls so deeply nested that the stack runs out before the call depth
+        // limit.
+        let nested = format!(
+            "fun f(n) {{ if (n > 0) return {}f(n - 1){}; }} f({MAX_CALL_DEPTH});",
+            "1 + (".repeat(200),
+            ")".repeat(200),
+        );
+        assert_eq!(run(nested), Status::RuntimeError);
     }
 }
@@ -1,7 +1,15 @@
-use std::env;
+use {
+    lexer::LanguageLevel,
+    lox::Stage,
+    parser::Arena,
+    resolver::Lints,
+    std::{env, process::ExitCode},
+};
 
+mod diagnostics;
 mod error;
 mod lox;
+mod repl;
 
 #[derive(thiserror::Error, miette::Diagnostic, Debug)]
 pub enum Error {
@@ -10,13 +18,153 @@ pub enum Error {
     InvalidArgs(String),
 }
 
-fn main() -> miette::Result<()> {
-    let lox = lox::Lox::new();
+/// Removes a flag from the arguments, returning whether it was given.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let len = args.len();
+    args.retain(|arg| arg != flag);
+    args.len() < len
+}
+
+fn main() -> miette::Result<ExitCode> {
+    lox::with_stack(run)?
+}
+
+/// Options given on the command line.
+#[derive(Debug, PartialEq, Eq)]
+struct Options {
+    deny_warnings: bool,
+    lints: Lints,
+    no_color: bool,
+    mem_stats: bool,
+    level: LanguageLevel,
+    stage: Stage,
+
+    /// Script to run, or none for the REPL.
+    script: Option<String>,
+}
+
+/// Parses the command line arguments, the first one being the program name.
+fn parse_args(mut args: Vec<String>) -> Result<Options, Error> {
+    let deny_warnings = take_flag(&mut args, "--deny-warnings");
+    let lints = Lints {
+        shadowing: take_flag(&mut args, "--warn-shadowing"),
+        dead_branches: !take_flag(&mut args, "--no-warn-dead-branches"),
+        unreachable_code: !take_flag(&mut args, "--no-warn-unreachable"),
+    };
+    let mem_stats = take_flag(&mut args, "--mem-stats");
+    let no_color = take_flag(&mut args, "--no-color");
+    let level = if take_flag(&mut args, "--extended") {
+        LanguageLevel::Extended
+    } else {
+        LanguageLevel::Classic
+    };
+    let stage = match (
+        take_flag(&mut args, "--tokens"),
+        take_flag(&mut args, "--ast"),
+        take_flag(&mut args, "--ast=json"),
+    ) {
+        (false, false, false) => Stage::Run,
+        (true, false, false) => Stage::Tokens,
+        (false, true, false) => Stage::Ast,
+        (false, false, true) => Stage::AstJson,
+        _ => return Err(usage(&args[0])),
+    };
+    let script = match args.len() {
+        // Stages are only printed for scripts.
+        1 if stage != Stage::Run => return Err(usage(&args[0])),
+        1 => None,
+        2 => args.pop(),
+        _ => return Err(usage(&args[0])),
+    };
+    Ok(Options {
+        deny_warnings,
+        lints,
+        no_color,
+        mem_stats,
+        level,
+        stage,
+        script,
+    })
+}
+
+fn run() -> miette::Result<ExitCode> {
+    let options = parse_args(env::args().collect())?;
+    let color = !options.no_color && env::var_os("NO_COLOR").is_none();
+    if !color {
+        miette::set_hook(Box::new(|_| {
+            Box::new(miette::MietteHandlerOpts::new().color(false).build())
+        }))?;
+    }
+    let arena = Arena::new();
+    let mut lox = lox::Lox::new(&arena)
+        .with_deny_warnings(options.deny_warnings)
+        .with_lints(options.lints)
+        .with_color(color)
+        .with_mem_stats(options.mem_stats)
+        .with_level(options.level)
+        .with_stage(options.stage);
+
+    match options.script {
+        Some(script) => Ok(lox.run_file(&script)?.into()),
+        None => {
+            lox.run_prompt()?;
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+fn usage(program: &str) -> Error {
+    Error::InvalidArgs(format!(
+        "Usage: {program} [--deny-warnings] [--warn-shadowing] [--no-warn-dead-branches] \
+         [--no-warn-unreachable] [--mem-stats] [--no-color] [--extended] [--tokens | \
+         --ast[=json]] [script]"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn parse(args: &str) -> Result<Options, Error> {
+        parse_args(args.split_whitespace().map(String::from).collect())
+    }
+
+    #[test]
+    fn arguments() {
+        let options = parse("jlox --ast=json --no-warn-unreachable script.lox").unwrap();
+        assert_eq!(options, Options {
+            deny_warnings: false,
+            lints: Lints {
+                unreachable_code: false,
+                ..Lints::default()
+            },
+            no_color: false,
+            mem_stats: false,
+            level: LanguageLevel::Classic,
+            stage: Stage::AstJson,
+            script: Some("script.lox".to_string()),
+        });
+        let options = parse("jlox --warn-shadowing --no-warn-dead-branches").unwrap();
+        assert_eq!(options.lints, Lints {
+            shadowing: true,
+            dead_branches: false,
+            unreachable_code: true,
+        });
+        let options = parse("jlox --no-color").unwrap();
+        assert_eq!((options.stage, options.script), (Stage::Run, None));
+        let options = parse("jlox --extended a.lox").unwrap();
+        assert_eq!(options.level, LanguageLevel::Extended);
+        assert_eq!(parse("jlox --tokens a.lox").unwrap().stage, Stage::Tokens);
 
-    let args: Vec<String> = env::args().collect();
-    match args.len() {
-        1 => lox.run_prompt().map_err(Into::into),
-        2 => lox.run_file(&args[1]).map_err(Into::into),
-        _ => Err(Error::InvalidArgs(format!("Usage: {} [script]", args[0])).into()),
+        // Several stages, stages without a script, and several scripts.
+        for args in [
+            "jlox --tokens --ast a.lox",
+            "jlox --ast --ast=json a.lox",
+            "jlox --ast",
+            "jlox --tokens",
+            "jlox a.lox b.lox",
+        ] {
+            assert!(matches!(parse(args), Err(Error::InvalidArgs(_))), "{args}");
+        }
     }
 }
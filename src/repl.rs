@@ -0,0 +1,204 @@
+use {
+    lexer::{
+        token::{Token, TokenKind, KEYWORDS},
+        LanguageLevel,
+        Lexer,
+        LexerConfig,
+    },
+    rustyline::{
+        completion::Completer,
+        highlight::{CmdKind, Highlighter},
+        hint::Hinter,
+        validate::Validator,
+        Context,
+        Helper,
+    },
+    std::{borrow::Cow, collections::BTreeSet},
+};
+
+/// ANSI escape sequences of the highlighted tokens.
+const KEYWORD: &str = "\x1b[35m";
+const NUMBER: &str = "\x1b[33m";
+const STRING: &str = "\x1b[32m";
+const COMMENT: &str = "\x1b[90m";
+const RESET: &str = "\x1b[0m";
+
+/// Line editor support for the REPL: completion of keywords and names, and
+/// syntax highlighting.
+#[derive(Debug, Default)]
+pub struct ReplHelper<'a> {
+    /// Names known to the session: global variables, and declarations made by
+    /// previous inputs, e.g. methods.
+    pub names: BTreeSet<&'a str>,
+
+    /// Whether the input is highlighted as it is typed.
+    pub color: bool,
+
+    /// Language level the input is lexed at.
+    pub level: LanguageLevel,
+}
+
+impl ReplHelper<'_> {
+    /// Returns the start of the word before `pos`, and the keywords and names
+    /// starting with it, sorted.
+    fn completions(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let start = line[..pos]
+            .trim_end_matches(|c: char| c.is_ascii_alphanumeric() || c == '_')
+            .len();
+        let word = &line[start..pos];
+        let keywords = KEYWORDS.iter().map(|(keyword, _)| *keyword);
+        let candidates: BTreeSet<_> = keywords
+            .chain(self.names.iter().copied())
+            .filter(|name| name.starts_with(word))
+            .collect();
+        (start, candidates.into_iter().map(String::from).collect())
+    }
+}
+
+impl Completer for ReplHelper<'_> {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.completions(line, pos))
+    }
+}
+
+impl Hinter for ReplHelper<'_> {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper<'_> {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        if self.color {
+            Cow::Owned(highlight(line, self.level))
+        } else {
+            Cow::Borrowed(line)
+        }
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, _kind: CmdKind) -> bool {
+        // The whole line is lexed again on every change.
+        self.color
+    }
+}
+
+/// Colors the keywords, numbers, strings and comments of a line of Lox, at
+/// the given language level.
+///
+/// Strings and comments that are not terminated yet, as the line is being
+/// typed, are colored up to the end of the line.
+fn highlight(line: &str, level: LanguageLevel) -> String {
+    let mut highlighted = String::with_capacity(line.len());
+    let mut last = 0;
+    let config = LexerConfig {
+        level,
+        comments: true,
+    };
+    for token in Lexer::new(line).with_config(config) {
+        let (at, color) = match token {
+            Ok(token) => match color(&token) {
+                Some(color) => (token.span.range(), color),
+                None => continue,
+            },
+            Err(lexer::Error::UnterminatedString { at, .. }) => (at.offset()..line.len(), STRING),
+            Err(lexer::Error::UnterminatedBlockComment { at, .. }) => {
+                (at.offset()..line.len(), COMMENT)
+            }
+            Err(_) => continue,
+        };
+        highlighted.push_str(&line[last..at.start]);
+        highlighted.push_str(color);
+        highlighted.push_str(&line[at.clone()]);
+        highlighted.push_str(RESET);
+        last = at.end;
+    }
+    highlighted.push_str(&line[last..]);
+    highlighted
+}
+
+/// Returns the color of a token, if it is highlighted.
+fn color(token: &Token<'_>) -> Option<&'static str> {
+    match token.kind {
+        TokenKind::Number => Some(NUMBER),
+        TokenKind::String
+        | TokenKind::InterpolationStart
+        | TokenKind::InterpolationMiddle
+        | TokenKind::InterpolationEnd => Some(STRING),
+        TokenKind::LineComment | TokenKind::BlockComment => Some(COMMENT),
+        kind if TokenKind::from_keyword(token.lexeme) == Some(kind) => Some(KEYWORD),
+        _ => None,
+    }
+}
+
+impl Validator for ReplHelper<'_> {}
+
+impl Helper for ReplHelper<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn completions() {
+        let helper = ReplHelper {
+            names: BTreeSet::from(["clock", "counter", "format"]),
+            ..ReplHelper::default()
+        };
+        assert_eq!(
+            helper.completions("print co", 8),
+            (6, vec!["counter".to_string()])
+        );
+        assert_eq!(
+            helper.completions("fo", 2),
+            (0, vec!["for".to_string(), "format".to_string()])
+        );
+        // At the cursor, not at the end of the line.
+        assert_eq!(helper.completions("c(1)", 1).1, &[
+            "class", "clock", "counter"
+        ]);
+        assert!(helper.completions("x.zz", 4).1.is_empty());
+    }
+
+    #[test]
+    fn highlighting() {
+        let highlight = |line| highlight(line, LanguageLevel::Classic);
+        let colored = |color: &str, text: &str| format!("{color}{text}{RESET}");
+        assert_eq!(
+            highlight("var a = 1.5; // one"),
+            format!(
+                "{} a = {}; {}",
+                colored(KEYWORD, "var"),
+                colored(NUMBER, "1.5"),
+                colored(COMMENT, "// one")
+            )
+        );
+        assert_eq!(
+            highlight("print \"a ${b} c"),
+            format!(
+                "{} {}",
+                colored(KEYWORD, "print"),
+                colored(STRING, "\"a ${b} c")
+            )
+        );
+        assert_eq!(
+            highlight("f(x) /* open"),
+            format!("f(x) {}", colored(COMMENT, "/* open"))
+        );
+
+        // Interpolated expressions are only highlighted at the extended level.
+        assert_eq!(
+            super::highlight("\"a ${1} b\"", LanguageLevel::Extended),
+            format!(
+                "{}{}{}",
+                colored(STRING, "\"a ${"),
+                colored(NUMBER, "1"),
+                colored(STRING, "} b\"")
+            )
+        );
+    }
+}